@@ -1,11 +1,57 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+use elm_core::ElmContext;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--quiet`; suppresses decorative output printed via `say!`
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// `elm run --restart-on-crash` starts counting restarts over once EVE has run this long
+const RESTART_RESET_AFTER: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+macro_rules! say_inline {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            print!($($arg)*);
+        }
+    };
+}
 
 #[derive(Parser)]
 #[command(name="elm", version, about="EVE Linux Manager (prototype CLI)")]
 struct Cli {
+    /// Suppress decorative output (errors still go to stderr)
+    #[arg(long, short = 'q', global = true)]
+    quiet: bool,
+    /// Log more to stderr: -v for progress details, -vv for debug output such as
+    /// the exact proton command and environment (RUST_LOG overrides this)
+    #[arg(long, short = 'v', global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Answer yes to confirmation prompts
+    #[arg(long, short = 'y', global = true)]
+    yes: bool,
+    /// Data directory: engines, prefixes, snapshots (default: $ELM_DATA_DIR or ~/.local/share/elm)
+    #[arg(long, global = true, value_parser = path_arg)]
+    data_dir: Option<PathBuf>,
+    /// Config directory: manifests, profiles, channels (default: $ELM_CONFIG_DIR or ~/.config/elm)
+    #[arg(long, global = true, value_parser = path_arg)]
+    config_dir: Option<PathBuf>,
+    /// Cap download speed in KB/s (default: manifest download.limit_rate, then $ELM_HTTP_LIMIT_RATE)
+    #[arg(long, global = true, value_name = "KB/s", value_parser = clap::value_parser!(u64).range(1..))]
+    limit_rate: Option<u64>,
+    /// Parallel connections for large downloads, where the server allows it (default: $ELM_HTTP_CONNECTIONS or 4)
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u64).range(1..=16))]
+    connections: Option<u64>,
     #[command(subcommand)]
     cmd: Commands,
 }
@@ -13,17 +59,23 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Launch EVE Online (auto-setup engine, prefix, and game)
+    ///
+    /// The engine is the first found of: one the profile pins (engine.ref in
+    /// its own manifests/eve-online.<profile>.* or in its overrides), its
+    /// channel's defaults.engine, the shared manifest's engine.ref, and
+    /// ge-proton-10-26.
     Run {
-        /// Profile name (default: "default")
-        #[arg(long, default_value = "default")]
-        profile: String,
+        /// Profile name (default: pick from a list when several profiles exist
+        /// and stdin is a terminal, else "default")
+        #[arg(long)]
+        profile: Option<String>,
         /// Launch on Singularity (test server)
         #[arg(long, visible_alias = "sisi")]
         singularity: bool,
-        /// Use DirectX 12 instead of DirectX 11
+        /// Use DirectX 12 instead of DirectX 11 (default: manifest's runtime.dx.preferred)
         #[arg(long)]
         dx12: bool,
-        /// Send desktop notification when EVE closes
+        /// Send desktop notifications when EVE's window appears and when EVE closes
         #[arg(long)]
         notify: bool,
         /// Enable MangoHud performance overlay (FPS, GPU, CPU stats)
@@ -32,9 +84,49 @@ enum Commands {
         /// MangoHud config (e.g., "fps,gpu_temp,cpu_temp,frametime")
         #[arg(long, default_value = "")]
         hud_config: String,
+        /// Render on a specific GPU: index from `elm doctor` or vendor (nvidia, amd, intel)
+        #[arg(long)]
+        gpu: Option<String>,
+        /// Upscale fullscreen modes with AMD FidelityFX Super Resolution
+        #[arg(long)]
+        fsr: bool,
+        /// FSR sharpening, 0 (sharpest) to 5 (softest); implies --fsr
+        #[arg(long, value_name = "0-5", value_parser = clap::value_parser!(u8).range(0..=5))]
+        fsr_strength: Option<u8>,
         /// Launch in background (for multiboxing multiple clients)
         #[arg(long, visible_alias = "bg")]
         background: bool,
+        /// Exit with EVE's own exit status (128 + signal if killed, 130 if
+        /// cancelled) instead of 1 for any failure, for scripts
+        #[arg(long, conflicts_with_all = ["background", "dry_run"])]
+        wait_exit_code: bool,
+        /// Relaunch EVE when it crashes, up to N times in a row (default 3),
+        /// waiting longer before each attempt
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "3", conflicts_with_all = ["background", "dry_run"])]
+        restart_on_crash: Option<u32>,
+        /// Launch even if the system doesn't meet the channel's Vulkan/GPU constraints
+        #[arg(long)]
+        ignore_constraints: bool,
+        /// Fail instead of creating the prefix or installing EVE when they're missing
+        #[arg(long, conflicts_with = "reinstall")]
+        no_setup: bool,
+        /// Run the EVE installer again even if the launcher is already installed
+        #[arg(long)]
+        reinstall: bool,
+        /// Minutes before a hung EVE installer is killed (1 to 1440)
+        #[arg(long, default_value = "30", value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..=1440))]
+        installer_timeout: u64,
+        /// Print the proton command and environment that would be run, without
+        /// running it (or setting anything up)
+        #[arg(long)]
+        dry_run: bool,
+        /// Apply a preset's environment for this launch only (see: elm config preset --list)
+        #[arg(long)]
+        preset: Option<String>,
+        /// Set an environment variable for this launch, over the manifest,
+        /// preset and profile (repeatable)
+        #[arg(long = "env", value_name = "KEY=VALUE", value_parser = env_arg)]
+        env: Vec<(String, String)>,
         /// Additional arguments to pass to EVE
         #[arg(long, num_args = 1..)]
         args: Vec<String>,
@@ -51,14 +143,63 @@ enum Commands {
         #[arg(long, default_value = "default")]
         profiles: String,
     },
+    /// Launch several profiles back-to-back in the background
+    Multibox {
+        /// Profiles to launch, in order
+        #[arg(required = true)]
+        profiles: Vec<String>,
+        /// Seconds to wait between launches (spares the login servers)
+        #[arg(long, default_value = "10")]
+        stagger_secs: u64,
+        /// Apply a preset's environment to every client (default: each profile's channel preset)
+        #[arg(long)]
+        preset: Option<String>,
+        /// Set an environment variable for every client, over the manifest,
+        /// preset and profile (repeatable)
+        #[arg(long = "env", value_name = "KEY=VALUE", value_parser = env_arg)]
+        env: Vec<(String, String)>,
+        /// Launch even if the system doesn't meet a channel's Vulkan/GPU constraints
+        #[arg(long)]
+        ignore_constraints: bool,
+    },
+    /// List EVE clients running in the background
+    Ps,
+    /// Stop background EVE clients by profile name or PID
+    Stop {
+        /// Profile name or PID (see: elm ps)
+        target: String,
+    },
+    /// Create an app launcher entry (and optionally a Steam library entry) for a profile
+    Shortcut {
+        /// Profile name (default: "default")
+        #[arg(long, default_value = "default")]
+        profile: String,
+        /// Also add EVE to Steam as a non-Steam game (close Steam first)
+        #[arg(long)]
+        steam: bool,
+        /// Steam account id under userdata/ (required if there are several)
+        #[arg(long)]
+        steam_user: Option<String>,
+        /// Icon file (default: ~/.local/share/icons/eve-online.png)
+        #[arg(long, value_parser = path_arg)]
+        icon: Option<PathBuf>,
+    },
     /// Show installed engines, prefixes, and snapshots
-    Status,
+    Status {
+        /// Print machine-readable JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
     /// Check system compatibility and dependencies
-    Doctor,
+    Doctor {
+        /// Print machine-readable JSON (per-check pass/fail) instead of text
+        #[arg(long)]
+        json: bool,
+    },
     /// View Wine/Proton and EVE logs
     Logs {
-        /// Log type: launcher, wine, all (default: launcher)
-        #[arg(long, default_value = "launcher")]
+        /// Log type: launcher, wine (or proton), squirrel (or installer), all
+        #[arg(long, alias = "type", default_value = "launcher")]
         log_type: String,
         /// Number of lines to show (default: 50)
         #[arg(long, short = 'n', default_value = "50")]
@@ -66,6 +207,9 @@ enum Commands {
         /// List available log files without showing content
         #[arg(long)]
         list: bool,
+        /// Keep printing new lines as they are written (like tail -f)
+        #[arg(long, short = 'f')]
+        follow: bool,
         /// Profile name (default: "default")
         #[arg(long, default_value = "default")]
         profile: String,
@@ -78,9 +222,24 @@ enum Commands {
         /// Skip automatic prefix backup before updating
         #[arg(long)]
         no_backup: bool,
-        /// Send desktop notification if update available
+        /// Send desktop notifications if an update is available and when it's installed
         #[arg(long)]
         notify: bool,
+        /// Put an Install button on the update notification and wait up to 10 minutes for it
+        #[arg(long, requires = "notify", conflicts_with = "install")]
+        offer_install: bool,
+        /// GitHub repository to pull releases from (owner/repo; default: manifest engine.repo, then Proton-GE)
+        #[arg(long)]
+        repo: Option<String>,
+        /// List recent releases instead of checking for an update
+        #[arg(long)]
+        list: bool,
+        /// Point the manifest's engine.ref at the newly installed engine
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL")]
+        set_active: bool,
+        /// Switch the manifest back to the engine used before the last update
+        #[arg(long, conflicts_with_all = ["install", "list"])]
+        rollback: bool,
     },
     /// Clean up old engines and download cache
     Clean {
@@ -90,23 +249,38 @@ enum Commands {
         /// Remove all downloaded archives
         #[arg(long)]
         downloads: bool,
-        /// Remove old engine versions (keep latest)
+        /// Remove old engine versions (keeps the latest and any in use)
         #[arg(long)]
         engines: bool,
-        /// Remove everything (downloads + old engines)
+        /// Remove Proton logs (per-profile and stray ~/steam-*.log)
+        #[arg(long)]
+        cache: bool,
+        /// Remove old snapshots, keeping the newest --keep per profile
+        #[arg(long)]
+        snapshots: bool,
+        /// Snapshots to keep per profile with --snapshots
+        #[arg(long, default_value = "3")]
+        keep: usize,
+        /// With --snapshots, only remove snapshots older than this many days
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+        /// Remove leftovers of interrupted downloads, engine installs and restores
+        #[arg(long)]
+        temp: bool,
+        /// Remove everything (downloads + old engines + cache + old snapshots + temp)
         #[arg(long)]
         all: bool,
     },
     Validate {
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         schemas: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         channel: Option<PathBuf>,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         engine: Option<PathBuf>,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         manifest: Option<PathBuf>,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         profile: Option<PathBuf>,
     },
     /// Manage EVE profiles (multiple accounts)
@@ -132,52 +306,127 @@ enum Commands {
         cmd: InstallCmd,
     },
     Launch {
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         proton_root: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         prefix: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         exe_rel: PathBuf,
+        /// Print the proton command and environment without running it
+        #[arg(long)]
+        dry_run: bool,
         #[arg(last=true)]
         args: Vec<String>,
     },
+    /// Create a prefix snapshot, or manage existing snapshots
+    #[command(args_conflicts_with_subcommands = true)]
     Snapshot {
+        #[command(subcommand)]
+        cmd: Option<SnapshotCmd>,
+        #[arg(long, value_parser = path_arg)]
+        prefix: Option<PathBuf>,
+        #[arg(long, value_parser = path_arg)]
+        snapshots: Option<PathBuf>,
         #[arg(long)]
-        prefix: PathBuf,
-        #[arg(long)]
-        snapshots: PathBuf,
-        #[arg(long)]
-        name: String,
+        name: Option<String>,
+        /// Only archive changes since this base snapshot
+        #[arg(long, value_parser = path_arg, value_name = "BASE")]
+        incremental: Option<PathBuf>,
+        /// Skip paths matching this glob, relative to the prefix (repeatable;
+        /// added to the defaults: ResFiles, logs and Crash Reports)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Archive only EVE's settings, overviews and character profiles; rolling
+        /// back merges them into the prefix instead of replacing it
+        #[arg(long, conflicts_with = "incremental")]
+        settings_only: bool,
     },
     Rollback {
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         snapshot: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         prefix: PathBuf,
+        /// Extract over the existing prefix instead of replacing it, keeping
+        /// files the snapshot doesn't contain (always on for settings-only snapshots)
+        #[arg(long)]
+        merge: bool,
+        /// Don't offer to update the prefix (wineboot -u) when the snapshot was
+        /// taken under a different engine
+        #[arg(long)]
+        no_update: bool,
+    },
+    /// Write man pages for elm and each subcommand (for packagers)
+    Man {
+        /// Directory to write the pages to (elm.1, elm-run.1, ...)
+        #[arg(value_parser = path_arg)]
+        out_dir: PathBuf,
     },
 }
 
 #[derive(Subcommand)]
 enum EngineCmd {
     Install {
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         schemas: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         engine: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         engines_dir: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         downloads_dir: PathBuf,
     },
 }
 
+#[derive(Subcommand)]
+enum SnapshotCmd {
+    /// List snapshots
+    List,
+    /// Delete a snapshot
+    Delete {
+        /// Snapshot name (with or without .tar.zst)
+        name: String,
+    },
+    /// Remove the oldest snapshots, keeping the newest N per profile
+    Prune {
+        /// Number of snapshots to keep per profile
+        #[arg(long, default_value = "3")]
+        keep: usize,
+        /// Only show what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum PrefixCmd {
     Init {
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         proton_root: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
+        prefix: PathBuf,
+    },
+    /// Check a prefix for missing registry files, drive links and Windows tree
+    Verify {
+        #[arg(long, value_parser = path_arg)]
+        prefix: PathBuf,
+    },
+    /// Stop any Wine processes still running in a prefix (wineserver -k)
+    Kill {
+        #[arg(long, value_parser = path_arg)]
         prefix: PathBuf,
+        /// Proton whose wineserver to use (default: the profile's engine)
+        #[arg(long, value_parser = path_arg)]
+        proton_root: Option<PathBuf>,
+    },
+    /// Install winetricks components (e.g. vcrun2019 corefonts) into a prefix
+    Winetricks {
+        #[arg(long, value_parser = path_arg)]
+        proton_root: PathBuf,
+        #[arg(long, value_parser = path_arg)]
+        prefix: PathBuf,
+        /// Winetricks verbs to run
+        #[arg(required = true)]
+        verbs: Vec<String>,
     },
 }
 
@@ -185,12 +434,19 @@ enum PrefixCmd {
 enum InstallCmd {
     /// Install EVE Online launcher into prefix
     Eve {
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         proton_root: PathBuf,
-        #[arg(long)]
+        #[arg(long, value_parser = path_arg)]
         prefix: PathBuf,
-        #[arg(long, default_value = "~/.local/share/elm/downloads")]
-        downloads_dir: PathBuf,
+        /// Where to cache the installer (default: <data dir>/downloads)
+        #[arg(long, value_parser = path_arg)]
+        downloads_dir: Option<PathBuf>,
+        /// Install without the installer GUI (for scripted setups)
+        #[arg(long)]
+        silent: bool,
+        /// Minutes before a hung installer is killed (1 to 1440)
+        #[arg(long, default_value = "30", value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..=1440))]
+        timeout: u64,
     },
 }
 
@@ -207,15 +463,27 @@ enum ProfileCmd {
     Delete {
         /// Profile name
         name: String,
-        /// Skip confirmation prompt
-        #[arg(long, short = 'y')]
-        yes: bool,
+        /// Delete only the prefix and leave the profile's snapshots in place
+        #[arg(long)]
+        keep_snapshots: bool,
+        /// Show what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Show profile details
     Info {
         /// Profile name
         name: String,
     },
+    /// Clear a profile's caches that EVE downloads or rebuilds again (ResFiles,
+    /// shader caches, logs), keeping the installed game
+    Trim {
+        /// Profile name
+        name: String,
+        /// Show what would be cleared without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Clone an existing profile to a new one
     Clone {
         /// Source profile name
@@ -223,6 +491,27 @@ enum ProfileCmd {
         /// New profile name
         target: String,
     },
+    /// Edit a profile's own manifest (created from the shared one on first use)
+    Config {
+        /// Profile name
+        name: String,
+    },
+    /// Export a profile (prefix and config) to a portable .elmprofile bundle
+    Export {
+        /// Profile name
+        name: String,
+        /// Bundle file to write (default: ./<name>.elmprofile)
+        #[arg(long, short, value_parser = path_arg)]
+        out: Option<PathBuf>,
+    },
+    /// Recreate a profile from an .elmprofile bundle
+    Import {
+        /// Bundle file from elm profile export
+        #[arg(value_parser = path_arg)]
+        file: PathBuf,
+        /// Profile name to import as (default: the exported profile's name)
+        name: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -237,177 +526,309 @@ enum ConfigCmd {
     Show,
     /// Edit config in default editor
     Edit,
-    /// Apply a settings preset (performance, quality, balanced)
+    /// Apply a settings preset (performance, quality, balanced, debug)
     Preset {
-        /// Preset name: performance, quality, balanced
-        name: String,
+        /// Preset name (omit to list presets)
+        name: Option<String>,
+        /// List available presets
+        #[arg(long, conflicts_with = "diff")]
+        list: bool,
+        /// Show what the preset would change without writing
+        #[arg(long)]
+        diff: bool,
+    },
+    /// Check the active manifest for errors
+    Validate,
+    /// Restore the manifest from a backup
+    Restore {
+        /// Backup file to restore (default: most recent)
+        #[arg(value_parser = path_arg)]
+        backup: Option<PathBuf>,
+        /// List available backups
+        #[arg(long)]
+        list: bool,
+    },
+    /// Upgrade manifests, profiles and channels to the current schema versions
+    Migrate {
+        /// Show what would change without writing
+        #[arg(long)]
+        dry_run: bool,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+    init_logging(cli.verbose);
+    let assume_yes = cli.yes;
+    let ctx = ElmContext::resolve(cli.data_dir, cli.config_dir);
+    let limit_rate = cli.limit_rate;
+    apply_limit_rate(limit_rate, None);
+    if let Some(connections) = cli.connections {
+        elm_core::http::set_connections(connections as usize);
+    }
 
     match cli.cmd {
-        Commands::Run { profile, singularity, dx12, notify, hud, hud_config, background, args: extra_args } => {
-            let home = std::env::var("HOME").unwrap_or_default();
-            let data_dir = PathBuf::from(format!("{home}/.local/share/elm"));
-            let config_dir = std::env::var("ELM_CONFIG_DIR")
-                .map(PathBuf::from)
-                .unwrap_or_else(|_| PathBuf::from(format!("{home}/.config/elm")));
-            let engines_dir = data_dir.join("engines");
-            let prefixes_dir = data_dir.join("prefixes");
-            let downloads_dir = data_dir.join("downloads");
-
-            // Try to load manifest from config dir, fallback to bundled
-            let manifest_path = config_dir.join("manifests/eve-online.json");
-            let manifest: Option<elm_core::config::models::ManifestV1> = if manifest_path.exists() {
-                let content = std::fs::read_to_string(&manifest_path)?;
-                Some(serde_json::from_str(&content)?)
-            } else {
-                None
+        Commands::Run {
+            profile, singularity, dx12, notify, hud, hud_config, gpu, fsr, fsr_strength, background, wait_exit_code, restart_on_crash, ignore_constraints, no_setup, reinstall,
+            installer_timeout, dry_run, preset, env, args: extra_args,
+        } => {
+            let profile = match profile {
+                Some(profile) => profile,
+                None => pick_profile(&ctx.prefixes_dir())?,
             };
-
-            // Get config from manifest or use defaults
-            let engine_id = manifest.as_ref()
-                .map(|m| m.engine.engine_ref.clone())
-                .unwrap_or_else(|| "ge-proton-10-26".to_string());
-
-            let exe_rel = manifest.as_ref()
-                .and_then(|m| m.launch.entrypoints.first())
-                .and_then(|e| e.path.clone())
-                .map(PathBuf::from)
-                .unwrap_or_else(|| PathBuf::from("drive_c/users/steamuser/AppData/Local/eve-online/eve-online.exe"));
-
-            // Build launch arguments
-            let mut launch_args: Vec<String> = Vec::new();
-
-            // Server selection (--singularity overrides config)
-            if singularity {
-                launch_args.push("/server:singularity".to_string());
-            } else {
-                // Use config default or tranquility
-                let default_server = manifest.as_ref()
-                    .and_then(|m| m.launch.entrypoints.first())
-                    .and_then(|e| e.args.as_ref())
-                    .and_then(|args| args.iter().find(|a| a.starts_with("/server:")))
-                    .cloned()
-                    .unwrap_or_else(|| "/server:tranquility".to_string());
-                launch_args.push(default_server);
-            }
-
-            // DX12 mode
-            if dx12 {
-                launch_args.push("/triPlatform:dx12".to_string());
-            }
-
-            // Add any extra user-provided arguments
-            launch_args.extend(extra_args);
-
-            let mut env_vars: HashMap<String, String> = manifest.as_ref()
-                .and_then(|m| m.env.as_ref())
-                .and_then(|e| e.base.clone())
-                .unwrap_or_else(|| [
-                    ("DXVK_ASYNC", "1"),
-                    ("PROTON_NO_ESYNC", "1"),
-                    ("PROTON_NO_FSYNC", "1"),
-                ].into_iter().map(|(k,v)| (k.to_string(), v.to_string())).collect());
-
-            // Enable VKD3D for DX12
-            if dx12 {
-                env_vars.insert("VKD3D_FEATURE_LEVEL".to_string(), "12_1".to_string());
-            }
-
-            // Enable MangoHud overlay
-            if hud {
-                env_vars.insert("MANGOHUD".to_string(), "1".to_string());
-                if !hud_config.is_empty() {
-                    env_vars.insert("MANGOHUD_CONFIG".to_string(), hud_config.clone());
-                } else {
-                    // Default config: FPS, frametime, GPU/CPU stats
-                    env_vars.insert("MANGOHUD_CONFIG".to_string(),
-                        "fps,frametime,gpu_stats,gpu_temp,cpu_stats,cpu_temp,ram,vram".to_string());
+            let data_dir = ctx.data_dir.clone();
+            let config_dir = ctx.config_dir.clone();
+            let downloads_dir = ctx.downloads_dir();
+
+            // Lookup order: the profile's own manifest, the shared one, built-in defaults
+            let manifest_path = match ctx.manifest_path(&profile) {
+                Some(path) => {
+                    say!("✓ Config: {}", path.display());
+                    path
+                }
+                None => {
+                    say!("○ Config: built-in defaults (no manifest in {})", config_dir.join("manifests").display());
+                    ctx.shared_manifest_path()
                 }
+            };
+            let LaunchPlan { manifest, engine_id, exe_rel, args: launch_args, env: mut env_vars, dx12, dx_source, gpu: gpu_choice } =
+                plan_launch(&ctx, &profile, &manifest_path, LaunchOptions {
+                    singularity, dx12, preset, env, extra_args, hud, hud_config, fsr, fsr_strength, gpu, ignore_constraints,
+                })?;
+            if let Some(kbps) = apply_limit_rate(limit_rate, manifest.as_ref()) {
+                say!("✓ Downloads limited to {} KB/s", kbps);
             }
 
-            let engine_dist = engines_dir.join(&engine_id).join("dist");
-            let prefix_dir = prefixes_dir.join(format!("eve-{}", profile));
+            let prefix_dir = ctx.profile_prefix(&profile);
 
             // Find the actual proton subdirectory (e.g., GE-Proton10-27)
-            let proton_root = if engine_dist.exists() {
-                std::fs::read_dir(&engine_dist)?
-                    .filter_map(|e| e.ok())
-                    .find(|e| e.path().join("proton").exists())
-                    .map(|e| e.path())
-                    .ok_or_else(|| anyhow::anyhow!("No proton found in {}", engine_dist.display()))?
-            } else {
-                return Err(anyhow::anyhow!("Engine not installed. Run: elm update --install"));
-            };
+            let proton_root = ctx.proton_root(&engine_id)?;
 
             // 1. Ensure engine is installed
-            if !proton_root.join("proton").exists() {
-                println!("Engine not found. Run: elm update --install");
-                return Err(anyhow::anyhow!("Engine not installed at {}", proton_root.display()));
-            }
-            println!("✓ Engine: {}", engine_id);
+            let Ok(runner) = elm_core::engine::runner::Runner::detect(&proton_root) else {
+                return Err(anyhow::anyhow!(
+                    "Engine not installed at {}. Run: elm update --install",
+                    proton_root.display()
+                ));
+            };
+            say!("✓ Engine: {} ({})", engine_id, runner.as_str());
 
             // 2. Ensure prefix is initialized
             if !prefix_dir.join("pfx/drive_c").exists() {
-                println!("Initializing prefix...");
-                elm_core::prefix::ensure_prefix_initialized(&prefix_dir, &proton_root).await?;
+                if no_setup || dry_run {
+                    return Err(anyhow::anyhow!(
+                        "Prefix {} is not initialized ({}; run without it to create it)",
+                        prefix_dir.display(),
+                        if dry_run { "--dry-run" } else { "--no-setup" }
+                    ));
+                }
+                say!("Initializing prefix...");
+                let components = manifest.as_ref().map(|m| &m.runtime.components);
+                elm_core::prefix::ensure_prefix_initialized(&prefix_dir, &proton_root, components).await?;
             }
-            println!("✓ Prefix: eve-{}", profile);
+            say!("✓ Prefix: eve-{}", profile);
 
             // 3. Ensure EVE is installed
             let eve_exe = prefix_dir.join("pfx").join(&exe_rel);
-            if !eve_exe.exists() {
-                println!("Installing EVE Online...");
-                elm_core::installer::install_eve_launcher(&prefix_dir, &proton_root, &downloads_dir).await?;
+            if (reinstall && !dry_run) || !eve_exe.exists() {
+                if no_setup || dry_run {
+                    return Err(anyhow::anyhow!(
+                        "EVE is not installed at {} ({}; run without it to install)",
+                        eve_exe.display(),
+                        if dry_run { "--dry-run" } else { "--no-setup" }
+                    ));
+                }
+                say!("{} EVE Online...", if eve_exe.exists() { "Reinstalling" } else { "Installing" });
+                say!("  Complete the installer GUI when it appears.");
+                let source = manifest.as_ref().map(|m| &m.installer.source);
+                let launcher = elm_core::installer::install_eve_launcher(
+                    &prefix_dir,
+                    &proton_root,
+                    &downloads_dir,
+                    source,
+                    &elm_core::installer::InstallOptions {
+                        silent: false,
+                        timeout: std::time::Duration::from_secs(installer_timeout * 60),
+                    },
+                )
+                .await?;
+                if !eve_exe.exists() {
+                    let found = launcher.strip_prefix(prefix_dir.join("pfx")).unwrap_or(&launcher);
+                    return Err(anyhow::anyhow!(
+                        "EVE launcher installed at {} but the manifest's entrypoint {} doesn't exist; set launch.entrypoints[0].path to \"{}\"",
+                        launcher.display(),
+                        exe_rel.display(),
+                        found.display()
+                    ));
+                }
+                say!("✓ EVE Launcher installed at {}", launcher.display());
             }
-            println!("✓ EVE ready");
+            say!("✓ EVE ready");
 
-            // 4. Launch with env from manifest
-            if manifest.is_some() {
-                println!("✓ Config loaded from {}", manifest_path.display());
+            set_proton_log_dir(&mut env_vars, &prefix_dir, !dry_run)?;
+
+            let steam_runtime = elm_core::runtime::steam_runtime::resolve(
+                manifest.as_ref().map(|m| m.runtime.steam_runtime).unwrap_or_default(),
+            );
+
+            if dry_run {
+                let spec = elm_core::runtime::launch::LaunchSpec {
+                    proton_root,
+                    prefix_dir,
+                    exe_path_in_prefix: exe_rel,
+                    args: launch_args,
+                    env: env_vars,
+                    log_path: None,
+                    steam_runtime,
+                };
+                println!("{}", elm_core::runtime::launch::build_command(&spec)?);
+                return Ok(());
             }
 
+            // Capture Proton's stdout/stderr for `elm logs --log-type proton`
+            let launch_log = elm_core::prefix::next_launch_log(&prefix_dir, 10)?;
+
+            // 4. Launch with env from manifest
             // Show launch info
             let server = if singularity { "Singularity (test)" } else { "Tranquility" };
             let dx_mode = if dx12 { "DirectX 12" } else { "DirectX 11" };
             let hud_status = if hud { ", HUD: On" } else { "" };
-            println!("✓ Server: {}, Mode: {}{}", server, dx_mode, hud_status);
+            say!("✓ Server: {}, Mode: {} ({}){}", server, dx_mode, dx_source, hud_status);
+            if let Some(g) = &gpu_choice {
+                say!("✓ GPU: [{}] {} ({})", g.index, g.device, g.pci_slot);
+            }
 
             if !launch_args.is_empty() {
-                println!("✓ Args: {}", launch_args.join(" "));
+                say!("✓ Args: {}", launch_args.join(" "));
+            }
+            if let Some(entry) = &steam_runtime {
+                say!("✓ Steam Runtime: {}", entry.parent().unwrap_or(entry).display());
+            }
+
+            if let Err(e) = elm_core::prefix::profile::record_launch(&prefix_dir, &engine_id) {
+                eprintln!("Warning: {:#} (launch not recorded)", e);
             }
 
             if background {
-                println!("Launching EVE Online (background)...");
+                say!("Launching EVE Online (background)...");
                 let spec = elm_core::runtime::launch::LaunchSpec {
-                    proton_root,
-                    prefix_dir,
+                    proton_root: proton_root.clone(),
+                    prefix_dir: prefix_dir.clone(),
                     exe_path_in_prefix: exe_rel,
                     args: launch_args,
                     env: env_vars,
+                    log_path: Some(launch_log.clone()),
+                    steam_runtime,
                 };
-                elm_core::runtime::launch::launch_background(spec)?;
-                println!("✓ EVE launched in background");
+                let pid = elm_core::runtime::launch::launch_background(spec)?;
+                elm_core::runtime::session::record(&data_dir, pid, &profile, &prefix_dir, &proton_root)?;
+                say!("✓ EVE launched in background (pid {}, stop with: elm stop {})", pid, profile);
+                say!("  Output: {}", launch_log.display());
             } else {
-                println!("Launching EVE Online...");
-                let start_time = std::time::Instant::now();
+                say!("Launching EVE Online... (output also saved to {})", launch_log.display());
 
-                let spec = elm_core::runtime::launch::LaunchSpec {
+                let mut spec = elm_core::runtime::launch::LaunchSpec {
                     proton_root,
                     prefix_dir,
                     exe_path_in_prefix: exe_rel,
                     args: launch_args,
                     env: env_vars,
+                    log_path: Some(launch_log.clone()),
+                    steam_runtime,
+                };
+                let mut restarts = 0;
+                let outcome = loop {
+                    // Tell the user once the client window is up; launcher updates can take a while
+                    let window_watch = notify.then(|| {
+                        tokio::spawn(async {
+                            loop {
+                                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                                match tokio::task::spawn_blocking(elm_core::integration::desktop::eve_window_shown).await {
+                                    Ok(Some(true)) => break,
+                                    Ok(Some(false)) => continue,
+                                    // No xdotool or no X display: no way to tell
+                                    _ => return,
+                                }
+                            }
+                            send_notification(
+                                &*elm_core::integration::notify::notifier(),
+                                "EVE Online Started",
+                                "The client window is up",
+                                "eve-online",
+                            );
+                        })
+                    });
+                    let outcome = elm_core::runtime::launch::launch(spec.clone()).await;
+                    if let Some(watch) = window_watch {
+                        watch.abort();
+                    }
+                    let outcome = outcome?;
+
+                    // First launch after an engine update: roll back if it died right away
+                    let mut rolled_back = false;
+                    if let Ok(Some(switch)) = elm_core::engine::switch::load(&data_dir) {
+                        if !switch.confirmed && switch.current == engine_id {
+                            let window = elm_core::engine::switch::ROLLBACK_WINDOW;
+                            if !outcome.success() && !outcome.cancelled && outcome.duration < window {
+                                match elm_core::engine::switch::rollback(&data_dir) {
+                                    Ok(s) => {
+                                        rolled_back = true;
+                                        eprintln!(
+                                            "⚠ EVE exited abnormally within {}s on the new engine {}; switched back to {}",
+                                            window.as_secs(), s.current, s.previous
+                                        )
+                                    }
+                                    Err(e) => eprintln!("⚠ Could not roll back engine {}: {:#}", switch.current, e),
+                                }
+                            } else {
+                                let _ = elm_core::engine::switch::confirm(&data_dir);
+                            }
+                        }
+                    }
+
+                    // --restart-on-crash: bring a crashed client back, backing off
+                    // when it keeps crashing. A clean exit or Ctrl-C ends the loop,
+                    // as does an engine rollback (the next run picks the old engine).
+                    let Some(max_restarts) = restart_on_crash else { break outcome };
+                    if outcome.success() || outcome.cancelled || rolled_back {
+                        break outcome;
+                    }
+                    if outcome.duration >= RESTART_RESET_AFTER {
+                        // It ran for a good while; this is a fresh crash, not a crash loop
+                        restarts = 0;
+                    }
+                    if restarts >= max_restarts {
+                        eprintln!("⚠ EVE crashed {} time(s) in a row; not restarting again", restarts + 1);
+                        break outcome;
+                    }
+                    restarts += 1;
+                    let delay = std::time::Duration::from_secs(10 << (restarts - 1).min(5));
+                    let status = match (outcome.exit_code, outcome.signal) {
+                        (Some(code), _) => format!("exited with code {}", code),
+                        (None, Some(sig)) => format!("was killed by signal {}", sig),
+                        (None, None) => "ended with unknown status".to_string(),
+                    };
+                    eprintln!(
+                        "⚠ EVE {} after {}s; restarting in {}s (restart {} of {}, Ctrl-C to stop)",
+                        status, outcome.duration.as_secs(), delay.as_secs(), restarts, max_restarts
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = tokio::signal::ctrl_c() => {
+                            eprintln!("Restart cancelled");
+                            break outcome;
+                        }
+                    }
+                    let launch_log = elm_core::prefix::next_launch_log(&spec.prefix_dir, 10)?;
+                    say!("Restarting EVE Online... (output also saved to {})", launch_log.display());
+                    spec.log_path = Some(launch_log);
                 };
-                let result = elm_core::runtime::launch::launch(spec).await;
 
                 // Send notification when EVE closes
                 if notify {
-                    let duration = start_time.elapsed();
+                    let duration = outcome.duration;
                     let hours = duration.as_secs() / 3600;
                     let minutes = (duration.as_secs() % 3600) / 60;
 
@@ -419,36 +840,49 @@ async fn main() -> Result<()> {
                         "< 1m".to_string()
                     };
 
-                    let (title, body, icon) = match &result {
-                        Ok(_) => (
+                    let (title, body, icon) = match (outcome.exit_code, outcome.signal) {
+                        _ if outcome.cancelled => (
+                            "EVE Online Closed",
+                            format!("Stopped after {}", time_str),
+                            "eve-online"
+                        ),
+                        (Some(0), _) => (
                             "EVE Online Closed",
                             format!("Session ended after {}", time_str),
                             "eve-online"
                         ),
-                        Err(e) => (
+                        (Some(code), _) => (
+                            "EVE Online Error",
+                            format!("Crashed after {} (exit code {})", time_str, code),
+                            "dialog-error"
+                        ),
+                        (None, Some(sig)) => (
+                            "EVE Online Killed",
+                            format!("Killed by signal {} after {}", sig, time_str),
+                            "dialog-error"
+                        ),
+                        (None, None) => (
                             "EVE Online Error",
-                            format!("Crashed after {}: {}", time_str, e),
+                            format!("Ended after {} with unknown status", time_str),
                             "dialog-error"
                         ),
                     };
 
-                    let _ = std::process::Command::new("notify-send")
-                        .args([
-                            "--app-name=ELM",
-                            &format!("--icon={}", icon),
-                            title,
-                            &body,
-                        ])
-                        .spawn();
+                    send_notification(&*elm_core::integration::notify::notifier(), title, &body, icon);
                 }
 
-                result?;
+                if wait_exit_code {
+                    let status = outcome.exit_status();
+                    if status != 0 {
+                        eprintln!("EVE exited with status {}", status);
+                    }
+                    let _ = std::io::Write::flush(&mut std::io::stdout());
+                    std::process::exit(status);
+                }
+                outcome.into_result()?;
             }
         }
         Commands::Multi { count, delay, profiles } => {
-            let home = std::env::var("HOME").unwrap_or_default();
-            let data_dir = PathBuf::from(format!("{home}/.local/share/elm"));
-            let prefixes_dir = data_dir.join("prefixes");
 
             // Parse profiles
             let profile_list: Vec<&str> = if profiles == "default" {
@@ -458,22 +892,22 @@ async fn main() -> Result<()> {
             };
 
             if profile_list.len() < count {
-                println!("Warning: Only {} profile(s) specified for {} clients", profile_list.len(), count);
+                eprintln!("Warning: Only {} profile(s) specified for {} clients", profile_list.len(), count);
             }
 
-            println!("Launching {} EVE client(s)...\n", count);
+            say!("Launching {} EVE client(s)...\n", count);
 
             for i in 0..count {
                 let profile = profile_list.get(i).unwrap_or(&"default");
-                let prefix_dir = prefixes_dir.join(format!("eve-{}", profile));
+                let prefix_dir = ctx.profile_prefix(profile);
 
                 if !prefix_dir.join("pfx/drive_c").exists() {
-                    println!("  {} [{}]: Prefix not initialized, skipping", i + 1, profile);
-                    println!("     Run: elm run --profile {}", profile);
+                    say!("  {} [{}]: Prefix not initialized, skipping", i + 1, profile);
+                    say!("     Run: elm run --profile {}", profile);
                     continue;
                 }
 
-                println!("  {} [{}]: Launching...", i + 1, profile);
+                say!("  {} [{}]: Launching...", i + 1, profile);
 
                 // Launch via elm run --background
                 let status = std::process::Command::new(std::env::current_exe()?)
@@ -481,352 +915,336 @@ async fn main() -> Result<()> {
                     .status();
 
                 match status {
-                    Ok(s) if s.success() => println!("  {} [{}]: ✓ Started", i + 1, profile),
-                    Ok(s) => println!("  {} [{}]: ✗ Failed (exit {})", i + 1, profile, s),
-                    Err(e) => println!("  {} [{}]: ✗ Error: {}", i + 1, profile, e),
+                    Ok(s) if s.success() => say!("  {} [{}]: ✓ Started", i + 1, profile),
+                    Ok(s) => eprintln!("  {} [{}]: ✗ Failed (exit {})", i + 1, profile, s),
+                    Err(e) => eprintln!("  {} [{}]: ✗ Error: {}", i + 1, profile, e),
                 }
 
                 // Delay between launches (except for last one)
                 if i < count - 1 && delay > 0 {
-                    println!("     Waiting {}s before next launch...", delay);
+                    say!("     Waiting {}s before next launch...", delay);
                     std::thread::sleep(std::time::Duration::from_secs(delay));
                 }
             }
 
-            println!("\n✓ Multi-launch complete");
+            say!("\n✓ Multi-launch complete");
         }
-        Commands::Status => {
-            let home = std::env::var("HOME").unwrap_or_default();
-            let data_dir = PathBuf::from(format!("{home}/.local/share/elm"));
-            let config_dir = PathBuf::from(format!("{home}/.config/elm"));
+        Commands::Multibox { profiles, stagger_secs, preset, env, ignore_constraints } => {
+            let data_dir = ctx.data_dir.clone();
 
-            println!("ELM Status");
-            println!("==========\n");
+            say!("Multiboxing {} profile(s), {}s apart...\n", profiles.len(), stagger_secs);
 
-            // Engines
-            println!("Engines:");
-            let engines_dir = data_dir.join("engines");
-            if engines_dir.exists() {
-                if let Ok(entries) = std::fs::read_dir(&engines_dir) {
-                    let mut found = false;
-                    for entry in entries.flatten() {
-                        if entry.path().is_dir() {
-                            let marker = entry.path().join("installed.json");
-                            let status = if marker.exists() { "✓" } else { "○" };
-                            println!("  {} {}", status, entry.file_name().to_string_lossy());
-                            found = true;
-                        }
-                    }
-                    if !found {
-                        println!("  (none)");
-                    }
+            let mut launched: Vec<(String, u32)> = Vec::new();
+            for profile in &profiles {
+                let prefix_dir = ctx.profile_prefix(profile);
+                if !prefix_dir.join("pfx/drive_c").exists() {
+                    eprintln!("  [{}] skipped: prefix not initialized (run: elm run --profile {})", profile, profile);
+                    continue;
                 }
-            } else {
-                println!("  (none)");
-            }
 
-            // Prefixes
-            println!("\nPrefixes:");
-            let prefixes_dir = data_dir.join("prefixes");
-            if prefixes_dir.exists() {
-                if let Ok(entries) = std::fs::read_dir(&prefixes_dir) {
-                    let mut found = false;
-                    for entry in entries.flatten() {
-                        if entry.path().is_dir() {
-                            let drive_c = entry.path().join("pfx/drive_c");
-                            let status = if drive_c.exists() { "✓" } else { "○" };
-                            // Get size
-                            let size = dir_size(&entry.path()).unwrap_or(0);
-                            println!("  {} {} ({:.1} GB)", status, entry.file_name().to_string_lossy(), size as f64 / 1_073_741_824.0);
-                            found = true;
-                        }
-                    }
-                    if !found {
-                        println!("  (none)");
+                // Per-profile setup problems skip that profile rather than the whole batch
+                let spec = (|| -> Result<elm_core::runtime::launch::LaunchSpec> {
+                    let manifest_path = ctx.manifest_path(profile).unwrap_or_else(|| ctx.shared_manifest_path());
+                    let LaunchPlan { manifest, engine_id, exe_rel, args, mut env, .. } = plan_launch(&ctx, profile, &manifest_path, LaunchOptions {
+                        preset: preset.clone(),
+                        env: env.clone(),
+                        ignore_constraints,
+                        ..Default::default()
+                    })?;
+                    let proton_root = ctx.proton_root(&engine_id)?;
+                    set_proton_log_dir(&mut env, &prefix_dir, true)?;
+
+                    Ok(elm_core::runtime::launch::LaunchSpec {
+                        proton_root,
+                        prefix_dir: prefix_dir.clone(),
+                        exe_path_in_prefix: exe_rel,
+                        args,
+                        env,
+                        log_path: Some(elm_core::prefix::next_launch_log(&prefix_dir, 10)?),
+                        steam_runtime: elm_core::runtime::steam_runtime::resolve(
+                            manifest.as_ref().map(|m| m.runtime.steam_runtime).unwrap_or_default(),
+                        ),
+                    })
+                })();
+
+                let spec = match spec {
+                    Ok(spec) => spec,
+                    Err(e) => {
+                        eprintln!("  [{}] skipped: {:#}", profile, e);
+                        continue;
                     }
-                }
-            } else {
-                println!("  (none)");
-            }
+                };
 
-            // Snapshots
-            println!("\nSnapshots:");
-            let snapshots_dir = data_dir.join("snapshots");
-            if snapshots_dir.exists() {
-                if let Ok(entries) = std::fs::read_dir(&snapshots_dir) {
-                    let mut found = false;
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.extension().map(|e| e == "zst").unwrap_or(false) {
-                            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-                            println!("  {} ({:.1} GB)", path.file_name().unwrap().to_string_lossy(), size as f64 / 1_073_741_824.0);
-                            found = true;
-                        }
-                    }
-                    if !found {
-                        println!("  (none)");
+                if !launched.is_empty() && stagger_secs > 0 {
+                    say!("  Waiting {}s...", stagger_secs);
+                    tokio::time::sleep(std::time::Duration::from_secs(stagger_secs)).await;
+                }
+                let proton_root = spec.proton_root.clone();
+                match elm_core::runtime::launch::launch_background(spec) {
+                    Ok(pid) => {
+                        elm_core::runtime::session::record(&data_dir, pid, profile, &prefix_dir, &proton_root)?;
+                        say!("  [{}] ✓ Started (pid {})", profile, pid);
+                        launched.push((profile.clone(), pid));
                     }
+                    Err(e) => eprintln!("  [{}] ✗ {:#}", profile, e),
                 }
-            } else {
-                println!("  (none)");
             }
 
-            // Config
-            println!("\nConfig:");
-            let manifest_path = config_dir.join("manifests/eve-online.json");
-            if manifest_path.exists() {
-                println!("  ✓ {}", manifest_path.display());
-            } else {
-                println!("  (no custom config)");
+            if launched.is_empty() {
+                return Err(anyhow::anyhow!("No clients launched"));
+            }
+            say!("\n{} of {} client(s) running:", launched.len(), profiles.len());
+            for (profile, pid) in &launched {
+                println!("{}\t{}", pid, profile);
             }
-
-            println!("\nPaths:");
-            println!("  Data:   {}", data_dir.display());
-            println!("  Config: {}", config_dir.display());
         }
-        Commands::Doctor => {
-            println!("ELM Doctor");
-            println!("==========\n");
-
-            let mut issues = 0;
-
-            // Check Vulkan
-            print!("Vulkan: ");
-            let vulkan_ok = std::process::Command::new("vulkaninfo")
-                .arg("--summary")
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false);
-            if vulkan_ok {
-                println!("✓ available");
-            } else {
-                println!("✗ not found (install vulkan-tools)");
-                issues += 1;
+        Commands::Ps => {
+            let data_dir = ctx.data_dir.clone();
+            let sessions = elm_core::runtime::session::live_sessions(&data_dir)?;
+            if sessions.is_empty() {
+                say!("No background EVE clients running");
+                return Ok(());
             }
-
-            // Check GPU
-            print!("GPU:    ");
-            let gpu_info = std::process::Command::new("sh")
-                .args(["-c", "lspci | grep -i vga | head -1"])
-                .output()
-                .ok()
-                .and_then(|o| String::from_utf8(o.stdout).ok())
-                .unwrap_or_default();
-            if !gpu_info.is_empty() {
-                let gpu = gpu_info.split(':').last().unwrap_or(&gpu_info).trim();
-                println!("✓ {}", gpu);
-            } else {
-                println!("? unknown");
-            }
-
-            // Check driver
-            print!("Driver: ");
-            let driver = if std::path::Path::new("/proc/driver/nvidia/version").exists() {
-                let ver = std::fs::read_to_string("/proc/driver/nvidia/version")
-                    .unwrap_or_default()
-                    .lines()
-                    .next()
-                    .unwrap_or("")
-                    .to_string();
-                format!("NVIDIA {}", ver.split_whitespace().nth(7).unwrap_or(""))
-            } else {
-                std::process::Command::new("sh")
-                    .args(["-c", "glxinfo 2>/dev/null | grep 'OpenGL version' | head -1"])
-                    .output()
-                    .ok()
-                    .and_then(|o| String::from_utf8(o.stdout).ok())
-                    .map(|s| s.trim().to_string())
-                    .unwrap_or_else(|| "unknown".to_string())
-            };
-            if driver.contains("unknown") {
-                println!("? {}", driver);
-            } else {
-                println!("✓ {}", driver.chars().take(50).collect::<String>());
+            println!("{:<8} {:<16} {:>10}", "PID", "PROFILE", "UPTIME");
+            for s in &sessions {
+                let started = std::time::UNIX_EPOCH + std::time::Duration::from_secs(s.started);
+                println!("{:<8} {:<16} {:>10}", s.pid, s.profile, format_age(started).trim_end_matches(" ago"));
             }
-
-            // Check Steam
-            print!("Steam:  ");
-            let home = std::env::var("HOME").unwrap_or_default();
-            let steam_path = format!("{home}/.steam/steam");
-            if std::path::Path::new(&steam_path).exists() {
-                println!("✓ {}", steam_path);
-            } else {
-                println!("✗ not found at {}", steam_path);
-                issues += 1;
+        }
+        Commands::Stop { target } => {
+            let data_dir = ctx.data_dir.clone();
+            let sessions = elm_core::runtime::session::find(&data_dir, &target)?;
+            if sessions.is_empty() {
+                return Err(anyhow::anyhow!("No running session matches '{}' (see: elm ps)", target));
             }
+            for s in &sessions {
+                elm_core::runtime::session::stop(&data_dir, s)?;
+                say!("✓ Stopped {} (pid {})", s.profile, s.pid);
+            }
+        }
+        Commands::Shortcut { profile, steam, steam_user, icon } => {
+            use elm_core::integration::{desktop, steam as steam_shortcuts};
 
-            // Check Python3
-            print!("Python: ");
-            let python_ver = std::process::Command::new("python3")
-                .arg("--version")
-                .output()
-                .ok()
-                .and_then(|o| String::from_utf8(o.stdout).ok())
-                .unwrap_or_default();
-            if !python_ver.is_empty() {
-                println!("✓ {}", python_ver.trim());
-            } else {
-                println!("✗ python3 not found");
-                issues += 1;
-            }
-
-            // Check MangoHud (optional)
-            print!("MangoHud: ");
-            let mangohud_ok = std::process::Command::new("mangohud")
-                .arg("--version")
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false);
-            if mangohud_ok {
-                println!("✓ available (use --hud to enable)");
-            } else {
-                println!("○ not installed (optional, for FPS overlay)");
-            }
-
-            // Check libraries
-            println!("\nLibraries:");
-            let libs = [
-                ("libvulkan", "vulkan-icd-loader"),
-                ("libGL", "mesa"),
-                ("libX11", "libx11"),
-            ];
-            for (lib, pkg) in libs {
-                print!("  {}: ", lib);
-                let found = std::process::Command::new("ldconfig")
-                    .args(["-p"])
-                    .output()
-                    .ok()
-                    .and_then(|o| String::from_utf8(o.stdout).ok())
-                    .map(|s| s.contains(lib))
-                    .unwrap_or(false);
-                if found {
-                    println!("✓");
-                } else {
-                    println!("✗ (install {})", pkg);
-                    issues += 1;
-                }
-            }
-
-            // Check disk space
-            println!("\nDisk:");
-            let data_dir = PathBuf::from(format!("{home}/.local/share/elm"));
-            if let Ok(output) = std::process::Command::new("df")
-                .args(["-h", data_dir.to_str().unwrap_or("/home")])
-                .output()
-            {
-                if let Ok(s) = String::from_utf8(output.stdout) {
-                    if let Some(line) = s.lines().nth(1) {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() >= 4 {
-                            println!("  Available: {} (on {})", parts[3], parts[0]);
-                        }
+            let home = std::env::var("HOME").unwrap_or_default();
+            let elm_bin = std::env::current_exe()?;
+            let icon = icon.or_else(|| desktop::default_icon(&home));
+
+            let desktop_path = desktop::write_desktop_file(&home, &elm_bin, &profile, icon.as_deref())?;
+            say!("✓ Desktop entry: {}", desktop_path.display());
+            let _ = std::process::Command::new("update-desktop-database")
+                .arg(desktop_path.parent().unwrap_or(std::path::Path::new(".")))
+                .output();
+
+            if steam {
+                let steam_root = steam_shortcuts::steam_root()?;
+                let users = steam_shortcuts::user_dirs(&steam_root)?;
+                let user_dir = match (&steam_user, users.as_slice()) {
+                    (Some(id), _) => users.iter()
+                        .find(|d| d.file_name().is_some_and(|n| n.to_string_lossy() == *id))
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("Steam user {} not found in {}/userdata", id, steam_root.display()))?,
+                    (None, [only]) => only.clone(),
+                    (None, []) => return Err(anyhow::anyhow!(
+                        "No Steam users found in {}/userdata (log in to Steam once first)", steam_root.display())),
+                    (None, _) => {
+                        let ids: Vec<String> = users.iter()
+                            .filter_map(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+                            .collect();
+                        return Err(anyhow::anyhow!(
+                            "Several Steam users found ({}); pick one with --steam-user", ids.join(", ")));
                     }
+                };
+
+                if steam_shortcuts::steam_running() {
+                    eprintln!("Warning: Steam is running and may overwrite shortcuts.vdf on exit. Restart Steam after this.");
                 }
-            }
 
-            // Summary
-            println!("\n----------");
-            if issues == 0 {
-                println!("✓ System ready for EVE Online");
-            } else {
-                println!("✗ {} issue(s) found", issues);
+                let app_name = if profile == "default" {
+                    "EVE Online".to_string()
+                } else {
+                    format!("EVE Online ({})", profile)
+                };
+                let shortcut = steam_shortcuts::Shortcut {
+                    app_name: app_name.clone(),
+                    exe: elm_bin.clone(),
+                    launch_options: format!("run --profile {}", profile),
+                    icon: icon.clone(),
+                };
+                let vdf_path = steam_shortcuts::shortcuts_path(&user_dir);
+                let replaced = steam_shortcuts::upsert_shortcut(&vdf_path, &shortcut)?;
+                say!("✓ Steam shortcut '{}' {} in {}", app_name,
+                    if replaced { "updated" } else { "added" }, vdf_path.display());
             }
         }
-        Commands::Logs { log_type, lines, list, profile } => {
-            let home = std::env::var("HOME").unwrap_or_default();
-            let prefix_dir = PathBuf::from(format!("{home}/.local/share/elm/prefixes/eve-{profile}"));
-            let logs_dir = prefix_dir.join("pfx/drive_c/users/steamuser/AppData/Roaming/EVE Online/logs");
-
-            // Collect all log files
-            let mut log_files: Vec<(PathBuf, std::time::SystemTime)> = Vec::new();
+        Commands::Status { json } => {
+            let data_dir = ctx.data_dir.clone();
+            let config_dir = ctx.config_dir.clone();
+            let report = elm_core::system::status::gather(&data_dir, &config_dir);
 
-            // EVE Launcher logs
-            if logs_dir.exists() {
-                if let Ok(entries) = std::fs::read_dir(&logs_dir) {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.extension().map(|e| e == "log").unwrap_or(false) {
-                            if let Ok(meta) = path.metadata() {
-                                if let Ok(modified) = meta.modified() {
-                                    log_files.push((path, modified));
-                                }
-                            }
-                        }
-                    }
-                }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
             }
 
-            // Squirrel/installer logs
-            let squirrel_log = prefix_dir.join("pfx/drive_c/users/steamuser/AppData/Local/eve-online/Squirrel-Update.log");
-            if squirrel_log.exists() {
-                if let Ok(meta) = squirrel_log.metadata() {
-                    if let Ok(modified) = meta.modified() {
-                        log_files.push((squirrel_log, modified));
-                    }
-                }
-            }
+            say!("ELM Status");
+            say!("==========\n");
 
-            // Proton fixes log
-            let proton_log = PathBuf::from("/tmp/protonfixes_test.log");
-            if proton_log.exists() {
-                if let Ok(meta) = proton_log.metadata() {
-                    if let Ok(modified) = meta.modified() {
-                        log_files.push((proton_log, modified));
-                    }
+            // Engines
+            say!("Engines:");
+            for engine in &report.engines {
+                say!("  {} {}", if engine.installed { "✓" } else { "○" }, engine.id);
+            }
+            if report.engines.is_empty() {
+                say!("  (none)");
+            }
+
+            // Prefixes
+            say!("\nPrefixes:");
+            for prefix in &report.prefixes {
+                say!("  {} {} ({:.1} GB)", if prefix.initialized { "✓" } else { "○" },
+                    prefix.name, prefix.size as f64 / 1_073_741_824.0);
+            }
+            if report.prefixes.is_empty() {
+                say!("  (none)");
+            }
+
+            // Snapshots
+            say!("\nSnapshots:");
+            for s in &report.snapshots {
+                say!("  {}.tar.zst ({:.1} GB)", s.name, s.size as f64 / 1_073_741_824.0);
+            }
+            if report.snapshots.is_empty() {
+                say!("  (none)");
+            }
+
+            // Config
+            say!("\nConfig:");
+            match &report.manifest {
+                Some(path) => say!("  ✓ {}", path.display()),
+                None => say!("  (no custom config)"),
+            }
+
+            say!("\nPaths:");
+            say!("  Data:   {}", report.data_dir.display());
+            say!("  Config: {}", report.config_dir.display());
+        }
+        Commands::Doctor { json } => {
+            if json {
+                QUIET.store(true, Ordering::Relaxed);
+            }
+            let data_dir = ctx.data_dir.clone();
+
+            // Checks like esync's file limit only matter if the config actually enables them
+            let manifest = load_profile_manifest(&ctx, "default").unwrap_or_else(|e| {
+                eprintln!("Warning: {:#} (checking against defaults)", e);
+                None
+            });
+            // Fall back to the built-in stable channel's constraints
+            let channel = match ctx.load_channel("default") {
+                Ok(Some(channel)) => Some(channel),
+                Ok(None) => elm_core::config::load::builtin_channel().ok(),
+                Err(e) => {
+                    eprintln!("Warning: {:#} (skipping channel constraints)", e);
+                    None
+                }
+            };
+            let min_vulkan = channel.as_ref()
+                .and_then(|c| c.constraints.as_ref())
+                .and_then(|c| c.min_vulkan.as_deref());
+            let report = elm_core::system::doctor::run_checks(
+                &data_dir, &launch_env(manifest.as_ref()), min_vulkan);
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+
+            say!("ELM Doctor");
+            say!("==========\n");
+
+            let mut in_libraries = false;
+            for check in &report.checks {
+                let mark = match (check.ok, check.required) {
+                    (true, _) => "✓",
+                    (false, true) => "✗",
+                    (false, false) => "○",
+                };
+                if check.category == "library" {
+                    if !in_libraries {
+                        say!("\nLibraries:");
+                        in_libraries = true;
+                    }
+                    if check.detail.is_empty() {
+                        say!("  {}: {}", check.name, mark);
+                    } else {
+                        say!("  {}: {} ({})", check.name, mark, check.detail);
+                    }
+                    continue;
+                }
+                say!("{:<7} {} {}", format!("{}:", check.name), mark, check.detail);
+                if let Some(fix) = &check.fix {
+                    for (i, line) in fix.lines().enumerate() {
+                        say!("        {} {}", if i == 0 { "→" } else { " " }, line);
+                    }
+                }
+                if check.name == "GPU" && report.gpus.len() > 1 {
+                    for gpu in &report.gpus {
+                        say!("        [{}] {} ({})", gpu.index, gpu.device, gpu.vendor);
+                    }
                 }
             }
 
-            // Sort by modification time (newest first)
-            log_files.sort_by(|a, b| b.1.cmp(&a.1));
+            // Disk space
+            say!("\nDisk:");
+            if let (Some(available), Some(device)) = (&report.disk_available, &report.disk_device) {
+                say!("  Available: {} (on {})", available, device);
+            }
+
+            // Summary
+            say!("\n----------");
+            if report.ready {
+                say!("✓ System ready for EVE Online");
+            } else {
+                // Named in the error too, so `-q` still shows what's wrong
+                let failed: Vec<&str> =
+                    report.checks.iter().filter(|c| c.required && !c.ok).map(|c| c.name.as_str()).collect();
+                say!("✗ {} issue(s) found", report.issues);
+                return Err(anyhow::anyhow!("{} issue(s) found: {}", report.issues, failed.join(", ")));
+            }
+        }
+        Commands::Logs { log_type, lines, list, follow, profile } => {
+            let prefix_dir = ctx.profile_prefix(&profile);
+            let log_files = collect_log_files(&prefix_dir);
 
             if list {
-                println!("Available log files:\n");
-                for (path, time) in &log_files {
-                    let age = time.elapsed().map(|d| {
-                        if d.as_secs() < 60 {
-                            format!("{}s ago", d.as_secs())
-                        } else if d.as_secs() < 3600 {
-                            format!("{}m ago", d.as_secs() / 60)
-                        } else if d.as_secs() < 86400 {
-                            format!("{}h ago", d.as_secs() / 3600)
-                        } else {
-                            format!("{}d ago", d.as_secs() / 86400)
-                        }
-                    }).unwrap_or_else(|_| "?".to_string());
+                say!("Available log files:\n");
+                for log in &log_files {
+                    let age = format_age(log.modified);
 
-                    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-                    println!("  {} ({}, {})", path.display(), age, format_size(size));
+                    let size = std::fs::metadata(&log.path).map(|m| m.len()).unwrap_or(0);
+                    println!("  [{}] {} ({}, {})", log.source, log.path.display(), age, format_size(size));
                 }
                 return Ok(());
             }
 
             // Filter by log type
-            let filtered: Vec<_> = match log_type.as_str() {
-                "launcher" => log_files.into_iter().filter(|(p, _)| {
-                    p.file_name().map(|n| n.to_string_lossy().contains("eve-online-launcher")).unwrap_or(false)
-                }).collect(),
-                "wine" | "proton" => log_files.into_iter().filter(|(p, _)| {
-                    p.to_string_lossy().contains("proton") || p.to_string_lossy().contains("wine")
-                }).collect(),
-                "squirrel" | "installer" => log_files.into_iter().filter(|(p, _)| {
-                    p.to_string_lossy().contains("Squirrel")
-                }).collect(),
-                "all" => log_files,
-                _ => {
-                    println!("Unknown log type: {}. Use: launcher, wine, squirrel, all", log_type);
-                    return Ok(());
-                }
+            let Some(filtered) = filter_logs(log_files, &log_type) else {
+                return Err(anyhow::anyhow!("Unknown log type '{}' (use: launcher, wine, proton, squirrel, installer, all)", log_type));
             };
 
+            if filtered.is_empty() && follow {
+                say!("No {} logs yet for profile '{}', waiting for one (Ctrl-C to stop)...", log_type, profile);
+                return follow_log(None, &log_type, &prefix_dir);
+            }
             if filtered.is_empty() {
-                println!("No {} logs found for profile '{}'", log_type, profile);
-                println!("\nRun 'elm logs --list' to see all available logs");
+                say!("No {} logs found for profile '{}'", log_type, profile);
+                say!("\nRun 'elm logs --list' to see all available logs");
                 return Ok(());
             }
 
             // Show most recent log
-            let (log_path, _) = &filtered[0];
-            println!("=== {} ===\n", log_path.display());
+            let log_path = &filtered[0].path;
+            say!("=== {} [{}] ===\n", log_path.display(), filtered[0].source);
 
             if let Ok(content) = std::fs::read_to_string(log_path) {
                 let all_lines: Vec<&str> = content.lines().collect();
@@ -834,23 +1252,56 @@ async fn main() -> Result<()> {
                 for line in &all_lines[start..] {
                     println!("{}", line);
                 }
-                println!("\n=== Showing last {} of {} lines ===",
+                say!("\n=== Showing last {} of {} lines ===",
                     std::cmp::min(lines, all_lines.len()), all_lines.len());
             } else {
-                println!("(could not read log file)");
+                say!("(could not read log file)");
+            }
+
+            if follow {
+                follow_log(Some(log_path.clone()), &log_type, &prefix_dir)?;
             }
         }
-        Commands::Update { install, no_backup, notify } => {
-            let home = std::env::var("HOME").unwrap_or_default();
-            let data_dir = PathBuf::from(format!("{home}/.local/share/elm"));
-            let engines_dir = data_dir.join("engines");
-            let downloads_dir = data_dir.join("downloads");
-            let prefixes_dir = data_dir.join("prefixes");
+        Commands::Update { install, no_backup, notify, offer_install, repo, list, set_active, rollback } => {
+            let data_dir = ctx.data_dir.clone();
+            let engines_dir = ctx.engines_dir();
+            let downloads_dir = ctx.downloads_dir();
+            let prefixes_dir = ctx.prefixes_dir();
             let snapshots_dir = data_dir.join("snapshots");
 
-            println!("Checking for engine updates...\n");
+            if rollback {
+                let switch = elm_core::engine::switch::rollback(&data_dir)?;
+                say!("✓ {} is back on {} (was {})", switch.manifest.display(), switch.previous, switch.current);
+                return Ok(());
+            }
+
+            let manifest = load_profile_manifest(&ctx, "default")?;
+            let limit_rate = apply_limit_rate(limit_rate, manifest.as_ref());
+            let repo = repo
+                .or_else(|| manifest.and_then(|m| m.engine.repo))
+                .unwrap_or_else(|| elm_core::engine::releases::DEFAULT_REPO.to_string());
 
-            // Get installed version
+            if list {
+                let releases = tokio::task::block_in_place(|| {
+                    elm_core::engine::releases::list_releases(&repo, 10)
+                })?;
+                say!("Releases of {}:\n", repo);
+                for r in &releases {
+                    let asset = r.tarball();
+                    println!(
+                        "  {:<24} {:<12} {}{}",
+                        r.tag,
+                        r.published_at.as_deref().and_then(|d| d.get(..10)).unwrap_or(""),
+                        asset.map(|a| format_size(a.size)).unwrap_or_else(|| "(no archive)".into()),
+                        if r.prerelease { "  [prerelease]" } else { "" },
+                    );
+                }
+                return Ok(());
+            }
+
+            say!("Checking for engine updates ({})...\n", repo);
+
+            // Get installed version (the newest, if several are installed)
             let installed: Option<String> = std::fs::read_dir(&engines_dir)
                 .ok()
                 .and_then(|entries| {
@@ -859,67 +1310,67 @@ async fn main() -> Result<()> {
                         .filter(|e| e.path().is_dir())
                         .filter(|e| e.path().join("installed.json").exists())
                         .map(|e| e.file_name().to_string_lossy().to_string())
-                        .next()
+                        .max_by_key(|id| elm_core::engine::version::parse(id))
                 });
 
-            println!("Installed: {}", installed.as_deref().unwrap_or("(none)"));
+            say!("Installed: {}", installed.as_deref().unwrap_or("(none)"));
 
             // Fetch latest from GitHub API
-            print!("Latest:    ");
-            let latest = std::process::Command::new("curl")
-                .args(["-s", "https://api.github.com/repos/GloriousEggroll/proton-ge-custom/releases/latest"])
-                .output()
-                .ok()
-                .and_then(|o| String::from_utf8(o.stdout).ok())
-                .and_then(|s| {
-                    // Parse tag_name from JSON
-                    s.lines()
-                        .find(|l| l.contains("\"tag_name\""))
-                        .and_then(|l| l.split('"').nth(3))
-                        .map(|s| s.to_string())
-                });
-
-            let latest_tag = match &latest {
-                Some(tag) => {
-                    println!("{}", tag);
-                    tag.clone()
-                }
-                None => {
-                    println!("(failed to fetch)");
-                    return Err(anyhow::anyhow!("Could not fetch latest release from GitHub"));
+            say_inline!("Latest:    ");
+            let release = match tokio::task::block_in_place(|| {
+                elm_core::engine::releases::latest_release(&repo)
+            }) {
+                Ok(release) => release,
+                Err(e) => {
+                    say!("(failed to fetch)");
+                    return Err(e.context(format!("Could not fetch latest release of {repo} from GitHub")));
                 }
             };
+            let latest_tag = release.tag.clone();
+            say!("{}", latest_tag);
 
             // Compare versions
-            let installed_normalized = installed.as_ref()
-                .map(|s| s.to_lowercase().replace("-", "").replace("_", ""));
-            let latest_normalized = latest_tag.to_lowercase().replace("-", "").replace("_", "");
-
-            let needs_update = installed_normalized.as_ref()
-                .map(|i| i != &latest_normalized)
+            let needs_update = installed
+                .as_deref()
+                .map(|i| elm_core::engine::version::parse(i) < elm_core::engine::version::parse(&latest_tag))
                 .unwrap_or(true);
 
             if !needs_update {
-                println!("\n✓ Engine is up to date");
+                say!("\n✓ Engine is up to date");
                 return Ok(());
             }
 
-            println!("\n⬆ Update available!");
+            say!("\n⬆ Update available!");
 
             // Send notification if requested
-            if notify {
-                let _ = std::process::Command::new("notify-send")
-                    .args([
-                        "--app-name=ELM",
-                        "--icon=software-update-available",
-                        "EVE Engine Update Available",
-                        &format!("{} → {}", installed.as_deref().unwrap_or("none"), latest_tag),
-                    ])
-                    .spawn();
+            let notifier = notify.then(elm_core::integration::notify::notifier);
+            let mut install = install;
+            if let Some(notifier) = &notifier {
+                let summary = "EVE Engine Update Available";
+                let body = format!("{} → {}", installed.as_deref().unwrap_or("none"), latest_tag);
+                if offer_install {
+                    let notification = elm_core::integration::notify::Notification {
+                        summary: summary.to_string(),
+                        body,
+                        icon: "software-update-available".to_string(),
+                        actions: vec![("install".to_string(), "Install".to_string())],
+                        ..Default::default()
+                    };
+                    say!("Waiting for the notification's Install button (Ctrl-C to skip)...");
+                    let answer = tokio::task::block_in_place(|| {
+                        notifier.ask(&notification, std::time::Duration::from_secs(600))
+                    });
+                    match answer {
+                        Ok(answer) => install = answer.as_deref() == Some("install"),
+                        Err(e) => eprintln!("⚠ Could not show a notification: {:#}", e),
+                    }
+                } else {
+                    send_notification(&**notifier, summary, &body, "software-update-available");
+                }
             }
 
             if !install {
-                println!("\nRun 'elm update --install' to download and install");
+                say!("\nRun 'elm update --install' to download and install");
                 return Ok(());
             }
 
@@ -931,7 +1382,7 @@ async fn main() -> Result<()> {
                     .collect();
 
                 if !prefixes.is_empty() {
-                    println!("\nBacking up {} prefix(es) before update...", prefixes.len());
+                    say!("\nBacking up {} prefix(es) before update...", prefixes.len());
                     std::fs::create_dir_all(&snapshots_dir)?;
 
                     let timestamp = std::time::SystemTime::now()
@@ -944,416 +1395,773 @@ async fn main() -> Result<()> {
                         let snapshot_name = format!("{}-pre-update-{}", name, timestamp);
                         let prefix_path = entry.path();
 
-                        print!("  {} ... ", name);
+                        let label = format!("  {} ... ", name);
+                        say_inline!("{}", label);
                         std::io::Write::flush(&mut std::io::stdout())?;
 
-                        match elm_core::rollback::snapshot::snapshot_prefix(
+                        let metadata = elm_core::rollback::snapshot::SnapshotMetadata::new(
+                            &prefix_path.join("pfx"),
+                            Some(ctx.engine_for_prefix(&prefix_path)),
+                            name.strip_prefix("eve-").map(|p| p.to_string()),
+                        );
+                        let mut totals = elm_core::rollback::snapshot::SnapshotProgress::default();
+                        let result = elm_core::rollback::snapshot::snapshot_prefix(
                             &prefix_path.join("pfx"),
                             &snapshots_dir,
-                            &snapshot_name
-                        ) {
+                            &snapshot_name,
+                            &metadata,
+                            &elm_core::rollback::snapshot::SnapshotOptions::default(),
+                            snapshot_progress(&label, &mut totals),
+                        );
+                        say_inline!("\r{}", label);
+                        match result {
                             Ok(out) => {
                                 let size = std::fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
-                                println!("✓ ({:.1} GB)", size as f64 / 1_073_741_824.0);
+                                say!("✓ {} entries ({:.1} GB)            ", totals.entries, size as f64 / 1_073_741_824.0);
                             }
                             Err(e) => {
-                                println!("✗ ({})", e);
-                                println!("\nWarning: Backup failed, but continuing with update.");
-                                println!("You may want to manually backup your prefix before proceeding.");
+                                say!("✗ ({})", e);
+                                eprintln!("\nWarning: Backup failed, but continuing with update.");
+                                eprintln!("You may want to manually backup your prefix before proceeding.");
                             }
                         }
                     }
-                    println!();
+                    say!();
                 }
             }
 
-            // Download and install
-            println!("Downloading {}...", latest_tag);
-
-            let download_url = format!(
-                "https://github.com/GloriousEggroll/proton-ge-custom/releases/download/{}/{}.tar.gz",
-                latest_tag, latest_tag
-            );
-
-            let archive_path = downloads_dir.join(format!("{}.tar.gz", latest_tag));
-            std::fs::create_dir_all(&downloads_dir)?;
-
-            // Download with curl (shows progress)
-            let status = std::process::Command::new("curl")
-                .args(["-L", "-o", archive_path.to_str().unwrap(), &download_url, "--progress-bar"])
-                .status()?;
-
-            if !status.success() {
-                return Err(anyhow::anyhow!("Download failed"));
+            // Download, verify and install
+            let artifact = tokio::task::block_in_place(|| {
+                elm_core::engine::install::EngineArtifact::from_release(&release, &repo)
+            })?;
+            match limit_rate {
+                Some(kbps) => say!("Downloading {} (limited to {} KB/s)...", latest_tag, kbps),
+                None => say!("Downloading {}...", latest_tag),
             }
 
-            // Extract
-            println!("Extracting...");
-            let engine_id = latest_tag.to_lowercase();
-            let engine_dir = engines_dir.join(&engine_id);
-            let dist_dir = engine_dir.join("dist");
-            std::fs::create_dir_all(&dist_dir)?;
-
-            let status = std::process::Command::new("tar")
-                .args(["-xzf", archive_path.to_str().unwrap(), "-C", dist_dir.to_str().unwrap()])
-                .status()?;
-
-            if !status.success() {
-                return Err(anyhow::anyhow!("Extraction failed"));
+            let mut last_percent = None;
+            // One notification updated in place every 10%, where the backend allows it
+            let mut progress_id = None;
+            let dist_dir = elm_core::engine::install::install_artifact(&artifact, &engines_dir, &downloads_dir, |done, total| {
+                let Some(total) = total.filter(|t| *t > 0) else { return };
+                let percent = done * 100 / total;
+                if last_percent != Some(percent) {
+                    if let Some(notifier) = &notifier {
+                        if last_percent.is_none_or(|last| last / 10 != percent / 10) {
+                            let notification = elm_core::integration::notify::Notification {
+                                summary: format!("Downloading {}", latest_tag),
+                                body: format!("{}% of {}", percent, format_size(total)),
+                                icon: "software-update-available".to_string(),
+                                replaces: progress_id,
+                                progress: Some(percent as u8),
+                                ..Default::default()
+                            };
+                            progress_id = notifier.notify(&notification).ok().flatten().or(progress_id);
+                        }
+                    }
+                    last_percent = Some(percent);
+                    say_inline!("\r  {}% of {}", percent, format_size(total));
+                    let _ = std::io::Write::flush(&mut std::io::stdout());
+                }
+            })
+            .await?;
+            say!();
+            match &artifact.checksum {
+                Some(sum) => say!("✓ {} verified", if sum.len() == 128 { "sha512" } else { "sha256" }),
+                None => eprintln!("Warning: {} publishes no checksum for {}; not verified", repo, latest_tag),
+            }
+            let engine_dir = dist_dir.parent().unwrap_or(&dist_dir);
+
+            say!("\n✓ Installed {} to {}", latest_tag, engine_dir.display());
+            if let Some(notifier) = &notifier {
+                // Takes the place of the progress notification
+                let notification = elm_core::integration::notify::Notification {
+                    summary: "EVE Engine Installed".to_string(),
+                    body: format!("{} is ready", latest_tag),
+                    icon: "software-update-available".to_string(),
+                    replaces: progress_id,
+                    ..Default::default()
+                };
+                if let Err(e) = notifier.notify(&notification) {
+                    eprintln!("⚠ Could not show a notification: {:#}", e);
+                }
             }
 
-            // Write marker
-            let marker = serde_json::json!({
-                "engine_id": engine_id,
-                "version": latest_tag
-            });
-            std::fs::write(engine_dir.join("installed.json"), serde_json::to_vec_pretty(&marker)?)?;
-
-            println!("\n✓ Installed {} to {}", latest_tag, engine_dir.display());
-            println!("\nNote: Update ~/.config/elm/manifests/eve-online.json to use the new engine");
+            let manifest_path = ctx.shared_manifest_path();
+            if !set_active {
+                say!("\nNote: Set engine.ref to \"{}\" in {} to use the new engine", artifact.id, manifest_path.display());
+            } else if manifest_path.exists() {
+                let previous = elm_core::config::load::read_config(&manifest_path)?["engine"]["ref"]
+                    .as_str()
+                    .map(str::to_string);
+                let backup = elm_core::config::edit::set_engine_ref(&manifest_path, &artifact.id)?;
+                say!("✓ {} now uses {}", manifest_path.display(), artifact.id);
+                if let Some(previous) = previous.filter(|p| *p != artifact.id) {
+                    elm_core::engine::switch::record(&data_dir, &manifest_path, &previous, &artifact.id)?;
+                    say!("  If EVE misbehaves on it: elm update --rollback (back to {})", previous);
+                }
+                if let Some(bak) = backup {
+                    say!("  Previous config saved to {}", bak.display());
+                }
+            } else {
+                say!("\nNo manifest at {}; run 'elm config init' to create one", manifest_path.display());
+            }
         }
-        Commands::Clean { dry_run, downloads, engines, all } => {
+        Commands::Clean { dry_run, downloads, engines, cache, snapshots, keep, older_than, temp, all } => {
             let home = std::env::var("HOME").unwrap_or_default();
-            let data_dir = PathBuf::from(format!("{home}/.local/share/elm"));
-            let downloads_dir = data_dir.join("downloads");
-            let engines_dir = data_dir.join("engines");
+            let data_dir = ctx.data_dir.clone();
+            let downloads_dir = ctx.downloads_dir();
+            let engines_dir = ctx.engines_dir();
+            let prefixes_dir = ctx.prefixes_dir();
+            let snapshots_dir = data_dir.join("snapshots");
 
             let clean_downloads = downloads || all;
             let clean_engines = engines || all;
-
-            if !clean_downloads && !clean_engines {
-                println!("ELM Clean");
-                println!("=========\n");
-                println!("Specify what to clean:");
-                println!("  --downloads  Remove downloaded archives");
-                println!("  --engines    Remove old engine versions (keep latest)");
-                println!("  --all        Remove both");
-                println!("  --dry-run    Show what would be removed");
+            let clean_cache = cache || all;
+            let clean_snapshots = snapshots || all;
+            let clean_temp = temp || all;
+
+            if !clean_downloads && !clean_engines && !clean_cache && !clean_snapshots && !clean_temp {
+                say!("ELM Clean");
+                say!("=========\n");
+                say!("Specify what to clean:");
+                say!("  --downloads  Remove downloaded archives");
+                say!("  --engines    Remove old engine versions (keep latest)");
+                say!("  --cache      Remove Proton logs");
+                say!("  --snapshots  Remove old snapshots (--keep N per profile, --older-than DAYS)");
+                say!("  --temp       Remove leftovers of interrupted downloads, installs and restores");
+                say!("  --all        Remove all of the above");
+                say!("  --dry-run    Show what would be removed");
                 return Ok(());
             }
 
-            let mut total_freed: u64 = 0;
+            // Work out everything to remove first, so it can be shown and confirmed
+            let mark = if dry_run { "○" } else { "✗" };
+            let mut doomed: Vec<(&str, PathBuf, u64)> = Vec::new();
 
             // Clean downloads
             if clean_downloads && downloads_dir.exists() {
-                println!("Downloads:");
-                let mut download_size: u64 = 0;
-                let mut files_to_remove = Vec::new();
-
+                say!("Downloads:");
+                let mut found = false;
                 for entry in std::fs::read_dir(&downloads_dir)?.flatten() {
                     let path = entry.path();
                     if path.is_file() {
-                        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-                        download_size += size;
-                        files_to_remove.push(path);
+                        let size = elm_core::system::disk::usage(&path);
+                        say!("  {} {} ({})", mark, path.file_name().unwrap().to_string_lossy(), format_size(size));
+                        doomed.push(("Downloads", path, size));
+                        found = true;
                     }
                 }
-
-                if files_to_remove.is_empty() {
-                    println!("  (no files to clean)");
-                } else {
-                    for path in &files_to_remove {
-                        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-                        println!("  {} {}", if dry_run { "○" } else { "✗" }, path.file_name().unwrap().to_string_lossy());
-                        if !dry_run {
-                            std::fs::remove_file(path)?;
-                        }
-                        total_freed += size;
-                    }
-                    println!("  {} ({:.1} MB)",
-                        if dry_run { "Would free" } else { "Freed" },
-                        download_size as f64 / 1_048_576.0);
+                if !found {
+                    say!("  (no files to clean)");
                 }
-                println!();
+                say!();
             }
 
-            // Clean old engines (keep latest)
+            // Clean old engines: keep the newest plus any engine a manifest still uses
             if clean_engines && engines_dir.exists() {
-                println!("Engines:");
+                say!("Engines:");
                 let mut engine_entries: Vec<_> = std::fs::read_dir(&engines_dir)?
                     .flatten()
                     .filter(|e| e.path().is_dir() && e.path().join("installed.json").exists())
                     .collect();
 
                 if engine_entries.len() <= 1 {
-                    println!("  (only one engine installed, nothing to clean)");
+                    say!("  (only one engine installed, nothing to clean)");
                 } else {
-                    // Sort by name (version) descending to keep latest
-                    engine_entries.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+                    // Newest first by version (GE-Proton10-10 after GE-Proton10-9), not by name
+                    engine_entries.sort_by_key(|e| {
+                        std::cmp::Reverse(elm_core::engine::version::parse(&e.file_name().to_string_lossy()))
+                    });
+                    let in_use = ctx.engines_in_use();
+
+                    for (i, entry) in engine_entries.iter().enumerate() {
+                        let id = entry.file_name().to_string_lossy().to_string();
+                        if i == 0 {
+                            say!("  ✓ Keeping: {} (latest)", id);
+                        } else if in_use.contains(&id) {
+                            say!("  ✓ Keeping: {} (in use)", id);
+                        } else {
+                            let size = elm_core::system::disk::usage(&entry.path());
+                            say!("  {} {} ({})", mark, id, format_size(size));
+                            doomed.push(("Engines", entry.path(), size));
+                        }
+                    }
+                }
+                say!();
+            }
 
-                    // Keep the first (latest), remove the rest
-                    let latest = &engine_entries[0];
-                    println!("  ✓ Keeping: {}", latest.file_name().to_string_lossy());
+            // Clean Proton logs: per-profile logs dirs plus any left in $HOME
+            if clean_cache {
+                say!("Proton logs:");
+                let mut log_dirs = vec![PathBuf::from(&home)];
+                if let Ok(entries) = std::fs::read_dir(&prefixes_dir) {
+                    log_dirs.extend(entries.flatten().map(|e| elm_core::prefix::proton_logs_dir(&e.path())));
+                }
 
-                    for entry in &engine_entries[1..] {
+                let mut found = false;
+                for dir in &log_dirs {
+                    let Ok(entries) = std::fs::read_dir(dir) else { continue };
+                    for entry in entries.flatten() {
                         let path = entry.path();
-                        let size = dir_size(&path).unwrap_or(0);
-                        println!("  {} {} ({:.1} GB)",
-                            if dry_run { "○" } else { "✗" },
-                            entry.file_name().to_string_lossy(),
-                            size as f64 / 1_073_741_824.0);
-
-                        if !dry_run {
-                            std::fs::remove_dir_all(&path)?;
+                        // Launch captures are only ours inside a prefix's logs dir, never in $HOME
+                        let is_log = elm_core::prefix::is_proton_log(&path)
+                            || (dir.as_os_str() != home.as_str() && elm_core::prefix::is_launch_log(&path));
+                        if !path.is_file() || !is_log {
+                            continue;
                         }
-                        total_freed += size;
+                        let size = elm_core::system::disk::usage(&path);
+                        say!("  {} {} ({})", mark, path.display(), format_size(size));
+                        doomed.push(("Proton logs", path, size));
+                        found = true;
                     }
                 }
-                println!();
+                if !found {
+                    say!("  (no logs to clean)");
+                }
+                say!();
             }
 
-            // Summary
-            println!("----------");
+            // Old snapshots (e.g. the automatic pre-update ones) past the keep/age policy
+            if clean_snapshots {
+                say!("Snapshots:");
+                let all_snapshots = elm_core::rollback::snapshot::list_snapshots(&snapshots_dir)?;
+                let cutoff = older_than.map(|days| {
+                    std::time::SystemTime::now() - std::time::Duration::from_secs(days * 24 * 60 * 60)
+                });
+                let old: Vec<_> = elm_core::rollback::snapshot::prune_candidates(&all_snapshots, keep)
+                    .into_iter()
+                    .filter(|s| cutoff.is_none_or(|cutoff| s.created < cutoff))
+                    .collect();
+                if old.is_empty() {
+                    say!("  (keeping all {} snapshot(s))", all_snapshots.len());
+                }
+                for s in old {
+                    say!("  {} {} ({}, {})", mark, s.name, format_size(s.size), format_age(s.created));
+                    doomed.push(("Snapshots", s.path, s.size));
+                }
+                say!();
+            }
+
+            // Leftovers of interrupted operations
+            if clean_temp {
+                say!("Temporary files:");
+                let leftovers: Vec<_> = elm_core::system::disk::leftovers(&data_dir)
+                    .into_iter()
+                    .filter(|p| !doomed.iter().any(|(_, d, _)| d == p))
+                    .collect();
+                if leftovers.is_empty() {
+                    say!("  (nothing left over)");
+                }
+                for path in leftovers {
+                    let size = elm_core::system::disk::usage(&path);
+                    say!("  {} {} ({})", mark, path.display(), format_size(size));
+                    doomed.push(("Temporary files", path, size));
+                }
+                say!();
+            }
+
+            // Summary, per category in the order they were listed
+            let total: u64 = doomed.iter().map(|(_, _, size)| size).sum();
+            let mut per_category: Vec<(&str, u64)> = Vec::new();
+            for (category, _, size) in &doomed {
+                match per_category.iter_mut().find(|(c, _)| c == category) {
+                    Some((_, sum)) => *sum += size,
+                    None => per_category.push((*category, *size)),
+                }
+            }
+            say!("----------");
+            for (category, size) in &per_category {
+                say!("  {:<16} {}", category, format_size(*size));
+            }
+            if doomed.is_empty() {
+                say!("Nothing to clean");
+                return Ok(());
+            }
             if dry_run {
-                println!("Dry run: would free {:.2} GB", total_freed as f64 / 1_073_741_824.0);
-                println!("\nRun without --dry-run to actually clean");
-            } else {
-                println!("Freed {:.2} GB", total_freed as f64 / 1_073_741_824.0);
+                say!("Dry run: would free {} ({} item(s))", format_size(total), doomed.len());
+                say!("\nRun without --dry-run to actually clean");
+                return Ok(());
+            }
+            if !assume_yes {
+                eprintln!("Delete the {} item(s) marked ✗ above? ({} freed)", doomed.len(), format_size(total));
+                if !confirm()? {
+                    eprintln!("Cancelled");
+                    return Ok(());
+                }
+            }
+            for (category, path, _) in &doomed {
+                if *category == "Snapshots" {
+                    elm_core::rollback::snapshot::delete_snapshot(path)?;
+                } else if path.is_dir() {
+                    std::fs::remove_dir_all(path).with_context(|| format!("remove {}", path.display()))?;
+                } else {
+                    std::fs::remove_file(path).with_context(|| format!("remove {}", path.display()))?;
+                }
             }
+            say!("Freed {}", format_size(total));
         }
         Commands::Validate { schemas, channel, engine, manifest, profile } => {
+            let mut cache = elm_core::config::validate::SchemaCache::new();
             if let Some(p) = channel {
-                let _ = elm_core::config::load::load_channel(&p, &schemas)?;
-                println!("OK: channel {}", p.display());
+                let _ = elm_core::config::load::load_channel_with(&p, &schemas, &mut cache)?;
+                say!("OK: channel {}", p.display());
             }
             if let Some(p) = engine {
-                let _ = elm_core::config::load::load_engine(&p, &schemas)?;
-                println!("OK: engine {}", p.display());
+                let _ = elm_core::config::load::load_engine_with(&p, &schemas, &mut cache)?;
+                say!("OK: engine {}", p.display());
             }
             if let Some(p) = manifest {
-                let _ = elm_core::config::load::load_manifest(&p, &schemas)?;
-                println!("OK: manifest {}", p.display());
+                let m = elm_core::config::load::load_manifest_with(&p, &schemas, &mut cache)?;
+                let engines_dir = ctx.engines_dir();
+                report_semantic_issues(&p, &elm_core::config::validate::validate_manifest_semantics(&m, &engines_dir))?;
+                say!("OK: manifest {}", p.display());
             }
             if let Some(p) = profile {
-                let _ = elm_core::config::load::load_profile(&p, &schemas)?;
-                println!("OK: profile {}", p.display());
+                let _ = elm_core::config::load::load_profile_with(&p, &schemas, &mut cache)?;
+                say!("OK: profile {}", p.display());
             }
         }
         Commands::Profile { cmd } => {
-            let home = std::env::var("HOME").unwrap_or_default();
-            let prefixes_dir = PathBuf::from(format!("{home}/.local/share/elm/prefixes"));
-            let snapshots_dir = PathBuf::from(format!("{home}/.local/share/elm/snapshots"));
+            let prefixes_dir = ctx.prefixes_dir();
+            let snapshots_dir = ctx.data_dir.join("snapshots");
 
             match cmd {
                 ProfileCmd::List => {
-                    println!("EVE Profiles");
-                    println!("============\n");
-
-                    if !prefixes_dir.exists() {
-                        println!("No profiles found. Create one with: elm profile create <name>");
-                        return Ok(());
-                    }
-
-                    let mut profiles: Vec<_> = std::fs::read_dir(&prefixes_dir)?
-                        .flatten()
-                        .filter(|e| e.path().is_dir())
-                        .filter(|e| e.file_name().to_string_lossy().starts_with("eve-"))
-                        .collect();
+                    say!("EVE Profiles");
+                    say!("============\n");
 
+                    let mut profiles = elm_core::prefix::profile::list(&prefixes_dir)?;
                     if profiles.is_empty() {
-                        println!("No profiles found. Create one with: elm profile create <name>");
+                        say!("No profiles found. Create one with: elm profile create <name>");
                         return Ok(());
                     }
 
-                    profiles.sort_by_key(|e| e.file_name());
+                    // Most recently launched first; never-launched profiles last, by name
+                    profiles.sort_by(|a, b| b.1.last_launched.cmp(&a.1.last_launched).then_with(|| a.1.name.cmp(&b.1.name)));
 
-                    for entry in profiles {
-                        let name = entry.file_name().to_string_lossy().replace("eve-", "");
-                        let path = entry.path();
+                    for (path, meta) in profiles {
                         let has_eve = path.join("pfx/drive_c/CCP/EVE").exists();
-                        let size = dir_size(&path).unwrap_or(0);
+                        let size = elm_core::system::disk::usage(&path);
+                        let last_run = meta
+                            .last_launched
+                            .map(|t| format!("last run {}", format_age(unix_time(t))))
+                            .unwrap_or_else(|| "never run".to_string());
 
                         let status = if has_eve { "✓" } else { "○" };
-                        println!("  {} {} ({:.1} GB)", status, name, size as f64 / 1_073_741_824.0);
+                        say!("  {} {} ({:.1} GB, {})", status, meta.display_name, size as f64 / 1_073_741_824.0, last_run);
                     }
 
-                    println!("\n✓ = EVE installed, ○ = prefix only");
-                    println!("\nUsage: elm run --profile <name>");
+                    say!("\n✓ = EVE installed, ○ = prefix only");
+                    say!("\nUsage: elm run --profile <name>");
                 }
                 ProfileCmd::Create { name } => {
-                    let prefix_dir = prefixes_dir.join(format!("eve-{}", name));
+                    let prefix_dir = ctx.profile_prefix(&name);
 
                     if prefix_dir.exists() {
-                        println!("Profile '{}' already exists at {}", name, prefix_dir.display());
-                        return Ok(());
+                        return Err(anyhow::anyhow!("Profile '{}' already exists at {}", name, prefix_dir.display()));
                     }
 
-                    // Find engine
-                    let engines_dir = PathBuf::from(format!("{home}/.local/share/elm/engines"));
-                    let engine_dir = std::fs::read_dir(&engines_dir)?
-                        .flatten()
-                        .find(|e| e.path().join("installed.json").exists())
-                        .map(|e| e.path());
-
-                    let proton_root = match engine_dir {
-                        Some(dir) => {
-                            let dist = dir.join("dist");
-                            std::fs::read_dir(&dist)?
-                                .flatten()
-                                .find(|e| e.path().join("proton").exists())
-                                .map(|e| e.path())
-                                .ok_or_else(|| anyhow::anyhow!("No proton found in engine"))?
-                        }
-                        None => {
-                            println!("No engine installed. Run: elm update --install");
-                            return Ok(());
-                        }
-                    };
+                    // The engine `elm run --profile <name>` will use
+                    let engine_ref = ctx.engine_for_profile(&name)?;
+                    let proton_root = ctx.proton_root(&engine_ref)?;
 
-                    println!("Creating profile '{}'...", name);
-                    elm_core::prefix::ensure_prefix_initialized(&prefix_dir, &proton_root).await?;
-                    println!("✓ Profile '{}' created at {}", name, prefix_dir.display());
-                    println!("\nTo install EVE: elm run --profile {}", name);
+                    say!("Creating profile '{}'...", name);
+                    elm_core::prefix::ensure_prefix_initialized(&prefix_dir, &proton_root, None).await?;
+                    elm_core::prefix::profile::write(&prefix_dir, &elm_core::prefix::profile::ProfileMeta::new(&name, Some(engine_ref)))?;
+                    say!("✓ Profile '{}' created at {}", name, prefix_dir.display());
+                    say!("\nTo install EVE: elm run --profile {}", name);
                 }
-                ProfileCmd::Delete { name, yes } => {
-                    let prefix_dir = prefixes_dir.join(format!("eve-{}", name));
+                ProfileCmd::Delete { name, keep_snapshots, dry_run } => {
+                    let prefix_dir = ctx.profile_prefix(&name);
 
                     if !prefix_dir.exists() {
-                        println!("Profile '{}' not found", name);
-                        return Ok(());
+                        return Err(anyhow::anyhow!("Profile '{}' not found", name));
                     }
 
-                    let size = dir_size(&prefix_dir).unwrap_or(0);
-                    let size_gb = size as f64 / 1_073_741_824.0;
+                    let size = elm_core::system::disk::usage(&prefix_dir);
+
+                    let snapshots = elm_core::rollback::snapshot::snapshots_for_profile(&snapshots_dir, &name)?;
+                    let snapshots_size: u64 = snapshots.iter().map(|s| s.size).sum();
 
-                    if !yes {
-                        println!("Delete profile '{}'? ({:.1} GB)", name, size_gb);
-                        println!("This will permanently remove: {}", prefix_dir.display());
-                        print!("\nType 'yes' to confirm: ");
-                        use std::io::Write;
-                        std::io::stdout().flush()?;
+                    let mut plan = vec![format!("  {} ({})", prefix_dir.display(), format_size(size))];
+                    if !keep_snapshots {
+                        plan.extend(snapshots.iter().map(|s| format!("  {} ({})", s.path.display(), format_size(s.size))));
+                    }
+                    let freed = if keep_snapshots { size } else { size + snapshots_size };
+                    let note = match (snapshots.len(), keep_snapshots) {
+                        (0, _) => None,
+                        (n, true) => Some(format!("Keeping {} snapshot(s) ({})", n, format_size(snapshots_size))),
+                        (_, false) => Some("Use --keep-snapshots to delete only the prefix".to_string()),
+                    };
 
-                        let mut input = String::new();
-                        std::io::stdin().read_line(&mut input)?;
+                    if dry_run {
+                        println!("Would delete profile '{}' ({} freed):", name, format_size(freed));
+                        for line in &plan {
+                            println!("{}", line);
+                        }
+                        if let Some(note) = &note {
+                            println!("{}", note);
+                        }
+                        return Ok(());
+                    }
 
-                        if input.trim() != "yes" {
-                            println!("Cancelled");
+                    if !assume_yes {
+                        eprintln!("Delete profile '{}'? ({} freed)", name, format_size(freed));
+                        eprintln!("This will permanently remove:");
+                        for line in &plan {
+                            eprintln!("{}", line);
+                        }
+                        if let Some(note) = &note {
+                            eprintln!("{}", note);
+                        }
+                        if !confirm()? {
+                            eprintln!("Cancelled");
                             return Ok(());
                         }
                     }
 
-                    println!("Deleting profile '{}'...", name);
+                    say!("Deleting profile '{}'...", name);
+                    stop_wine(&ctx, &prefix_dir).await;
                     std::fs::remove_dir_all(&prefix_dir)?;
 
-                    // Also remove any snapshots for this profile
-                    if snapshots_dir.exists() {
-                        for entry in std::fs::read_dir(&snapshots_dir)?.flatten() {
-                            let fname = entry.file_name().to_string_lossy().to_string();
-                            if fname.starts_with(&format!("{}-", name)) || fname.starts_with(&format!("eve-{}-", name)) {
-                                std::fs::remove_file(entry.path())?;
-                                println!("  Removed snapshot: {}", fname);
-                            }
+                    if !keep_snapshots {
+                        for s in &snapshots {
+                            elm_core::rollback::snapshot::delete_snapshot(&s.path)?;
+                            say!("  Removed snapshot: {}", s.name);
                         }
                     }
 
-                    println!("✓ Profile '{}' deleted ({:.1} GB freed)", name, size_gb);
+                    say!("✓ Profile '{}' deleted ({} freed)", name, format_size(freed));
                 }
                 ProfileCmd::Info { name } => {
-                    let prefix_dir = prefixes_dir.join(format!("eve-{}", name));
+                    let prefix_dir = ctx.profile_prefix(&name);
 
                     if !prefix_dir.exists() {
-                        println!("Profile '{}' not found", name);
-                        return Ok(());
+                        return Err(anyhow::anyhow!("Profile '{}' not found", name));
                     }
 
-                    println!("Profile: {}", name);
-                    println!("=========={}", "=".repeat(name.len()));
-                    println!();
+                    let meta = elm_core::prefix::profile::load(&prefix_dir)?;
+                    say!("Profile: {}", meta.display_name);
+                    say!("=========={}", "=".repeat(meta.display_name.chars().count()));
+                    say!();
 
                     // Size
-                    let size = dir_size(&prefix_dir).unwrap_or(0);
-                    println!("Size:     {:.2} GB", size as f64 / 1_073_741_824.0);
-                    println!("Path:     {}", prefix_dir.display());
+                    let size = elm_core::system::disk::usage(&prefix_dir);
+                    say!("Size:     {:.2} GB on disk", size as f64 / 1_073_741_824.0);
+                    say!("Path:     {}", prefix_dir.display());
+                    say!("Created:  {}", format_age(unix_time(meta.created)));
+                    say!("Engine:   {}", meta.engine_ref.as_deref().unwrap_or("(unknown)"));
+                    match meta.last_launched {
+                        Some(t) => say!("Launched: {}", format_age(unix_time(t))),
+                        None => say!("Launched: never"),
+                    }
 
                     // EVE status
                     let eve_path = prefix_dir.join("pfx/drive_c/CCP/EVE");
                     if eve_path.exists() {
-                        println!("EVE:      ✓ installed");
+                        say!("EVE:      ✓ installed");
 
                         // Check for game client
                         let client_path = eve_path.join("tq/bin64/exefile.exe");
                         if client_path.exists() {
-                            println!("Client:   ✓ downloaded");
+                            say!("Client:   ✓ downloaded");
                         } else {
-                            println!("Client:   ○ not downloaded (run game once)");
+                            say!("Client:   ○ not downloaded (run game once)");
                         }
                     } else {
-                        println!("EVE:      ○ not installed");
+                        say!("EVE:      ○ not installed");
+                    }
+
+                    // Where the space goes
+                    let drive_c = prefix_dir.join("pfx/drive_c");
+                    let largest = largest_dirs(&drive_c, 5);
+                    if !largest.is_empty() {
+                        say!();
+                        say!("Largest folders:");
+                        for (path, size) in &largest {
+                            let rel = path.strip_prefix(prefix_dir.join("pfx")).unwrap_or(path);
+                            say!("  {:>10}  {}", format_size(*size), rel.display());
+                        }
+                    }
+                    let caches = elm_core::prefix::cache::find_caches(&prefix_dir);
+                    if !caches.is_empty() {
+                        say!();
+                        say!("Safe to clear with 'elm profile trim {}':", name);
+                        for (kind, size) in elm_core::prefix::cache::totals(&caches).iter().filter(|(_, size)| *size > 0) {
+                            say!("  {:>10}  {}", format_size(*size), kind.label());
+                        }
                     }
 
                     // Snapshots
-                    println!();
-                    println!("Snapshots:");
-                    let mut found_snapshots = false;
-                    if snapshots_dir.exists() {
-                        for entry in std::fs::read_dir(&snapshots_dir)?.flatten() {
-                            let fname = entry.file_name().to_string_lossy().to_string();
-                            if fname.contains(&name) && fname.ends_with(".tar.zst") {
-                                let size = std::fs::metadata(entry.path()).map(|m| m.len()).unwrap_or(0);
-                                println!("  {} ({:.1} GB)", fname, size as f64 / 1_073_741_824.0);
-                                found_snapshots = true;
-                            }
+                    say!();
+                    say!("Snapshots:");
+                    let snapshots = elm_core::rollback::snapshot::snapshots_for_profile(&snapshots_dir, &name)?;
+                    for s in &snapshots {
+                        say!("  {}.tar.zst ({:.1} GB)", s.name, s.size as f64 / 1_073_741_824.0);
+                    }
+                    if snapshots.is_empty() {
+                        say!("  (none)");
+                    }
+
+                    say!();
+                    say!("Commands:");
+                    say!("  elm run --profile {}           # Launch EVE", name);
+                    say!("  elm snapshot --prefix {} \\", prefix_dir.display());
+                    say!("    --snapshots {} --name {}-backup", snapshots_dir.display(), name);
+                }
+                ProfileCmd::Trim { name, dry_run } => {
+                    let prefix_dir = ctx.profile_prefix(&name);
+
+                    if !prefix_dir.exists() {
+                        return Err(anyhow::anyhow!("Profile '{}' not found", name));
+                    }
+                    if !elm_core::runtime::session::find(&ctx.data_dir, &name)?.is_empty() {
+                        return Err(anyhow::anyhow!("Profile '{}' is running; stop it first: elm stop {}", name, name));
+                    }
+
+                    let caches = elm_core::prefix::cache::find_caches(&prefix_dir);
+                    let total: u64 = caches.iter().map(|c| c.size).sum();
+                    if caches.is_empty() {
+                        say!("Nothing to trim in profile '{}'", name);
+                        return Ok(());
+                    }
+
+                    let plan: Vec<String> = caches
+                        .iter()
+                        .map(|c| {
+                            let rel = c.path.strip_prefix(prefix_dir.join("pfx")).unwrap_or(&c.path);
+                            format!("  {:>10}  {} [{}]", format_size(c.size), rel.display(), c.kind.label())
+                        })
+                        .collect();
+                    if dry_run {
+                        println!("Would clear {} from profile '{}':", format_size(total), name);
+                        for line in &plan {
+                            println!("{}", line);
                         }
+                        return Ok(());
                     }
-                    if !found_snapshots {
-                        println!("  (none)");
+
+                    if !assume_yes {
+                        eprintln!("Clear {} of caches from profile '{}'? EVE downloads or rebuilds them as needed:", format_size(total), name);
+                        for line in &plan {
+                            eprintln!("{}", line);
+                        }
+                        if !confirm()? {
+                            eprintln!("Cancelled");
+                            return Ok(());
+                        }
                     }
 
-                    println!();
-                    println!("Commands:");
-                    println!("  elm run --profile {}           # Launch EVE", name);
-                    println!("  elm snapshot --prefix {} \\", prefix_dir.display());
-                    println!("    --snapshots {} --name {}-backup", snapshots_dir.display(), name);
+                    stop_wine(&ctx, &prefix_dir).await;
+                    let freed = elm_core::prefix::cache::clear(&caches)?;
+                    say!("✓ Freed {} from profile '{}'", format_size(freed), name);
                 }
                 ProfileCmd::Clone { source, target } => {
-                    let source_dir = prefixes_dir.join(format!("eve-{}", source));
-                    let target_dir = prefixes_dir.join(format!("eve-{}", target));
+                    let source_dir = ctx.profile_prefix(&source);
+                    let target_dir = ctx.profile_prefix(&target);
 
                     if !source_dir.exists() {
-                        println!("Source profile '{}' not found", source);
-                        return Ok(());
+                        return Err(anyhow::anyhow!("Source profile '{}' not found", source));
                     }
 
                     if target_dir.exists() {
-                        println!("Target profile '{}' already exists", target);
-                        return Ok(());
+                        return Err(anyhow::anyhow!("Target profile '{}' already exists", target));
                     }
 
                     let source_size = dir_size(&source_dir).unwrap_or(0);
-                    println!("Cloning profile '{}' to '{}'...", source, target);
-                    println!("Size: {:.2} GB", source_size as f64 / 1_073_741_824.0);
-                    println!();
+                    say!("Cloning profile '{}' to '{}'...", source, target);
+                    say!("Size: {:.2} GB", source_size as f64 / 1_073_741_824.0);
+                    say!();
+
+                    // Reflink on Btrfs/XFS, full cp -a elsewhere
+                    let started = std::time::Instant::now();
+                    let mut reported = false;
+                    let method = elm_core::system::filesystem::copy_dir(&source_dir, &target_dir, |copied| {
+                        reported = true;
+                        let percent = (copied * 100).checked_div(source_size).unwrap_or(100).min(100);
+                        say_inline!("\r  Copied {} of {} ({}%)   ", format_size(copied), format_size(source_size), percent);
+                        let _ = std::io::Write::flush(&mut std::io::stdout());
+                    })
+                    .context("Failed to clone profile")?;
+                    if reported {
+                        say!();
+                    }
+                    elm_core::prefix::profile::rename(&target_dir, &target)?;
+
+                    let how = match method {
+                        elm_core::system::filesystem::CopyMethod::Reflink => "reflink copy, no extra space used",
+                        elm_core::system::filesystem::CopyMethod::Full => "full copy",
+                    };
+                    say!("✓ Profile '{}' cloned to '{}' ({}, {:.1}s)", source, target, how, started.elapsed().as_secs_f64());
+                    say!("\nLaunch with: elm run --profile {}", target);
+                }
+                ProfileCmd::Config { name } => {
+                    let shared_path = ctx.shared_manifest_path();
+                    let manifest_path = ctx.profile_manifest_path(&name);
+
+                    if !manifest_path.exists() {
+                        if !shared_path.exists() {
+                            return Err(anyhow::anyhow!(
+                                "No shared manifest to start from at {} (run: elm config init)",
+                                shared_path.display()
+                            ));
+                        }
+                        std::fs::copy(&shared_path, &manifest_path)
+                            .with_context(|| format!("creating {}", manifest_path.display()))?;
+                        say!("Created {} from {}", manifest_path.display(), shared_path.display());
+                    }
+
+                    if open_in_editor(&manifest_path)? {
+                        say!("Config saved. 'elm run --profile {}' now uses it instead of the shared manifest.", name);
+                    } else {
+                        eprintln!("⚠ Editor exited with error");
+                    }
+
+                    let problems = elm_core::config::validate::check_manifest_file(&manifest_path)?;
+                    if !problems.is_empty() {
+                        eprintln!("\nWarning: config has {} problem(s):", problems.len());
+                        for p in &problems {
+                            eprintln!("  ✗ {}", p);
+                        }
+                        eprintln!("\nRun 'elm profile config {}' again to fix", name);
+                    }
+                }
+                ProfileCmd::Export { name, out } => {
+                    use elm_core::rollback::bundle::{export_profile, BUNDLE_EXTENSION};
+                    use elm_core::rollback::snapshot::{BundleConfig, SnapshotMetadata};
+
+                    let prefix_dir = ctx.profile_prefix(&name);
+                    if !prefix_dir.exists() {
+                        return Err(anyhow::anyhow!("Profile '{}' not found", name));
+                    }
+
+                    let out = out.unwrap_or_else(|| PathBuf::from(format!("{}.{}", name, BUNDLE_EXTENSION)));
+                    if out.exists() && !assume_yes {
+                        eprintln!("{} already exists. Overwrite?", out.display());
+                        if !confirm()? {
+                            eprintln!("Cancelled");
+                            return Ok(());
+                        }
+                    }
+
+                    let read_json = |path: PathBuf| -> Result<Option<serde_json::Value>> {
+                        if !path.exists() {
+                            return Ok(None);
+                        }
+                        Ok(Some(elm_core::config::load::read_config(&path)?))
+                    };
+                    let manifest = match ctx.manifest_path(&name) {
+                        Some(path) => read_json(path)?,
+                        None => None,
+                    };
+                    let profile = read_json(ctx.profile_config_path(&name))?;
+                    let engine = ctx.engine_for_profile(&name)?;
+
+                    let mut metadata = SnapshotMetadata::new(&prefix_dir, Some(engine), Some(name.clone()));
+                    metadata.bundle = Some(BundleConfig { manifest, profile });
+
+                    let size = dir_size(&prefix_dir).unwrap_or(0);
+                    say!("Exporting profile '{}' ({:.2} GB)...", name, size as f64 / 1_073_741_824.0);
+                    export_profile(&prefix_dir, &out, &metadata)?;
+
+                    let bundle_size = std::fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
+                    say!("✓ Exported to {} ({:.2} GB)", out.display(), bundle_size as f64 / 1_073_741_824.0);
+                    say!("\nOn the other machine: elm profile import {}", out.display());
+                }
+                ProfileCmd::Import { file, name } => {
+                    use elm_core::rollback::snapshot::read_metadata;
+
+                    if !file.exists() {
+                        return Err(anyhow::anyhow!("Bundle not found: {}", file.display()));
+                    }
+                    let name = match name {
+                        Some(name) => name,
+                        None => read_metadata(&file)?.and_then(|m| m.profile).ok_or_else(|| {
+                            anyhow::anyhow!("bundle doesn't record a profile name; pass one: elm profile import <file> <name>")
+                        })?,
+                    };
+                    let prefix_dir = ctx.profile_prefix(&name);
+                    if prefix_dir.exists() {
+                        return Err(anyhow::anyhow!("Profile '{}' already exists", name));
+                    }
 
-                    // Use cp -a for preserving symlinks and attributes
-                    let status = std::process::Command::new("cp")
-                        .args(["-a", source_dir.to_str().unwrap(), target_dir.to_str().unwrap()])
-                        .status()?;
+                    say!("Importing profile '{}' from {}...", name, file.display());
+                    let active = ctx.active_engine();
+                    let metadata = elm_core::rollback::bundle::import_profile(&file, &prefix_dir, Some(&active))?;
+                    elm_core::prefix::profile::rename(&prefix_dir, &name)?;
+                    say!("✓ Prefix restored to {}", prefix_dir.display());
 
-                    if !status.success() {
-                        return Err(anyhow::anyhow!("Failed to clone profile"));
+                    let write_json = |path: &std::path::Path, value: &serde_json::Value| -> Result<()> {
+                        if let Some(parent) = path.parent() {
+                            std::fs::create_dir_all(parent)?;
+                        }
+                        std::fs::write(path, serde_json::to_string_pretty(value)? + "\n")
+                            .with_context(|| format!("writing {}", path.display()))
+                    };
+                    let config = metadata.bundle.clone().unwrap_or(elm_core::rollback::snapshot::BundleConfig {
+                        manifest: None,
+                        profile: None,
+                    });
+
+                    // Never clobber the target machine's own manifest
+                    let manifest_path = ctx.shared_manifest_path();
+                    if let Some(manifest) = &config.manifest {
+                        if manifest_path.exists() {
+                            say!("  ○ Kept existing manifest {}", manifest_path.display());
+                        } else {
+                            write_json(&manifest_path, manifest)?;
+                            say!("✓ Manifest written to {}", manifest_path.display());
+                        }
                     }
 
-                    println!("✓ Profile '{}' cloned to '{}'", source, target);
-                    println!("\nLaunch with: elm run --profile {}", target);
+                    if let Some(mut profile) = config.profile.clone() {
+                        let profile_path = ctx.profile_config_path(&name);
+                        if profile_path.exists() {
+                            eprintln!("Warning: {} exists; bundled profile config not applied", profile_path.display());
+                        } else {
+                            if let Some(obj) = profile.as_object_mut() {
+                                obj.insert("name".to_string(), serde_json::Value::String(name.clone()));
+                            }
+                            write_json(&profile_path, &profile)?;
+                            say!("✓ Profile config written to {}", profile_path.display());
+                        }
+                    }
+
+                    if let Some(engine) = &metadata.engine_id {
+                        let engine_paths = elm_core::engine::install::engine_paths(&ctx.engines_dir(), engine);
+                        if engine_paths.marker.exists() {
+                            say!("✓ Engine {} is installed", engine);
+                        } else {
+                            eprintln!(
+                                "Warning: engine '{}' used by this profile is not installed (run: elm update --install)",
+                                engine
+                            );
+                        }
+                    }
+
+                    say!("✓ Profile '{}' imported", name);
+                    say!("\nLaunch with: elm run --profile {}", name);
                 }
             }
         }
         Commands::Config { cmd } => {
-            let home = std::env::var("HOME").unwrap_or_default();
-            let config_dir = PathBuf::from(format!("{home}/.config/elm"));
+            let config_dir = ctx.config_dir.clone();
             let manifests_dir = config_dir.join("manifests");
 
             match cmd {
                 ConfigCmd::Init { force } => {
-                    println!("Initializing ELM configuration...\n");
+                    say!("Initializing ELM configuration...\n");
 
                     // Create directories
                     std::fs::create_dir_all(&manifests_dir)?;
 
-                    // Default manifest
+                    // Default manifest. A YAML or TOML one counts as existing; --force
+                    // writes the JSON default, which takes precedence over it
+                    let existing = ctx.shared_manifest_path();
                     let manifest_path = manifests_dir.join("eve-online.json");
-                    if manifest_path.exists() && !force {
-                        println!("  ○ {} (exists, use --force to overwrite)", manifest_path.display());
+                    if existing.exists() && !force {
+                        say!("  ○ {} (exists, use --force to overwrite)", existing.display());
                     } else {
                         let default_manifest = r#"{
   "schema": "elm.manifest.v1",
@@ -1401,62 +2209,61 @@ async fn main() -> Result<()> {
   }
 }
 "#;
+                        if let Some(bak) = elm_core::config::backup::backup_file(&manifest_path)? {
+                            say!("  ✓ Backed up to {}", bak.display());
+                        }
                         std::fs::write(&manifest_path, default_manifest)?;
-                        println!("  ✓ Created {}", manifest_path.display());
+                        say!("  ✓ Created {}", manifest_path.display());
                     }
 
-                    println!("\nConfiguration initialized!");
-                    println!("\nEdit your config:");
-                    println!("  elm config edit");
-                    println!("\nOr manually edit:");
-                    println!("  {}", manifest_path.display());
+                    say!("\nConfiguration initialized!");
+                    say!("\nEdit your config:");
+                    say!("  elm config edit");
+                    say!("\nOr manually edit:");
+                    say!("  {}", manifest_path.display());
                 }
                 ConfigCmd::Show => {
-                    println!("ELM Configuration");
-                    println!("=================\n");
+                    say!("ELM Configuration");
+                    say!("=================\n");
 
-                    println!("Config directory: {}", config_dir.display());
-                    println!();
+                    say!("Config directory: {}", config_dir.display());
+                    say!();
 
-                    println!("Files:");
-                    let manifest_path = manifests_dir.join("eve-online.json");
+                    say!("Files:");
+                    let manifest_path = ctx.shared_manifest_path();
                     if manifest_path.exists() {
                         let size = std::fs::metadata(&manifest_path).map(|m| m.len()).unwrap_or(0);
-                        println!("  ✓ {} ({} bytes)", manifest_path.display(), size);
+                        say!("  ✓ {} ({} bytes)", manifest_path.display(), size);
                     } else {
-                        println!("  ○ {} (not found)", manifest_path.display());
-                        println!("\n  Run 'elm config init' to create default configs");
+                        say!("  ○ {} (not found)", manifest_path.display());
+                        say!("\n  Run 'elm config init' to create default configs");
                     }
 
                     // Show current settings if config exists
-                    if manifest_path.exists() {
-                        if let Ok(content) = std::fs::read_to_string(&manifest_path) {
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                                println!();
-                                println!("Current settings:");
-                                if let Some(engine) = json.get("engine").and_then(|e| e.get("ref")) {
-                                    println!("  Engine: {}", engine.as_str().unwrap_or("?"));
+                    if let Ok(json) = elm_core::config::load::read_config(&manifest_path) {
+                        say!();
+                        say!("Current settings:");
+                        if let Some(engine) = json.get("engine").and_then(|e| e.get("ref")) {
+                            say!("  Engine: {}", engine.as_str().unwrap_or("?"));
+                        }
+                        if let Some(env) = json.get("env").and_then(|e| e.get("base")) {
+                            if let Some(obj) = env.as_object() {
+                                say!("  Environment variables: {}", obj.len());
+                                for (k, v) in obj.iter().take(5) {
+                                    say!("    {}={}", k, v.as_str().unwrap_or("?"));
                                 }
-                                if let Some(env) = json.get("env").and_then(|e| e.get("base")) {
-                                    if let Some(obj) = env.as_object() {
-                                        println!("  Environment variables: {}", obj.len());
-                                        for (k, v) in obj.iter().take(5) {
-                                            println!("    {}={}", k, v.as_str().unwrap_or("?"));
-                                        }
-                                        if obj.len() > 5 {
-                                            println!("    ... and {} more", obj.len() - 5);
-                                        }
-                                    }
+                                if obj.len() > 5 {
+                                    say!("    ... and {} more", obj.len() - 5);
                                 }
                             }
                         }
                     }
                 }
                 ConfigCmd::Edit => {
-                    let manifest_path = manifests_dir.join("eve-online.json");
+                    let manifest_path = ctx.shared_manifest_path();
 
                     if !manifest_path.exists() {
-                        println!("Config file not found. Creating with defaults...");
+                        say!("Config file not found. Creating with defaults...");
                         // Trigger init
                         std::fs::create_dir_all(&manifests_dir)?;
                         let default_manifest = r#"{
@@ -1476,90 +2283,43 @@ async fn main() -> Result<()> {
                         std::fs::write(&manifest_path, default_manifest)?;
                     }
 
-                    // Open in editor
-                    let editor = std::env::var("EDITOR")
-                        .or_else(|_| std::env::var("VISUAL"))
-                        .unwrap_or_else(|_| "nano".to_string());
-
-                    println!("Opening {} in {}...", manifest_path.display(), editor);
-
-                    let status = std::process::Command::new(&editor)
-                        .arg(&manifest_path)
-                        .status()?;
-
-                    if status.success() {
-                        println!("Config saved.");
+                    if open_in_editor(&manifest_path)? {
+                        say!("Config saved.");
                     } else {
-                        println!("Editor exited with error");
+                        eprintln!("⚠ Editor exited with error");
                     }
-                }
-                ConfigCmd::Preset { name } => {
-                    let manifest_path = manifests_dir.join("eve-online.json");
 
-                    // Define presets
-                    let presets: std::collections::HashMap<&str, serde_json::Value> = [
-                        ("performance", serde_json::json!({
-                            "DXVK_ASYNC": "1",
-                            "PROTON_NO_ESYNC": "0",
-                            "PROTON_NO_FSYNC": "0",
-                            "DXVK_HUD": "",
-                            "WINE_FULLSCREEN_FSR": "1",
-                            "WINE_FULLSCREEN_FSR_STRENGTH": "2",
-                            "PROTON_ENABLE_NVAPI": "1",
-                            "VKD3D_FEATURE_LEVEL": "12_1",
-                            "__GL_SHADER_DISK_CACHE": "1",
-                            "__GL_SHADER_DISK_CACHE_SKIP_CLEANUP": "1"
-                        })),
-                        ("quality", serde_json::json!({
-                            "DXVK_ASYNC": "0",
-                            "PROTON_NO_ESYNC": "0",
-                            "PROTON_NO_FSYNC": "0",
-                            "DXVK_HUD": "",
-                            "WINE_FULLSCREEN_FSR": "0",
-                            "PROTON_ENABLE_NVAPI": "1",
-                            "VKD3D_FEATURE_LEVEL": "12_1",
-                            "__GL_SHADER_DISK_CACHE": "1"
-                        })),
-                        ("balanced", serde_json::json!({
-                            "DXVK_ASYNC": "1",
-                            "PROTON_NO_ESYNC": "0",
-                            "PROTON_NO_FSYNC": "0",
-                            "DXVK_HUD": "fps",
-                            "WINE_FULLSCREEN_FSR": "1",
-                            "PROTON_ENABLE_NVAPI": "1",
-                            "VKD3D_FEATURE_LEVEL": "12_1"
-                        })),
-                        ("debug", serde_json::json!({
-                            "DXVK_ASYNC": "1",
-                            "PROTON_NO_ESYNC": "0",
-                            "PROTON_NO_FSYNC": "0",
-                            "DXVK_HUD": "fps,frametimes,gpuload,devinfo",
-                            "DXVK_LOG_LEVEL": "info",
-                            "PROTON_LOG": "1",
-                            "WINEDEBUG": "warn+all"
-                        }))
-                    ].into_iter().collect();
-
-                    let preset_name = name.to_lowercase();
-                    let preset = match presets.get(preset_name.as_str()) {
-                        Some(p) => p,
-                        None => {
-                            println!("Unknown preset: {}", name);
-                            println!("\nAvailable presets:");
-                            println!("  performance  - Maximum FPS, FSR upscaling, minimal HUD");
-                            println!("  quality      - Native resolution, no async shaders");
-                            println!("  balanced     - Good performance with FPS counter");
-                            println!("  debug        - Verbose logging for troubleshooting");
-                            return Ok(());
+                    // Catch a broken edit now rather than at launch
+                    let problems = elm_core::config::validate::check_manifest_file(&manifest_path)?;
+                    if !problems.is_empty() {
+                        eprintln!("\nWarning: config has {} problem(s):", problems.len());
+                        for p in &problems {
+                            eprintln!("  ✗ {}", p);
                         }
+                        eprintln!("\nRun 'elm config edit' again to fix, or 'elm config validate' to re-check");
+                    }
+                }
+                ConfigCmd::Preset { name, list, diff } => {
+                    let manifest_path = ctx.shared_manifest_path();
+                    let presets = elm_core::config::presets::load_presets(&config_dir)?;
+
+                    if list || name.is_none() {
+                        for p in &presets {
+                            let source = if p.builtin { "builtin" } else { "user" };
+                            println!("  {:<12} [{:<7}] {}", p.name, source, p.description);
+                        }
+                        return Ok(());
+                    }
+
+                    let name = name.unwrap_or_default();
+                    let Some(preset) = elm_core::config::presets::find(&presets, &name) else {
+                        return Err(anyhow::anyhow!("Unknown preset '{}' (see: elm config preset --list)", name));
                     };
 
                     // Load existing config or create new
                     let mut config: serde_json::Value = if manifest_path.exists() {
-                        let content = std::fs::read_to_string(&manifest_path)?;
-                        serde_json::from_str(&content)?
+                        elm_core::config::load::read_config(&manifest_path)?
                     } else {
-                        std::fs::create_dir_all(&manifests_dir)?;
                         serde_json::json!({
                             "schema": "elm.manifest.v1",
                             "id": "eve-online",
@@ -1569,29 +2329,147 @@ async fn main() -> Result<()> {
                         })
                     };
 
-                    // Update env.base with preset values
-                    if let Some(env) = config.get_mut("env") {
-                        if let Some(base) = env.get_mut("base") {
-                            if let Some(base_obj) = base.as_object_mut() {
-                                if let Some(preset_obj) = preset.as_object() {
-                                    for (k, v) in preset_obj {
-                                        base_obj.insert(k.clone(), v.clone());
-                                    }
-                                }
+                    if diff {
+                        let changes = elm_core::config::presets::diff(&config, preset);
+                        say!("'{}' preset vs {}:\n", preset.name, manifest_path.display());
+                        if changes.is_empty() {
+                            say!("  (no changes)");
+                        }
+                        for c in &changes {
+                            match &c.before {
+                                Some(before) => println!("  ~ {}: {:?} -> {:?}", c.key, before, c.after),
+                                None => println!("  + {}={:?}", c.key, c.after),
                             }
                         }
+                        return Ok(());
                     }
 
+                    // Update env.base with preset values
+                    elm_core::config::presets::apply(&mut config, preset);
+
                     // Write updated config
-                    let pretty = serde_json::to_string_pretty(&config)?;
-                    std::fs::write(&manifest_path, pretty)?;
-
-                    println!("Applied '{}' preset to {}", preset_name, manifest_path.display());
-                    println!("\nSettings:");
-                    if let Some(preset_obj) = preset.as_object() {
-                        for (k, v) in preset_obj {
-                            println!("  {}={}", k, v.as_str().unwrap_or("?"));
+                    std::fs::create_dir_all(&manifests_dir)?;
+                    let backup = elm_core::config::backup::backup_file(&manifest_path)?;
+                    let content = elm_core::config::load::Format::of(&manifest_path).serialize(&config)?;
+                    std::fs::write(&manifest_path, content)?;
+
+                    say!("Applied '{}' preset to {}", preset.name, manifest_path.display());
+                    if let Some(bak) = backup {
+                        say!("Previous config saved to {}", bak.display());
+                    }
+                    say!("\nSettings:");
+                    for (k, v) in &preset.env {
+                        say!("  {}={}", k, v);
+                    }
+                }
+                ConfigCmd::Validate => {
+                    let manifest_path = ctx.shared_manifest_path();
+
+                    if !manifest_path.exists() {
+                        return Err(anyhow::anyhow!(
+                            "Config file not found: {} (run 'elm config init' to create default configs)",
+                            manifest_path.display()
+                        ));
+                    }
+
+                    let problems = elm_core::config::validate::check_manifest_file(&manifest_path)?;
+                    if problems.is_empty() {
+                        let manifest: elm_core::config::models::ManifestV1 =
+                            serde_json::from_value(elm_core::config::load::read_config(&manifest_path)?)?;
+                        let engines_dir = ctx.engines_dir();
+                        let issues = elm_core::config::validate::validate_manifest_semantics(&manifest, &engines_dir);
+                        report_semantic_issues(&manifest_path, &issues)?;
+                        say!("✓ {} is valid", manifest_path.display());
+                    } else {
+                        say!("✗ {}\n", manifest_path.display());
+                        for p in &problems {
+                            eprintln!("  {}", p);
+                        }
+                        return Err(anyhow::anyhow!("config has {} problem(s)", problems.len()));
+                    }
+                }
+                ConfigCmd::Restore { backup, list } => {
+                    let manifest_path = ctx.shared_manifest_path();
+                    let backups = elm_core::config::backup::list_backups(&manifest_path)?;
+
+                    if list {
+                        say!("Backups of {}:\n", manifest_path.display());
+                        if backups.is_empty() {
+                            say!("  (none)");
                         }
+                        for path in &backups {
+                            let age = std::fs::metadata(path)
+                                .and_then(|m| m.modified())
+                                .map(format_age)
+                                .unwrap_or_else(|_| "?".to_string());
+                            say!("  {} ({})", path.display(), age);
+                        }
+                        return Ok(());
+                    }
+
+                    let source = match backup {
+                        Some(p) => p,
+                        None => match backups.first() {
+                            Some(p) => p.clone(),
+                            None => {
+                                return Err(anyhow::anyhow!("No backups found for {}", manifest_path.display()));
+                            }
+                        },
+                    };
+
+                    elm_core::config::backup::restore_backup(&manifest_path, &source)?;
+                    say!("✓ Restored {} from {}", manifest_path.display(), source.display());
+                }
+                ConfigCmd::Migrate { dry_run } => {
+                    let mut files: Vec<PathBuf> = ["manifests", "profiles", "channels"]
+                        .iter()
+                        .filter_map(|dir| std::fs::read_dir(config_dir.join(dir)).ok())
+                        .flat_map(|entries| entries.flatten().map(|e| e.path()))
+                        .filter(|p| {
+                            p.extension()
+                                .and_then(|ext| ext.to_str())
+                                .is_some_and(|ext| elm_core::config::load::CONFIG_EXTENSIONS.contains(&ext))
+                        })
+                        .collect();
+                    files.sort();
+
+                    let mut failed = 0;
+                    let mut upgraded = 0;
+                    for path in &files {
+                        let result = if dry_run {
+                            elm_core::config::load::read_config(path)
+                                .and_then(|mut doc| elm_core::config::migrate::migrate(&mut doc))
+                                .map(|m| (m, None))
+                        } else {
+                            elm_core::config::migrate::migrate_file(path)
+                        };
+                        match result {
+                            Ok((m, _)) if !m.changed => say!("  ✓ {} ({})", path.display(), m.to),
+                            Ok((m, backup)) => {
+                                upgraded += 1;
+                                say!("  ↑ {} ({} → {})", path.display(), m.from, m.to);
+                                if let Some(backup) = backup {
+                                    say!("    backup: {}", backup.display());
+                                }
+                            }
+                            Err(e) => {
+                                failed += 1;
+                                eprintln!("  ✗ {}: {:#}", path.display(), e);
+                            }
+                        }
+                    }
+
+                    if files.is_empty() {
+                        say!("No config files in {}", config_dir.display());
+                    } else if dry_run {
+                        say!("
+Dry run: {} of {} file(s) would be upgraded", upgraded, files.len());
+                    } else {
+                        say!("
+{} of {} file(s) upgraded", upgraded, files.len());
+                    }
+                    if failed > 0 {
+                        return Err(anyhow::anyhow!("{} file(s) could not be migrated", failed));
                     }
                 }
             }
@@ -1599,52 +2477,694 @@ async fn main() -> Result<()> {
         Commands::Engine { cmd } => match cmd {
             EngineCmd::Install { schemas, engine, engines_dir, downloads_dir } => {
                 let e = elm_core::config::load::load_engine(&engine, &schemas)?;
-                let dist = elm_core::engine::install::ensure_engine_installed(&e, &engines_dir, &downloads_dir)?;
-                println!("Installed engine dist at: {}", dist.display());
+                let dist = elm_core::engine::install::ensure_engine_installed(&e, &engines_dir, &downloads_dir).await?;
+                say!("Installed engine dist at: {}", dist.display());
             }
         },
         Commands::Prefix { cmd } => match cmd {
             PrefixCmd::Init { proton_root, prefix } => {
-                elm_core::prefix::ensure_prefix_initialized(&prefix, &proton_root).await?;
-                println!("Prefix ready: {}", prefix.display());
+                elm_core::prefix::ensure_prefix_initialized(&prefix, &proton_root, None).await?;
+                say!("Prefix ready: {}", prefix.display());
+            }
+            PrefixCmd::Verify { prefix } => {
+                if !prefix.exists() {
+                    return Err(anyhow::anyhow!("prefix not found: {}", prefix.display()));
+                }
+                let report = elm_core::prefix::verify(&prefix);
+                say!("Prefix: {}\n", prefix.display());
+                for check in &report.checks {
+                    say!("  {} {:<16} {}", if check.ok { "✓" } else { "✗" }, check.name, check.detail);
+                }
+
+                if report.is_ok() {
+                    say!("\n✓ Prefix looks healthy");
+                } else {
+                    let failed = report.failures().count();
+                    say!("\n✗ {} check(s) failed. The prefix is likely damaged (e.g. an interrupted wineboot).", failed);
+                    say!("  Restore a snapshot with 'elm rollback', or delete the prefix and let 'elm run' recreate it.");
+                    return Err(anyhow::anyhow!("prefix verification failed"));
+                }
+            }
+            PrefixCmd::Kill { prefix, proton_root } => {
+                if !prefix.exists() {
+                    return Err(anyhow::anyhow!("prefix not found: {}", prefix.display()));
+                }
+                let proton_root = match proton_root {
+                    Some(root) => root,
+                    None => ctx.proton_root_for_prefix(&prefix)?,
+                };
+                elm_core::prefix::shutdown(&prefix, &proton_root).await?;
+                say!("✓ Stopped Wine in {}", prefix.display());
+            }
+            PrefixCmd::Winetricks { proton_root, prefix, verbs } => {
+                say!("Running winetricks: {}", verbs.join(" "));
+                elm_core::prefix::run_winetricks(&prefix, &proton_root, &verbs).await?;
+                say!("✓ Installed into {}", prefix.display());
             }
         },
         Commands::Install { cmd } => match cmd {
-            InstallCmd::Eve { proton_root, prefix, downloads_dir } => {
-                // Expand ~ in downloads_dir
-                let downloads = if downloads_dir.starts_with("~") {
-                    let home = std::env::var("HOME").unwrap_or_default();
-                    PathBuf::from(downloads_dir.to_string_lossy().replacen("~", &home, 1))
+            InstallCmd::Eve { proton_root, prefix, downloads_dir, silent, timeout } => {
+                let downloads_dir = downloads_dir.unwrap_or_else(|| ctx.downloads_dir());
+                let profile = elm_core::context::profile_from_prefix(&prefix).unwrap_or_else(|| "default".to_string());
+                let manifest = load_profile_manifest(&ctx, &profile).unwrap_or_else(|e| {
+                    eprintln!("Warning: {:#} (using the bundled installer URL)", e);
+                    None
+                });
+                let source = manifest.as_ref().map(|m| &m.installer.source);
+                if silent {
+                    say!("Installing EVE Launcher unattended...");
                 } else {
-                    downloads_dir
-                };
-                let result = elm_core::installer::install_eve_launcher(&prefix, &proton_root, &downloads).await?;
-                println!("EVE installation complete: {}", result.display());
+                    say!("Installing EVE Launcher...");
+                    say!("  Complete the installer GUI when it appears.");
+                }
+                let result = elm_core::installer::install_eve_launcher(
+                    &prefix,
+                    &proton_root,
+                    &downloads_dir,
+                    source,
+                    &elm_core::installer::InstallOptions { silent, timeout: std::time::Duration::from_secs(timeout * 60) },
+                )
+                .await?;
+                say!("EVE installation complete: {}", result.display());
             }
         },
-        Commands::Launch { proton_root, prefix, exe_rel, args } => {
+        Commands::Launch { proton_root, prefix, exe_rel, dry_run, args } => {
             let spec = elm_core::runtime::launch::LaunchSpec {
                 proton_root,
                 prefix_dir: prefix,
                 exe_path_in_prefix: exe_rel,
                 args,
                 env: HashMap::new(),
+                log_path: None,
+                steam_runtime: None,
+            };
+            if dry_run {
+                println!("{}", elm_core::runtime::launch::build_command(&spec)?);
+                return Ok(());
+            }
+            elm_core::runtime::launch::launch(spec).await?.into_result()?;
+        }
+        Commands::Snapshot { cmd: None, prefix, snapshots, name, incremental, exclude, settings_only } => {
+            let (Some(prefix), Some(snapshots), Some(name)) = (prefix, snapshots, name) else {
+                return Err(anyhow::anyhow!("--prefix, --snapshots and --name are required to create a snapshot"));
             };
-            elm_core::runtime::launch::launch(spec).await?;
+            let metadata = elm_core::rollback::snapshot::SnapshotMetadata::new(
+                &prefix,
+                Some(ctx.engine_for_prefix(&prefix)),
+                elm_core::context::profile_from_prefix(&prefix),
+            );
+            let mut options = if settings_only {
+                elm_core::rollback::snapshot::SnapshotOptions::settings_only()
+            } else {
+                elm_core::rollback::snapshot::SnapshotOptions::default()
+            };
+            options.excludes.extend(exclude);
+            let mut totals = elm_core::rollback::snapshot::SnapshotProgress::default();
+            let progress = snapshot_progress("  Archiving ", &mut totals);
+            let out = match incremental {
+                Some(base) => elm_core::rollback::snapshot::snapshot_prefix_incremental(&prefix, &snapshots, &name, &base, &metadata, &options, progress)?,
+                None => elm_core::rollback::snapshot::snapshot_prefix(&prefix, &snapshots, &name, &metadata, &options, progress)?,
+            };
+            if totals.entries > 0 {
+                say!();
+            }
+            say!("Snapshot created: {} ({} entries, {} read)", out.display(), totals.entries, format_size(totals.bytes));
+        }
+        Commands::Snapshot { cmd: Some(cmd), .. } => {
+            let snapshots_dir = ctx.data_dir.join("snapshots");
+            let snapshots = elm_core::rollback::snapshot::list_snapshots(&snapshots_dir)?;
+
+            match cmd {
+                SnapshotCmd::List => {
+                    if snapshots.is_empty() {
+                        say!("No snapshots in {}", snapshots_dir.display());
+                        return Ok(());
+                    }
+                    for s in &snapshots {
+                        let profile = s.profile.as_deref().unwrap_or("-");
+                        let kind = if s.base.is_some() { ", incremental" } else { "" };
+                        println!("  {} [{}] ({}, {}{})", s.name, profile, format_size(s.size), format_age(s.created), kind);
+                    }
+                }
+                SnapshotCmd::Delete { name } => {
+                    let name = name.strip_suffix(".tar.zst").unwrap_or(&name);
+                    let Some(target) = snapshots.iter().find(|s| s.name == name) else {
+                        return Err(anyhow::anyhow!("Snapshot '{}' not found in {}", name, snapshots_dir.display()));
+                    };
+                    let file_name = format!("{}.tar.zst", target.name);
+                    if let Some(dependent) = snapshots.iter().find(|s| s.base.as_deref() == Some(file_name.as_str())) {
+                        return Err(anyhow::anyhow!(
+                            "'{}' is the base of incremental snapshot '{}'; delete that first", target.name, dependent.name));
+                    }
+                    elm_core::rollback::snapshot::delete_snapshot(&target.path)?;
+                    say!("✓ Deleted snapshot '{}' ({})", target.name, format_size(target.size));
+                }
+                SnapshotCmd::Prune { keep, dry_run } => {
+                    let doomed = elm_core::rollback::snapshot::prune_candidates(&snapshots, keep);
+                    if doomed.is_empty() {
+                        say!("Nothing to prune (keeping {} per profile)", keep);
+                        return Ok(());
+                    }
+
+                    let mut freed: u64 = 0;
+                    for s in &doomed {
+                        say!("  {} {} ({})", if dry_run { "○" } else { "✗" }, s.name, format_size(s.size));
+                        if !dry_run {
+                            elm_core::rollback::snapshot::delete_snapshot(&s.path)?;
+                        }
+                        freed += s.size;
+                    }
+                    if dry_run {
+                        say!("\nDry run: would free {}", format_size(freed));
+                    } else {
+                        say!("\nFreed {}", format_size(freed));
+                    }
+                }
+            }
         }
-        Commands::Snapshot { prefix, snapshots, name } => {
-            let out = elm_core::rollback::snapshot::snapshot_prefix(&prefix, &snapshots, &name)?;
-            println!("Snapshot created: {}", out.display());
+        Commands::Rollback { snapshot, prefix, merge, no_update } => {
+            let active = ctx.engine_for_prefix(&prefix);
+            stop_wine(&ctx, &prefix).await;
+            let result = if merge {
+                elm_core::rollback::restore::restore_merge(&snapshot, &prefix, Some(&active))
+            } else {
+                elm_core::rollback::restore::restore_prefix(&snapshot, &prefix, Some(&active))
+            };
+            if let Err(e) = result {
+                if let elm_core::ElmError::CorruptSnapshot(corrupt) = &e {
+                    eprintln!("✗ {}", corrupt);
+                    eprintln!("  Prefix untouched: {}", prefix.display());
+                }
+                return Err(e.into());
+            }
+            say!("Prefix restored: {}", prefix.display());
+
+            // Wine from another engine set this prefix up; until it's updated for
+            // this one, things can break in subtle ways
+            if let Some(recorded) = elm_core::rollback::restore::engine_mismatch(&snapshot, &active)? {
+                eprintln!("⚠ The snapshot was taken under engine {}, but this prefix runs on {}", recorded, active);
+                if no_update {
+                    eprintln!("  Skipped updating the prefix (--no-update)");
+                } else {
+                    use std::io::IsTerminal;
+                    eprintln!("Update the prefix for {} now (wineboot -u)?", active);
+                    if assume_yes || (std::io::stdin().is_terminal() && confirm()?) {
+                        let proton_root = ctx.proton_root(&active)?;
+                        say_inline!("Updating prefix... ");
+                        let _ = std::io::Write::flush(&mut std::io::stdout());
+                        elm_core::prefix::update_prefix(&prefix, &proton_root).await?;
+                        say!("✓");
+                    } else {
+                        eprintln!("  Not updated; if EVE misbehaves, restore again without --no-update");
+                    }
+                }
+            }
         }
-        Commands::Rollback { snapshot, prefix } => {
-            elm_core::rollback::restore::restore_prefix(&snapshot, &prefix)?;
-            println!("Prefix restored: {}", prefix.display());
+        Commands::Man { out_dir } => {
+            std::fs::create_dir_all(&out_dir).with_context(|| format!("create {}", out_dir.display()))?;
+            let mut cmd = Cli::command();
+            cmd.build();
+            let pages = write_man_pages(&cmd, "elm", &out_dir)?;
+            say!("✓ Wrote {} man pages to {}", pages.len(), out_dir.display());
         }
     }
 
     Ok(())
 }
 
+/// Log to stderr: warnings by default, ELM's own info (`-v`) or debug (`-vv`)
+/// messages on request. A set `RUST_LOG` takes precedence over the flag.
+fn init_logging(verbose: u8) {
+    let default = match verbose {
+        0 => "warn",
+        1 => "warn,elm=info,elm_core=info",
+        _ => "warn,elm=debug,elm_core=debug",
+    };
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(default));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .without_time()
+        .with_target(verbose >= 2)
+        .init();
+}
+
+/// clap value parser for path arguments, so `~/...` works even when the shell didn't expand it
+fn path_arg(s: &str) -> std::result::Result<PathBuf, String> {
+    if s.is_empty() {
+        return Err("path cannot be empty".to_string());
+    }
+    Ok(elm_core::context::expand_tilde(PathBuf::from(s)))
+}
+
+/// Write a roff man page for `cmd` and, recursively, each of its subcommands,
+/// named after their path: `elm.1`, `elm-run.1`, `elm-profile-create.1`, ...
+fn write_man_pages(cmd: &clap::Command, name: &str, out_dir: &std::path::Path) -> Result<Vec<PathBuf>> {
+    let path = out_dir.join(format!("{}.1", name));
+    let mut page = Vec::new();
+    clap_mangen::Man::new(cmd.clone()).title(name).render(&mut page)?;
+    std::fs::write(&path, page).with_context(|| format!("writing {}", path.display()))?;
+
+    let mut written = vec![path];
+    for sub in cmd.get_subcommands().filter(|s| s.get_name() != "help" && !s.is_hide_set()) {
+        written.extend(write_man_pages(sub, &format!("{}-{}", name, sub.get_name()), out_dir)?);
+    }
+    Ok(written)
+}
+
+/// clap value parser for `--env KEY=VALUE`
+fn env_arg(s: &str) -> std::result::Result<(String, String), String> {
+    elm_core::runtime::env::parse_assignment(s).map_err(|e| e.to_string())
+}
+
+/// Print semantic validation warnings, and fail if there are errors
+fn report_semantic_issues(
+    path: &std::path::Path,
+    issues: &elm_core::config::validate::SemanticIssues,
+) -> Result<()> {
+    for w in &issues.warnings {
+        eprintln!("⚠ {}", w);
+    }
+    if issues.errors.is_empty() {
+        return Ok(());
+    }
+    say!("✗ {}\n", path.display());
+    for e in &issues.errors {
+        eprintln!("  {}", e);
+    }
+    Err(anyhow::anyhow!("manifest has {} problem(s)", issues.errors.len()))
+}
+
+/// [`ElmContext::load_manifest`], saying whether profile overrides were applied
+fn load_profile_manifest(ctx: &ElmContext, profile: &str) -> Result<Option<elm_core::config::models::ManifestV1>> {
+    let manifest = ctx.load_manifest(profile)?;
+    let overrides = ctx.profile_config_path(profile);
+    if overrides.exists() {
+        match manifest {
+            Some(_) => say!("✓ Profile overrides from {}", overrides.display()),
+            None => eprintln!("Warning: only overrides.env from {} applies (no manifest to override)", overrides.display()),
+        }
+    }
+    Ok(manifest)
+}
+
+/// Cap downloads at `--limit-rate`, else the manifest's `download.limit_rate`
+/// (both KB/s); returns the cap applied. With neither, `ELM_HTTP_LIMIT_RATE`
+/// still applies.
+fn apply_limit_rate(flag: Option<u64>, manifest: Option<&elm_core::config::models::ManifestV1>) -> Option<u64> {
+    let kbps = flag.or_else(|| manifest?.download.as_ref()?.limit_rate)?;
+    elm_core::http::set_limit_rate(Some(kbps.saturating_mul(1024)));
+    Some(kbps)
+}
+
+/// Show a desktop notification; failing to is only a warning
+fn send_notification(notifier: &dyn elm_core::integration::notify::Notifier, summary: &str, body: &str, icon: &str) {
+    let notification = elm_core::integration::notify::Notification {
+        summary: summary.to_string(),
+        body: body.to_string(),
+        icon: icon.to_string(),
+        ..Default::default()
+    };
+    if let Err(e) = notifier.notify(&notification) {
+        eprintln!("⚠ Could not show a notification: {:#}", e);
+    }
+}
+
+/// Launch flags `elm run` and `elm multibox` share
+#[derive(Default)]
+struct LaunchOptions {
+    singularity: bool,
+    dx12: bool,
+    preset: Option<String>,
+    env: Vec<(String, String)>,
+    extra_args: Vec<String>,
+    hud: bool,
+    hud_config: String,
+    fsr: bool,
+    fsr_strength: Option<u8>,
+    gpu: Option<String>,
+    ignore_constraints: bool,
+}
+
+/// What a profile launches with, before the engine, prefix and EVE are set up
+struct LaunchPlan {
+    manifest: Option<elm_core::config::models::ManifestV1>,
+    engine_id: String,
+    exe_rel: PathBuf,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    dx12: bool,
+    dx_source: &'static str,
+    gpu: Option<elm_core::system::gpu::Gpu>,
+}
+
+/// Resolve a profile's manifest, channel, engine, arguments and layered
+/// environment; `manifest_path` is only named in errors
+fn plan_launch(ctx: &ElmContext, profile: &str, manifest_path: &std::path::Path, options: LaunchOptions) -> Result<LaunchPlan> {
+    let LaunchOptions { singularity, dx12, preset, env, extra_args, hud, hud_config, fsr, fsr_strength, gpu, ignore_constraints } = options;
+    let manifest = load_profile_manifest(ctx, profile)?;
+    // The profile's channel (if configured) picked the manifest above and
+    // supplies the engine and settings preset the manifest and flags leave open
+    let channel = ctx.load_channel(profile)?;
+    if let Some(channel) = &channel {
+        say!("✓ Channel: {}", channel.name);
+    }
+
+    // Engine: the profile's pin, else the channel, else the manifest, else defaults
+    let engine_id = ctx.engine_for_profile(profile)?;
+
+    let exe_rel = manifest.as_ref()
+        .and_then(|m| m.launch.entrypoints.first())
+        .and_then(|e| e.path.clone())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("drive_c/users/steamuser/AppData/Local/eve-online/eve-online.exe"));
+
+    // Build launch arguments
+    let mut launch_args: Vec<String> = Vec::new();
+
+    // Server selection (--singularity overrides config)
+    if singularity {
+        launch_args.push("/server:singularity".to_string());
+    } else {
+        // Use config default or tranquility
+        let default_server = manifest.as_ref()
+            .and_then(|m| m.launch.entrypoints.first())
+            .and_then(|e| e.args.as_ref())
+            .and_then(|args| args.iter().find(|a| a.starts_with("/server:")))
+            .cloned()
+            .unwrap_or_else(|| "/server:tranquility".to_string());
+        launch_args.push(default_server);
+    }
+
+    // DX mode: --dx12 flag, else the manifest's runtime.dx.preferred, else DX11
+    let dx_config = manifest.as_ref().map(|m| &m.runtime.dx);
+    let (dx12, dx_source) = match dx_config {
+        _ if dx12 => {
+            if dx_config.map(|d| !d.allow_dx12).unwrap_or(false) {
+                return Err(anyhow::anyhow!(
+                    "--dx12 rejected: {} sets runtime.dx.allow_dx12 = false",
+                    manifest_path.display()
+                ));
+            }
+            (true, "--dx12 flag")
+        }
+        Some(d) if d.preferred == "dx12" && d.allow_dx12 => (true, "manifest"),
+        Some(d) if d.preferred == "dx12" => (false, "manifest prefers dx12 but allow_dx12 is false"),
+        Some(_) => (false, "manifest"),
+        None => (false, "default"),
+    };
+
+    // DX12 mode
+    if dx12 {
+        launch_args.push("/triPlatform:dx12".to_string());
+    }
+
+    // Add any extra user-provided arguments
+    launch_args.extend(extra_args);
+
+    // Environment, lowest precedence first: manifest, preset, profile, --env, flags
+    let mut layers = elm_core::runtime::env::EnvLayers {
+        base: launch_env(manifest.as_ref()),
+        profile: ctx.profile_env(profile)?,
+        cli: env.into_iter().collect(),
+        ..Default::default()
+    };
+    if let Some(name) = &preset {
+        let presets = elm_core::config::presets::load_presets(&ctx.config_dir)?;
+        let preset = elm_core::config::presets::find(&presets, name).ok_or_else(|| {
+            anyhow::anyhow!("unknown preset '{}' (see: elm config preset --list)", name)
+        })?;
+        layers.preset = preset.env.clone().into_iter().collect();
+        say!("✓ Preset: {} (this launch only)", preset.name);
+    } else if let Some(channel) = &channel {
+        let name = &channel.defaults.settings_preset;
+        let presets = elm_core::config::presets::load_presets(&ctx.config_dir)?;
+        match elm_core::config::presets::find(&presets, name) {
+            Some(preset) => {
+                layers.preset = preset.env.clone().into_iter().collect();
+                say!("✓ Preset: {} (from channel {})", preset.name, channel.name);
+            }
+            None => eprintln!("⚠ Channel {} names unknown preset '{}'; launching without it", channel.name, name),
+        }
+    }
+
+    // Enable VKD3D for DX12
+    if dx12 {
+        layers.internal.insert("VKD3D_FEATURE_LEVEL".to_string(), "12_1".to_string());
+    }
+
+    // Enable MangoHud overlay
+    if hud {
+        layers.internal.insert("MANGOHUD".to_string(), "1".to_string());
+        if !hud_config.is_empty() {
+            layers.internal.insert("MANGOHUD_CONFIG".to_string(), hud_config);
+        } else {
+            // Default config: FPS, frametime, GPU/CPU stats
+            layers.internal.insert("MANGOHUD_CONFIG".to_string(),
+                "fps,frametime,gpu_stats,gpu_temp,cpu_stats,cpu_temp,ram,vram".to_string());
+        }
+    }
+
+    // FSR upscaling, overriding whatever the manifest or preset chose
+    if fsr || fsr_strength.is_some() {
+        layers.internal.insert("WINE_FULLSCREEN_FSR".to_string(), "1".to_string());
+    }
+    if let Some(strength) = fsr_strength {
+        layers.internal.insert("WINE_FULLSCREEN_FSR_STRENGTH".to_string(), strength.to_string());
+    }
+
+    // PRIME render offload for hybrid graphics
+    let gpu_choice = if let Some(selector) = &gpu {
+        let gpus = elm_core::system::gpu::enumerate();
+        let chosen = elm_core::system::gpu::select(&gpus, selector)?.clone();
+        layers.internal.extend(elm_core::system::gpu::offload_env(&chosen, &gpus));
+        Some(chosen)
+    } else {
+        None
+    };
+
+    let env_vars = elm_core::runtime::env::resolve(&layers);
+    for warning in elm_core::runtime::env::lint(&env_vars, &engine_id) {
+        eprintln!("⚠ {}", warning);
+    }
+
+    // Refuse to launch on hardware the profile's channel doesn't support
+    if let Some(constraints) = channel.as_ref().and_then(|c| c.constraints.as_ref()) {
+        let system = elm_core::config::constraints::SystemInfo::detect(gpu_choice.as_ref());
+        if let Err(e) = elm_core::config::constraints::check(constraints, &system) {
+            if !ignore_constraints {
+                return Err(e.context("Unsupported system (override with --ignore-constraints)"));
+            }
+            eprintln!("Warning: {:#} (ignored)", e);
+        }
+    }
+
+
+    Ok(LaunchPlan { manifest, engine_id, exe_rel, args: launch_args, env: env_vars, dx12, dx_source, gpu: gpu_choice })
+}
+
+/// Keep Proton logs (PROTON_LOG=1) in the profile's logs dir instead of $HOME,
+/// unless the launch environment already names one; `rotate` moves the last
+/// launch's logs aside first
+fn set_proton_log_dir(env: &mut HashMap<String, String>, prefix_dir: &std::path::Path, rotate: bool) -> Result<()> {
+    if env.contains_key("PROTON_LOG_DIR") {
+        return Ok(());
+    }
+    if rotate {
+        elm_core::prefix::rotate_proton_logs(prefix_dir, 5)?;
+    }
+    let logs_dir = elm_core::prefix::proton_logs_dir(prefix_dir);
+    env.insert("PROTON_LOG_DIR".to_string(), logs_dir.display().to_string());
+    Ok(())
+}
+
+/// Base launch environment: the manifest's env.base (or ELM defaults) plus DXVK/VKD3D toggles
+fn launch_env(manifest: Option<&elm_core::config::models::ManifestV1>) -> HashMap<String, String> {
+    let mut env: HashMap<String, String> = manifest
+        .and_then(|m| m.env.as_ref())
+        .and_then(|e| e.base.clone())
+        .unwrap_or_else(|| [
+            ("DXVK_ASYNC", "1"),
+            ("PROTON_NO_ESYNC", "1"),
+            ("PROTON_NO_FSYNC", "1"),
+        ].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect());
+    if let Some(m) = manifest {
+        env.extend(elm_core::prefix::components_env(&m.runtime.components));
+    }
+    env
+}
+
+/// A discovered log file and where it came from
+struct LogFile {
+    path: PathBuf,
+    modified: std::time::SystemTime,
+    /// launcher, installer, proton, launch, protonfixes or prefix
+    source: &'static str,
+}
+
+/// Log files for a profile (launcher, installer, Proton, Wine), newest first
+fn collect_log_files(prefix_dir: &std::path::Path) -> Vec<LogFile> {
+    let home = std::env::var("HOME").unwrap_or_default();
+    let drive_c = prefix_dir.join("pfx/drive_c");
+    let mut log_files: Vec<LogFile> = Vec::new();
+
+    let mut add = |path: PathBuf, source: &'static str| {
+        if log_files.iter().any(|l| l.path == path) {
+            return;
+        }
+        if let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) {
+            log_files.push(LogFile { path, modified, source });
+        }
+    };
+    let dir_entries = |dir: &std::path::Path| -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default()
+    };
+
+    // EVE Launcher logs
+    let logs_dir = drive_c.join("users/steamuser/AppData/Roaming/EVE Online/logs");
+    for path in dir_entries(&logs_dir) {
+        if path.extension().map(|e| e == "log").unwrap_or(false) {
+            add(path, "launcher");
+        }
+    }
+
+    // Squirrel/installer logs
+    let squirrel_log = drive_c.join("users/steamuser/AppData/Local/eve-online/Squirrel-Update.log");
+    if squirrel_log.exists() {
+        add(squirrel_log, "installer");
+    }
+
+    // PROTON_LOG=1 output: our per-prefix logs dir, PROTON_LOG_DIR if the user set
+    // one, and $HOME where Proton writes by default
+    let mut proton_dirs = vec![elm_core::prefix::proton_logs_dir(prefix_dir)];
+    if let Some(dir) = std::env::var_os("PROTON_LOG_DIR") {
+        proton_dirs.push(PathBuf::from(dir));
+    }
+    if !home.is_empty() {
+        proton_dirs.push(PathBuf::from(&home));
+    }
+    for (i, dir) in proton_dirs.iter().enumerate() {
+        for path in dir_entries(dir) {
+            if elm_core::prefix::is_proton_log(&path) {
+                add(path, "proton");
+            } else if i == 0 && elm_core::prefix::is_launch_log(&path) {
+                add(path, "launch");
+            }
+        }
+    }
+
+    // Proton fixes log
+    let protonfixes_log = PathBuf::from("/tmp/protonfixes_test.log");
+    if protonfixes_log.exists() {
+        add(protonfixes_log, "protonfixes");
+    }
+
+    // Anything else Wine-side apps logged inside the prefix (skipping the Windows tree)
+    let mut stack = vec![(drive_c.clone(), 0usize)];
+    while let Some((dir, depth)) = stack.pop() {
+        for path in dir_entries(&dir) {
+            let Ok(meta) = std::fs::symlink_metadata(&path) else { continue };
+            if meta.is_dir() {
+                if depth < 8 && path != drive_c.join("windows") {
+                    stack.push((path, depth + 1));
+                }
+            } else if meta.is_file() && path.extension().map(|e| e == "log").unwrap_or(false) {
+                add(path, "prefix");
+            }
+        }
+    }
+
+    // Sort by modification time (newest first)
+    log_files.sort_by_key(|l| std::cmp::Reverse(l.modified));
+    log_files
+}
+
+/// Narrow `log_files` to one `--log-type`; `None` for an unknown type
+fn filter_logs(log_files: Vec<LogFile>, log_type: &str) -> Option<Vec<LogFile>> {
+    let filtered = match log_type {
+        "launcher" => log_files.into_iter().filter(|l| {
+            l.source == "launcher"
+                && l.path.file_name().map(|n| n.to_string_lossy().contains("eve-online-launcher")).unwrap_or(false)
+        }).collect(),
+        "wine" | "proton" => log_files.into_iter().filter(|l| {
+            matches!(l.source, "proton" | "launch" | "protonfixes" | "prefix")
+        }).collect(),
+        "squirrel" | "installer" => log_files.into_iter().filter(|l| l.source == "installer").collect(),
+        "all" => log_files,
+        _ => return None,
+    };
+    Some(filtered)
+}
+
+/// Print bytes appended to `path` until interrupted, switching to a newer
+/// log of the same type when the launcher or Proton starts one. With no
+/// `path`, waits for the first log to appear and prints it from the start.
+fn follow_log(mut path: Option<PathBuf>, log_type: &str, prefix_dir: &std::path::Path) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut offset = path.as_ref().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len()).unwrap_or(0);
+    if let Some(p) = &path {
+        say!("\n=== Following {} (Ctrl-C to stop) ===", p.display());
+    }
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+
+        // A newer log of this type means the old one was rotated or a new session started
+        let newest = filter_logs(collect_log_files(prefix_dir), log_type)
+            .and_then(|logs| logs.into_iter().next())
+            .map(|l| l.path);
+        if let Some(newest) = newest {
+            if path.as_ref() != Some(&newest) {
+                say!("\n=== Now following {} ===", newest.display());
+                path = Some(newest);
+                offset = 0;
+            }
+        }
+
+        let Some(path) = &path else { continue };
+        let Ok(len) = std::fs::metadata(path).map(|m| m.len()) else { continue };
+        if len < offset {
+            // Truncated in place: start over from the top
+            say!("\n=== {} truncated ===", path.display());
+            offset = 0;
+        }
+        if len == offset {
+            continue;
+        }
+
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::new();
+        file.take(len - offset).read_to_end(&mut buf)?;
+        offset += buf.len() as u64;
+
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(String::from_utf8_lossy(&buf).as_bytes())?;
+        stdout.flush()?;
+    }
+}
+
+/// Stop a wineserver left running in `prefix` before it's replaced or removed.
+/// Failing to is only a warning: most of the time nothing is running.
+async fn stop_wine(ctx: &ElmContext, prefix: &std::path::Path) {
+    if !prefix.exists() {
+        return;
+    }
+    let result = match ctx.proton_root_for_prefix(prefix) {
+        Ok(root) => elm_core::prefix::shutdown(prefix, &root).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        eprintln!("⚠ Could not stop Wine in {}: {:#}", prefix.display(), e);
+        eprintln!("  Close EVE first if it's still running");
+    }
+}
+
+/// Apparent size of the files under `path`: how much there is to copy or
+/// archive. For the space deleting it frees, see `elm_core::system::disk::usage`.
 fn dir_size(path: &std::path::Path) -> std::io::Result<u64> {
     let mut size = 0;
     if path.is_dir() {
@@ -1661,6 +3181,51 @@ fn dir_size(path: &std::path::Path) -> std::io::Result<u64> {
     Ok(size)
 }
 
+/// The `n` largest directories under `root`. A directory holding more than
+/// half of `root` is split into its own children (a few levels deep), so the
+/// answer is e.g. `CCP/EVE/SharedCache` and `CCP/EVE/tq` rather than just `CCP`.
+fn largest_dirs(root: &std::path::Path, n: usize) -> Vec<(PathBuf, u64)> {
+    let total = dir_size(root).unwrap_or(0);
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            // file_type doesn't follow symlinks, so dosdevices/z: (→ /) is skipped
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                continue;
+            }
+            let size = dir_size(&entry.path()).unwrap_or(0);
+            if size * 2 > total && depth < 4 {
+                pending.push((entry.path(), depth + 1));
+            } else {
+                found.push((entry.path(), size));
+            }
+        }
+    }
+    found.sort_by_key(|d| std::cmp::Reverse(d.1));
+    found.truncate(n);
+    found
+}
+
+/// Snapshot progress callback that redraws `label` followed by a running entry
+/// count and size a few times a second, leaving the final totals in `totals`
+fn snapshot_progress<'a>(
+    label: &'a str,
+    totals: &'a mut elm_core::rollback::snapshot::SnapshotProgress,
+) -> impl FnMut(elm_core::rollback::snapshot::SnapshotProgress) + 'a {
+    let mut last_report: Option<std::time::Instant> = None;
+    move |progress| {
+        *totals = progress;
+        if last_report.is_some_and(|t| t.elapsed() < std::time::Duration::from_millis(250)) {
+            return;
+        }
+        last_report = Some(std::time::Instant::now());
+        say_inline!("\r{}{} entries, {}   ", label, progress.entries, format_size(progress.bytes));
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+}
+
 fn format_size(bytes: u64) -> String {
     if bytes < 1024 {
         format!("{} B", bytes)
@@ -1672,3 +3237,87 @@ fn format_size(bytes: u64) -> String {
         format!("{:.1} GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
     }
 }
+
+fn unix_time(secs: u64) -> std::time::SystemTime {
+    std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs)
+}
+
+fn format_age(time: std::time::SystemTime) -> String {
+    time.elapsed().map(|d| {
+        if d.as_secs() < 60 {
+            format!("{}s ago", d.as_secs())
+        } else if d.as_secs() < 3600 {
+            format!("{}m ago", d.as_secs() / 60)
+        } else if d.as_secs() < 86400 {
+            format!("{}h ago", d.as_secs() / 3600)
+        } else {
+            format!("{}d ago", d.as_secs() / 86400)
+        }
+    }).unwrap_or_else(|_| "?".to_string())
+}
+
+/// Profile for `elm run` without `--profile`: chosen from a list (most recently
+/// launched first) when there are several and we're on a terminal, else "default"
+fn pick_profile(prefixes_dir: &std::path::Path) -> Result<String> {
+    use std::io::IsTerminal;
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Ok("default".to_string());
+    }
+    let mut profiles = elm_core::prefix::profile::list(prefixes_dir)?;
+    if profiles.len() < 2 {
+        return Ok("default".to_string());
+    }
+    profiles.sort_by(|a, b| b.1.last_launched.cmp(&a.1.last_launched).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    let items: Vec<String> = profiles
+        .iter()
+        .map(|(_, meta)| {
+            let last_run = meta
+                .last_launched
+                .map(|t| format!("last run {}", format_age(unix_time(t))))
+                .unwrap_or_else(|| "never run".to_string());
+            format!("{} ({})", meta.display_name, last_run)
+        })
+        .collect();
+    let choice = dialoguer::Select::new()
+        .with_prompt("Profile to launch")
+        .items(&items)
+        .default(0)
+        .interact_opt()?
+        .ok_or_else(|| anyhow::anyhow!("no profile selected (pass --profile <name>)"))?;
+    Ok(profiles.swap_remove(choice).1.name)
+}
+
+/// Open `path` in $EDITOR (else $VISUAL, else nano). What the file held before
+/// is backed up once the editor exits, and only if the edit changed it.
+/// Returns whether the editor exited successfully.
+fn open_in_editor(path: &std::path::Path) -> Result<bool> {
+    let editor = std::env::var("EDITOR")
+        .or_else(|_| std::env::var("VISUAL"))
+        .unwrap_or_else(|_| "nano".to_string());
+    let before = std::fs::read(path).ok();
+    say!("Opening {} in {}...", path.display(), editor);
+
+    let status = std::process::Command::new(&editor).arg(path).status()?;
+
+    if let Some(before) = before {
+        if std::fs::read(path).ok().as_ref() != Some(&before) {
+            elm_core::config::backup::backup_contents(path, &before)?;
+        }
+    }
+    Ok(status.success())
+}
+
+/// Ask the user to type 'yes'. Fails instead of blocking when stdin is not a terminal.
+fn confirm() -> Result<bool> {
+    use std::io::{IsTerminal, Write};
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!("confirmation required but stdin is not a terminal (re-run with --yes)"));
+    }
+    eprint!("\nType 'yes' to confirm: ");
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim() == "yes")
+}
@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Number of backups kept per config file
+pub const KEEP_BACKUPS: usize = 10;
+
+/// Copy `path` to a timestamped `<name>.<millis>.bak` next to it, pruning old backups.
+/// Returns `None` when there is nothing to back up.
+pub fn backup_file(path: &Path) -> Result<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    backup_contents(path, &content).map(Some)
+}
+
+/// Like [`backup_file`], but backs up `content` (e.g. what `path` held before
+/// an edit) instead of the file's current contents
+pub fn backup_contents(path: &Path, content: &[u8]) -> Result<PathBuf> {
+    let mut timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("not a file: {}", path.display()))?
+        .to_string_lossy();
+    let mut bak = path.with_file_name(format!("{file_name}.{timestamp}.bak"));
+    while bak.exists() {
+        timestamp += 1;
+        bak = path.with_file_name(format!("{file_name}.{timestamp}.bak"));
+    }
+    fs::write(&bak, content).with_context(|| format!("backup {} to {}", path.display(), bak.display()))?;
+
+    for old in list_backups(path)?.into_iter().skip(KEEP_BACKUPS) {
+        fs::remove_file(&old).with_context(|| format!("remove {}", old.display()))?;
+    }
+    Ok(bak)
+}
+
+/// Backups of `path`, newest first
+pub fn list_backups(path: &Path) -> Result<Vec<PathBuf>> {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let prefix = format!("{}.", file_name.to_string_lossy());
+
+    let mut backups: Vec<(u128, PathBuf)> = fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let stamp = name.strip_prefix(&prefix)?.strip_suffix(".bak")?.parse().ok()?;
+            Some((stamp, e.path()))
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.0));
+    Ok(backups.into_iter().map(|(_, p)| p).collect())
+}
+
+/// Replace `path` with the contents of `backup`, backing up the current file first
+pub fn restore_backup(path: &Path, backup: &Path) -> Result<()> {
+    // Read first: backing up the current file may prune the one being restored
+    let content = fs::read(backup).with_context(|| format!("reading backup {}", backup.display()))?;
+    backup_file(path)?;
+    fs::write(path, content).with_context(|| format!("restore {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::models::ChannelConstraints;
+use crate::system::{gpu, vulkan};
+use anyhow::{anyhow, Result};
+
+/// What the launch will actually run on, for comparison against a channel's constraints
+pub struct SystemInfo {
+    /// Vulkan API version of the primary device; `None` if vulkaninfo is unavailable
+    pub vulkan_version: Option<String>,
+    pub vulkan_device: Option<String>,
+    /// Lowercase vendors (`nvidia`, `amd`, `intel`, `other`) of the GPUs that may render
+    pub gpu_vendors: Vec<String>,
+}
+
+impl SystemInfo {
+    /// Probe Vulkan and the GPUs; `selected` narrows the GPUs to the one chosen with `--gpu`
+    pub fn detect(selected: Option<&gpu::Gpu>) -> Self {
+        let vulkan = vulkan::probe();
+        let primary = vulkan.as_ref().and_then(|v| v.primary_device());
+        let gpus = match selected {
+            Some(g) => vec![g.clone()],
+            None => gpu::enumerate(),
+        };
+        SystemInfo {
+            vulkan_version: primary
+                .and_then(|d| d.api_version.clone())
+                .or_else(|| vulkan.as_ref().and_then(|v| v.instance_version.clone())),
+            vulkan_device: primary.map(|d| d.name.clone()),
+            gpu_vendors: gpus.iter().map(|g| g.vendor.to_string().to_lowercase()).collect(),
+        }
+    }
+}
+
+/// Fail if the system is known not to meet `constraints`. Anything that couldn't
+/// be detected (no vulkaninfo, no lspci) is given the benefit of the doubt.
+pub fn check(constraints: &ChannelConstraints, system: &SystemInfo) -> Result<()> {
+    if let (Some(min), Some(have)) = (&constraints.min_vulkan, &system.vulkan_version) {
+        if !vulkan::version_at_least(have, min) {
+            return Err(anyhow!(
+                "Vulkan {} on {} is below the channel's minimum of {}; update your GPU driver or Mesa",
+                have,
+                system.vulkan_device.as_deref().unwrap_or("this GPU"),
+                min
+            ));
+        }
+    }
+
+    if let Some(allowed) = &constraints.gpu_vendors {
+        let supported = |vendor: &String| allowed.iter().any(|a| a.eq_ignore_ascii_case(vendor));
+        if !system.gpu_vendors.is_empty() && !system.gpu_vendors.iter().any(supported) {
+            return Err(anyhow!(
+                "GPU vendor {} is not supported by this channel (supported: {})",
+                system.gpu_vendors.join(", "),
+                allowed.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use super::backup::backup_file;
+use super::load::{read_config, Format};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Edit a config file as raw JSON, so keys ELM doesn't model survive the
+/// round trip. YAML and TOML files are read and written back in their own
+/// format, which drops their comments; the previous version is backed up
+/// first either way. Returns the backup path.
+pub fn edit_config(path: &Path, edit: impl FnOnce(&mut Value) -> Result<()>) -> Result<Option<PathBuf>> {
+    let mut doc = read_config(path)?;
+    edit(&mut doc)?;
+    let content = Format::of(path).serialize(&doc).with_context(|| format!("serializing {}", path.display()))?;
+
+    let backup = backup_file(path)?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    fs::write(&tmp, content).with_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))?;
+    Ok(backup)
+}
+
+/// Point the manifest's `engine.ref` at `engine_id`, leaving everything else as is
+pub fn set_engine_ref(manifest_path: &Path, engine_id: &str) -> Result<Option<PathBuf>> {
+    edit_config(manifest_path, |manifest| {
+        let engine = manifest
+            .get_mut("engine")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| anyhow!("{} has no engine section", manifest_path.display()))?;
+        engine.insert("ref".to_string(), Value::String(engine_id.to_string()));
+        Ok(())
+    })
+}
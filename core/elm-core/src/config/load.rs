@@ -1,36 +1,112 @@
 use super::models::*;
-use super::validate::validate_json_against_schema;
+use super::validate::SchemaCache;
 use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-fn read_json(path: &Path) -> Result<Value> {
+/// The stable channel shipped with ELM, used when the user has no channel config
+pub const STABLE_CHANNEL_V1: &str = include_str!("../../../../configs/channels/stable.json");
+
+pub fn builtin_channel() -> Result<ChannelV1> {
+    serde_json::from_str(STABLE_CHANNEL_V1).context("parsing built-in stable channel")
+}
+
+/// Extensions a config file may have, in the order they're looked for.
+/// JSON is what ELM writes; YAML and TOML are accepted for hand-written files.
+pub const CONFIG_EXTENSIONS: &[&str] = &["json", "yaml", "yml", "toml"];
+
+/// Config file formats ELM reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// The format of `path`, by extension; anything unrecognised is read as JSON
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+        }
+    }
+
+    /// Parse `content` in this format into the JSON value schemas validate
+    pub fn parse(self, content: &str) -> Result<Value> {
+        Ok(match self {
+            Format::Json => serde_json::from_str(content)?,
+            Format::Yaml => serde_yaml::from_str(content)?,
+            Format::Toml => toml::from_str(content)?,
+        })
+    }
+
+    /// Write `value` out in this format (pretty-printed JSON for JSON)
+    pub fn serialize(self, value: &Value) -> Result<String> {
+        Ok(match self {
+            Format::Json => serde_json::to_string_pretty(value)? + "\n",
+            Format::Yaml => serde_yaml::to_string(value)?,
+            Format::Toml => toml::to_string_pretty(value)?,
+        })
+    }
+}
+
+/// `<dir>/<stem>.<ext>` for the first of [`CONFIG_EXTENSIONS`] that exists
+pub fn find_config(dir: &Path, stem: &str) -> Option<PathBuf> {
+    CONFIG_EXTENSIONS.iter().map(|ext| dir.join(format!("{stem}.{ext}"))).find(|p| p.exists())
+}
+
+/// Read a JSON, YAML or TOML config file (by extension) as a JSON value
+pub fn read_config(path: &Path) -> Result<Value> {
     let s = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
-    let v: Value = serde_json::from_str(&s).with_context(|| format!("parsing {}", path.display()))?;
-    Ok(v)
+    Format::of(path).parse(&s).with_context(|| format!("parsing {}", path.display()))
 }
 
-pub fn load_channel(path: &Path, schemas_dir: &Path) -> Result<ChannelV1> {
-    let v = read_json(path)?;
-    validate_json_against_schema(&v, &schemas_dir.join("elm.channel.v1.schema.json"))?;
+fn load_validated<T: DeserializeOwned>(path: &Path, schema_path: &Path, cache: &mut SchemaCache) -> Result<T> {
+    let v = read_config(path)?;
+    cache.validate(&v, schema_path).with_context(|| format!("validating {}", path.display()))?;
     Ok(serde_json::from_value(v)?)
 }
 
+pub fn load_channel(path: &Path, schemas_dir: &Path) -> Result<ChannelV1> {
+    load_channel_with(path, schemas_dir, &mut SchemaCache::new())
+}
+
+pub fn load_channel_with(path: &Path, schemas_dir: &Path, cache: &mut SchemaCache) -> Result<ChannelV1> {
+    load_validated(path, &schemas_dir.join("elm.channel.v1.schema.json"), cache)
+}
+
 pub fn load_engine(path: &Path, schemas_dir: &Path) -> Result<EngineV1> {
-    let v = read_json(path)?;
-    validate_json_against_schema(&v, &schemas_dir.join("elm.engine.v1.schema.json"))?;
-    Ok(serde_json::from_value(v)?)
+    load_engine_with(path, schemas_dir, &mut SchemaCache::new())
+}
+
+pub fn load_engine_with(path: &Path, schemas_dir: &Path, cache: &mut SchemaCache) -> Result<EngineV1> {
+    load_validated(path, &schemas_dir.join("elm.engine.v1.schema.json"), cache)
 }
 
 pub fn load_manifest(path: &Path, schemas_dir: &Path) -> Result<ManifestV1> {
-    let v = read_json(path)?;
-    validate_json_against_schema(&v, &schemas_dir.join("elm.manifest.v1.schema.json"))?;
-    Ok(serde_json::from_value(v)?)
+    load_manifest_with(path, schemas_dir, &mut SchemaCache::new())
+}
+
+pub fn load_manifest_with(path: &Path, schemas_dir: &Path, cache: &mut SchemaCache) -> Result<ManifestV1> {
+    load_validated(path, &schemas_dir.join("elm.manifest.v1.schema.json"), cache)
 }
 
 pub fn load_profile(path: &Path, schemas_dir: &Path) -> Result<ProfileV1> {
-    let v = read_json(path)?;
-    validate_json_against_schema(&v, &schemas_dir.join("elm.profile.v1.schema.json"))?;
-    Ok(serde_json::from_value(v)?)
+    load_profile_with(path, schemas_dir, &mut SchemaCache::new())
+}
+
+pub fn load_profile_with(path: &Path, schemas_dir: &Path, cache: &mut SchemaCache) -> Result<ProfileV1> {
+    load_validated(path, &schemas_dir.join("elm.profile.v1.schema.json"), cache)
 }
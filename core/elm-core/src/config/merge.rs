@@ -0,0 +1,43 @@
+use super::models::ManifestV1;
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+/// Top-level manifest sections a profile is allowed to override
+pub const OVERRIDABLE: &[&str] = &["env", "engine", "launch"];
+
+/// Deep-merge a profile's `overrides` onto a manifest.
+///
+/// Objects merge recursively; scalars and arrays replace the manifest value
+/// (so `launch.entrypoints` must be given in full). Only the `env`, `engine`
+/// and `launch` sections can be overridden.
+pub fn apply_overrides(manifest: ManifestV1, overrides: &Value) -> Result<ManifestV1> {
+    let overrides = match overrides {
+        Value::Null => return Ok(manifest),
+        Value::Object(map) => map,
+        _ => return Err(anyhow!("profile overrides must be an object")),
+    };
+    if let Some(key) = overrides.keys().find(|k| !OVERRIDABLE.contains(&k.as_str())) {
+        return Err(anyhow!(
+            "cannot override '{}' from a profile (allowed: {})",
+            key,
+            OVERRIDABLE.join(", ")
+        ));
+    }
+
+    let mut merged = serde_json::to_value(&manifest)?;
+    for (key, value) in overrides {
+        merge_value(&mut merged[key.as_str()], value);
+    }
+    serde_json::from_value(merged).context("profile overrides produce an invalid manifest")
+}
+
+fn merge_value(base: &mut Value, patch: &Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge_value(base.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
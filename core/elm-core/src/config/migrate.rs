@@ -0,0 +1,202 @@
+use super::edit::edit_config;
+use super::load::{read_config, Format};
+use super::models::ManifestV1;
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Config kinds and the schema version this ELM reads for each
+pub const CURRENT_VERSIONS: &[(&str, u32)] = &[("manifest", 1), ("profile", 1), ("channel", 1), ("engine", 1)];
+
+/// One upgrade step for a config kind. A step whose `from` and `to` are both
+/// the current version is that version's clean-up pass and ends the chain.
+struct Migration {
+    kind: &'static str,
+    from: u32,
+    to: u32,
+    apply: fn(&mut Value) -> Result<()>,
+}
+
+/// Every registered step. A v2 manifest would add `manifest 1 -> 2` and turn
+/// `manifest 1 -> 1` into `manifest 2 -> 2`.
+const MIGRATIONS: &[Migration] = &[
+    Migration { kind: "manifest", from: 1, to: 1, apply: unchanged },
+    Migration { kind: "profile", from: 1, to: 1, apply: unchanged },
+    Migration { kind: "channel", from: 1, to: 1, apply: unchanged },
+    Migration { kind: "engine", from: 1, to: 1, apply: unchanged },
+];
+
+fn unchanged(_: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+/// What [`migrate`] did to a config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrated {
+    /// `schema` before and after, e.g. `elm.manifest.v1`
+    pub from: String,
+    pub to: String,
+    /// Whether anything in the document changed
+    pub changed: bool,
+}
+
+/// Split a schema tag such as `elm.manifest.v1` into its kind and version
+pub fn parse_schema(schema: &str) -> Result<(&str, u32)> {
+    let parsed = schema
+        .strip_prefix("elm.")
+        .and_then(|rest| rest.rsplit_once(".v"))
+        .and_then(|(kind, version)| Some((kind, version.parse().ok()?)));
+    parsed.ok_or_else(|| anyhow!("unrecognised schema '{schema}' (expected elm.<kind>.v<N>)"))
+}
+
+/// Upgrade a config document in place to the current version of its kind,
+/// going by its `schema` field
+pub fn migrate(doc: &mut Value) -> Result<Migrated> {
+    migrate_with(doc, MIGRATIONS, CURRENT_VERSIONS)
+}
+
+/// [`migrate`] against the given steps and current versions instead of the registered ones
+fn migrate_with(doc: &mut Value, migrations: &[Migration], current_versions: &[(&str, u32)]) -> Result<Migrated> {
+    let from = doc
+        .get("schema")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("no schema field"))?
+        .to_string();
+    let (kind, mut version) = parse_schema(&from)?;
+    let kind = kind.to_string();
+    let current = current_versions
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map(|(_, v)| *v)
+        .ok_or_else(|| anyhow!("unknown config kind '{kind}' in schema '{from}'"))?;
+    if version > current {
+        return Err(anyhow!("{from} is newer than this ELM understands (v{current}); update ELM"));
+    }
+
+    let original = doc.clone();
+    loop {
+        let step = migrations
+            .iter()
+            .find(|m| m.kind == kind && m.from == version)
+            .ok_or_else(|| anyhow!("no migration from {kind} v{version}"))?;
+        (step.apply)(doc).with_context(|| format!("migrating {kind} v{} to v{}", step.from, step.to))?;
+        version = step.to;
+        doc["schema"] = Value::String(format!("elm.{kind}.v{version}"));
+        if version == current {
+            break;
+        }
+    }
+
+    Ok(Migrated { to: format!("elm.{kind}.v{version}"), changed: *doc != original, from })
+}
+
+/// Read a manifest of any supported version as the current model, without
+/// touching the file
+pub fn load_manifest(path: &Path) -> Result<ManifestV1> {
+    let mut doc = read_config(path)?;
+    migrate(&mut doc).with_context(|| path.display().to_string())?;
+    serde_json::from_value(doc).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Migrate a config file on disk. The file is only rewritten (after a backup)
+/// when something changed; returns what happened and the backup path, if any.
+/// YAML and TOML files are never rewritten, since that would drop their comments.
+pub fn migrate_file(path: &Path) -> Result<(Migrated, Option<PathBuf>)> {
+    migrate_file_with(path, migrate)
+}
+
+/// [`migrate_file`] with the upgrade done by `migrate` instead of [`migrate`]
+fn migrate_file_with(
+    path: &Path,
+    migrate: impl FnOnce(&mut Value) -> Result<Migrated>,
+) -> Result<(Migrated, Option<PathBuf>)> {
+    let mut doc = read_config(path)?;
+    let migrated = migrate(&mut doc).with_context(|| path.display().to_string())?;
+    if !migrated.changed {
+        return Ok((migrated, None));
+    }
+    if Format::of(path) != Format::Json {
+        return Err(anyhow!(
+            "{} needs upgrading from {} to {}, but only JSON files are rewritten; update it by hand",
+            path.display(),
+            migrated.from,
+            migrated.to
+        ));
+    }
+    let backup = edit_config(path, |json| {
+        *json = doc;
+        Ok(())
+    })?;
+    Ok((migrated, backup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A made-up manifest v2 that renames `engine.ref` to `engine.id`
+    const V2: &[Migration] = &[
+        Migration { kind: "manifest", from: 1, to: 2, apply: rename_ref },
+        Migration { kind: "manifest", from: 2, to: 2, apply: unchanged },
+    ];
+
+    fn rename_ref(doc: &mut Value) -> Result<()> {
+        let engine = doc["engine"].as_object_mut().ok_or_else(|| anyhow!("no engine section"))?;
+        if let Some(id) = engine.remove("ref") {
+            engine.insert("id".to_string(), id);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn current_version_is_unchanged() {
+        let mut doc = json!({"schema": "elm.manifest.v1", "engine": {"ref": "ge-proton"}});
+        let original = doc.clone();
+        let migrated = migrate(&mut doc).unwrap();
+        assert_eq!(
+            migrated,
+            Migrated { from: "elm.manifest.v1".to_string(), to: "elm.manifest.v1".to_string(), changed: false }
+        );
+        assert_eq!(doc, original);
+    }
+
+    #[test]
+    fn rejects_unknown_schemas() {
+        for schema in ["elm.manifest.v2", "elm.widget.v1", "manifest.v1", "elm.manifest.vx"] {
+            let mut doc = json!({ "schema": schema });
+            assert!(migrate(&mut doc).is_err(), "{schema}");
+        }
+        assert!(migrate(&mut json!({})).is_err());
+    }
+
+    #[test]
+    fn steps_through_to_current() {
+        let mut doc = json!({"schema": "elm.manifest.v1", "engine": {"ref": "ge-proton"}});
+        let migrated = migrate_with(&mut doc, V2, &[("manifest", 2)]).unwrap();
+        assert_eq!(migrated.to, "elm.manifest.v2");
+        assert!(migrated.changed);
+        assert_eq!(doc, json!({"schema": "elm.manifest.v2", "engine": {"id": "ge-proton"}}));
+    }
+
+    #[test]
+    fn migrate_file_backs_up_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("eve-online.json");
+        let original = r#"{"schema": "elm.manifest.v1", "engine": {"ref": "ge-proton"}}"#;
+        std::fs::write(&path, original).unwrap();
+
+        // Nothing to do: no rewrite, no backup
+        let (migrated, backup) = migrate_file(&path).unwrap();
+        assert!(!migrated.changed);
+        assert_eq!(backup, None);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+
+        let (migrated, backup) = migrate_file_with(&path, |doc| migrate_with(doc, V2, &[("manifest", 2)])).unwrap();
+        assert!(migrated.changed);
+        let backup = backup.expect("a backup of the v1 file");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), original);
+        let rewritten: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(rewritten, json!({"schema": "elm.manifest.v2", "engine": {"id": "ge-proton"}}));
+    }
+}
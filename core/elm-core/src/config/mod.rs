@@ -1,3 +1,9 @@
+pub mod backup;
+pub mod constraints;
+pub mod edit;
 pub mod load;
+pub mod merge;
+pub mod migrate;
 pub mod models;
+pub mod presets;
 pub mod validate;
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ChannelV1 {
@@ -38,8 +39,23 @@ pub struct EngineV1 {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct EngineSource {
+    /// `url` to download the archive, `file` (or `path`) to use a local one
     pub kind: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub url: String,
+    /// Mirrors tried in order if `url` fails or serves a file that fails verification
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    /// Archive already on disk, for `kind` `file`/`path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl EngineSource {
+    /// `url` followed by the mirrors, without duplicates
+    pub fn candidates(&self) -> Vec<String> {
+        candidate_urls(&self.url, &self.urls)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -57,7 +73,13 @@ pub struct ManifestV1 {
     pub engine: ManifestEngineRef,
     pub runtime: RuntimeConfig,
     pub env: Option<EnvConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download: Option<DownloadConfig>,
     pub launch: LaunchConfig,
+    /// Top-level keys ELM doesn't know about (user annotations), kept so a
+    /// load/save round trip doesn't drop them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -72,21 +94,66 @@ pub struct Installer {
 pub struct InstallerSource {
     pub url: String,
     pub sha256: Option<String>,
+    /// Mirrors tried in order if `url` fails or serves a file that fails verification
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+}
+
+impl InstallerSource {
+    /// `url` followed by the mirrors, without duplicates
+    pub fn candidates(&self) -> Vec<String> {
+        candidate_urls(&self.url, &self.urls)
+    }
+}
+
+fn candidate_urls(url: &str, mirrors: &[String]) -> Vec<String> {
+    let mut all = vec![url.to_string()];
+    for mirror in mirrors {
+        if !all.contains(mirror) {
+            all.push(mirror.clone());
+        }
+    }
+    all
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ManifestEngineRef {
     #[serde(rename = "ref")]
     pub engine_ref: String,
+    /// GitHub `owner/repo` that `elm update` pulls releases from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RuntimeConfig {
     pub wineprefix_layout: String,
+    /// Run Proton inside a Steam Linux Runtime container, like Steam does
+    #[serde(default, skip_serializing_if = "SteamRuntime::is_off")]
+    pub steam_runtime: SteamRuntime,
     pub dx: DxConfig,
     pub components: ComponentsConfig,
 }
 
+/// Which Steam Linux Runtime container to launch Proton in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SteamRuntime {
+    /// Bare proton
+    #[default]
+    Off,
+    /// Sniper if installed, else soldier, else bare proton
+    Auto,
+    Sniper,
+    Soldier,
+}
+
+impl SteamRuntime {
+    pub fn is_off(&self) -> bool {
+        *self == SteamRuntime::Off
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DxConfig {
     pub preferred: String,
@@ -109,6 +176,12 @@ pub struct EnvConfig {
     pub base: Option<HashMap<String, String>>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DownloadConfig {
+    /// Download speed cap in KB/s
+    pub limit_rate: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LaunchConfig {
     pub entrypoints: Vec<Entrypoint>,
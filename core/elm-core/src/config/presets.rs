@@ -0,0 +1,189 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A named set of `env.base` overrides for the manifest
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: String,
+    pub description: String,
+    pub env: BTreeMap<String, String>,
+    /// Shipped with ELM rather than defined in `presets.json`
+    pub builtin: bool,
+}
+
+fn preset(name: &str, description: &str, env: &[(&str, &str)]) -> Preset {
+    Preset {
+        name: name.to_string(),
+        description: description.to_string(),
+        env: env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        builtin: true,
+    }
+}
+
+/// The presets shipped with ELM
+pub fn builtin_presets() -> Vec<Preset> {
+    vec![
+        preset(
+            "performance",
+            "Maximum FPS, FSR upscaling, minimal HUD",
+            &[
+                ("DXVK_ASYNC", "1"),
+                ("PROTON_NO_ESYNC", "0"),
+                ("PROTON_NO_FSYNC", "0"),
+                ("DXVK_HUD", ""),
+                ("WINE_FULLSCREEN_FSR", "1"),
+                ("WINE_FULLSCREEN_FSR_STRENGTH", "2"),
+                ("PROTON_ENABLE_NVAPI", "1"),
+                ("VKD3D_FEATURE_LEVEL", "12_1"),
+                ("__GL_SHADER_DISK_CACHE", "1"),
+                ("__GL_SHADER_DISK_CACHE_SKIP_CLEANUP", "1"),
+            ],
+        ),
+        preset(
+            "quality",
+            "Native resolution, no async shaders",
+            &[
+                ("DXVK_ASYNC", "0"),
+                ("PROTON_NO_ESYNC", "0"),
+                ("PROTON_NO_FSYNC", "0"),
+                ("DXVK_HUD", ""),
+                ("WINE_FULLSCREEN_FSR", "0"),
+                ("PROTON_ENABLE_NVAPI", "1"),
+                ("VKD3D_FEATURE_LEVEL", "12_1"),
+                ("__GL_SHADER_DISK_CACHE", "1"),
+            ],
+        ),
+        preset(
+            "balanced",
+            "Good performance with FPS counter",
+            &[
+                ("DXVK_ASYNC", "1"),
+                ("PROTON_NO_ESYNC", "0"),
+                ("PROTON_NO_FSYNC", "0"),
+                ("DXVK_HUD", "fps"),
+                ("WINE_FULLSCREEN_FSR", "1"),
+                ("PROTON_ENABLE_NVAPI", "1"),
+                ("VKD3D_FEATURE_LEVEL", "12_1"),
+            ],
+        ),
+        preset(
+            "debug",
+            "Verbose logging for troubleshooting",
+            &[
+                ("DXVK_ASYNC", "1"),
+                ("PROTON_NO_ESYNC", "0"),
+                ("PROTON_NO_FSYNC", "0"),
+                ("DXVK_HUD", "fps,frametimes,gpuload,devinfo"),
+                ("DXVK_LOG_LEVEL", "info"),
+                ("PROTON_LOG", "1"),
+                ("WINEDEBUG", "warn+all"),
+            ],
+        ),
+    ]
+}
+
+/// Parse user presets: a JSON object mapping preset name to env overrides
+pub fn parse_user_presets(content: &str) -> Result<Vec<Preset>> {
+    let root: Value = serde_json::from_str(content)?;
+    let presets = root
+        .as_object()
+        .ok_or_else(|| anyhow!("expected an object mapping preset names to env overrides"))?;
+
+    presets
+        .iter()
+        .map(|(name, env)| {
+            let env = env
+                .as_object()
+                .ok_or_else(|| anyhow!("preset '{}': expected an object of env overrides", name))?
+                .iter()
+                .map(|(key, value)| {
+                    value
+                        .as_str()
+                        .map(|v| (key.clone(), v.to_string()))
+                        .ok_or_else(|| anyhow!("preset '{}': value of {} must be a string, got {}", name, key, value))
+                })
+                .collect::<Result<BTreeMap<_, _>>>()?;
+            Ok(Preset {
+                name: name.clone(),
+                description: "User-defined".to_string(),
+                env,
+                builtin: false,
+            })
+        })
+        .collect()
+}
+
+/// Built-in presets with `presets.json` from `config_dir` merged over them.
+/// A user preset with a built-in's name replaces it.
+pub fn load_presets(config_dir: &Path) -> Result<Vec<Preset>> {
+    let mut presets = builtin_presets();
+    let path = config_dir.join("presets.json");
+    if !path.exists() {
+        return Ok(presets);
+    }
+
+    let content = std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let user = parse_user_presets(&content).with_context(|| format!("invalid {}", path.display()))?;
+    for preset in user {
+        match presets.iter_mut().find(|p| p.name.eq_ignore_ascii_case(&preset.name)) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+    }
+    Ok(presets)
+}
+
+/// Look up a preset by name, case-insensitively
+pub fn find<'a>(presets: &'a [Preset], name: &str) -> Option<&'a Preset> {
+    presets.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+/// One `env.base` entry a preset would change
+#[derive(Debug)]
+pub struct EnvChange {
+    pub key: String,
+    /// `None` if the variable isn't set yet
+    pub before: Option<String>,
+    pub after: String,
+}
+
+fn env_base(manifest: &Value) -> Option<&serde_json::Map<String, Value>> {
+    manifest.get("env")?.get("base")?.as_object()
+}
+
+/// What applying `preset` to a manifest (as raw JSON) would change
+pub fn diff(manifest: &Value, preset: &Preset) -> Vec<EnvChange> {
+    let base = env_base(manifest);
+    preset
+        .env
+        .iter()
+        .filter_map(|(key, after)| {
+            let before = base.and_then(|b| b.get(key)).and_then(Value::as_str).map(str::to_string);
+            (before.as_deref() != Some(after.as_str())).then(|| EnvChange {
+                key: key.clone(),
+                before,
+                after: after.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Merge `preset` into the manifest's `env.base`, creating it if needed
+pub fn apply(manifest: &mut Value, preset: &Preset) {
+    if !manifest.is_object() {
+        return;
+    }
+    if !manifest["env"].is_object() {
+        manifest["env"] = Value::Object(Default::default());
+    }
+    if !manifest["env"]["base"].is_object() {
+        manifest["env"]["base"] = Value::Object(Default::default());
+    }
+    if let Some(base) = manifest["env"]["base"].as_object_mut() {
+        for (k, v) in &preset.env {
+            base.insert(k.clone(), Value::String(v.clone()));
+        }
+    }
+}
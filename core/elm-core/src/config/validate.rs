@@ -1,19 +1,316 @@
 use anyhow::{anyhow, Context, Result};
-use jsonschema::Validator;
+use jsonschema::{Resource, Retrieve, UriRef, Validator};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use super::load::Format;
+use super::models::ManifestV1;
+
+/// Manifest schema bundled into the binary, so validation works without a schemas dir
+pub const MANIFEST_SCHEMA_V1: &str = include_str!("../../schemas/elm.manifest.v1.schema.json");
+
+/// Resolves relative `$ref`s (e.g. `elm.common.v1.schema.json#/$defs/x` in a
+/// schema without an `$id`) to files in the schemas directory
+struct SchemaDirRetriever {
+    dir: PathBuf,
+}
+
+impl Retrieve for SchemaDirRetriever {
+    fn retrieve(&self, uri: &UriRef<&str>) -> std::result::Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let file_name = uri.path().as_str().rsplit('/').next().unwrap_or_default();
+        if file_name.is_empty() || file_name == ".." {
+            return Err(format!("cannot resolve schema reference {}", uri.as_str()).into());
+        }
+        let file = self.dir.join(file_name);
+        let content = fs::read_to_string(&file).map_err(|e| format!("{}: {e}", file.display()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+/// Other schemas in `dir` that declare an `$id`, so `$ref: "urn:elm:common:v1"`
+/// style references between our schemas resolve without retrieval
+fn sibling_schemas(dir: &Path, skip: &Path) -> Result<Vec<(String, Resource)>> {
+    let mut resources = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?.flatten() {
+        let path = entry.path();
+        if path == skip || !path.to_string_lossy().ends_with(".schema.json") {
+            continue;
+        }
+        let content = fs::read_to_string(&path).with_context(|| format!("reading schema: {}", path.display()))?;
+        let json: Value = serde_json::from_str(&content)
+            .with_context(|| format!("parsing schema json: {}", path.display()))?;
+        let Some(id) = json.get("$id").and_then(Value::as_str).map(str::to_string) else {
+            continue;
+        };
+        let resource = Resource::from_contents(json).map_err(|e| anyhow!("schema {}: {e}", path.display()))?;
+        resources.push((id, resource));
+    }
+    Ok(resources)
+}
+
+/// Compiled schema validators keyed by schema path, so validating several
+/// documents against the same schema only reads and compiles it once
+#[derive(Default)]
+pub struct SchemaCache {
+    validators: HashMap<PathBuf, Validator>,
+}
+
+impl SchemaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The compiled validator for `schema_path`, compiling it on first use
+    pub fn validator(&mut self, schema_path: &Path) -> Result<&Validator> {
+        if !self.validators.contains_key(schema_path) {
+            let schema_str = fs::read_to_string(schema_path)
+                .with_context(|| format!("reading schema: {}", schema_path.display()))?;
+            let schema_json: Value = serde_json::from_str(&schema_str)
+                .with_context(|| format!("parsing schema json: {}", schema_path.display()))?;
+            let dir = schema_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            let siblings = sibling_schemas(&dir, schema_path)?;
+            let compiled = jsonschema::options()
+                .with_resources(siblings.into_iter())
+                .with_retriever(SchemaDirRetriever { dir })
+                .build(&schema_json)
+                .map_err(|e| anyhow!("schema compile error {}: {e}", schema_path.display()))?;
+            self.validators.insert(schema_path.to_path_buf(), compiled);
+        }
+        Ok(&self.validators[schema_path])
+    }
+
+    /// Every violation of the schema at `schema_path`; empty if `json` is valid
+    pub fn violations(&mut self, json: &Value, schema_path: &Path) -> Result<Vec<ValidationError>> {
+        Ok(collect_violations(self.validator(schema_path)?, json))
+    }
+
+    pub fn validate(&mut self, json: &Value, schema_path: &Path) -> Result<()> {
+        let errors = self.violations(json, schema_path)?;
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let msgs: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        Err(anyhow!("schema validation failed:\n- {}", msgs.join("\n- ")))
+    }
+}
+
+/// One schema violation
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    /// JSON pointer to the offending value, e.g. `/env/base/DXVK_ASYNC` (empty for the root)
+    pub instance_path: String,
+    /// JSON pointer to the schema keyword that failed
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    /// `instance_path` as `env.base.DXVK_ASYNC` / `launch.entrypoints[0].path`
+    pub fn field(&self) -> String {
+        let mut field = String::new();
+        for segment in self.instance_path.split('/').skip(1) {
+            let segment = segment.replace("~1", "/").replace("~0", "~");
+            if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+                field.push_str(&format!("[{segment}]"));
+            } else {
+                if !field.is_empty() {
+                    field.push('.');
+                }
+                field.push_str(&segment);
+            }
+        }
+        field
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = self.field();
+        if field.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", field, self.message)
+        }
+    }
+}
+
+fn collect_violations(validator: &Validator, json: &Value) -> Vec<ValidationError> {
+    match validator.validate(json) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|e| ValidationError {
+                instance_path: e.instance_path.to_string(),
+                schema_path: e.schema_path.to_string(),
+                message: e.to_string(),
+            })
+            .collect(),
+    }
+}
+
+/// Single-shot validation; use a [`SchemaCache`] when validating several documents
 pub fn validate_json_against_schema(json: &Value, schema_path: &Path) -> Result<()> {
-    let schema_str = fs::read_to_string(schema_path)
-        .with_context(|| format!("reading schema: {}", schema_path.display()))?;
-    let schema_json: Value = serde_json::from_str(&schema_str)
-        .with_context(|| format!("parsing schema json: {}", schema_path.display()))?;
-    let compiled = Validator::new(&schema_json)
-        .map_err(|e| anyhow!("schema compile error {}: {e}", schema_path.display()))?;
-    if let Err(errors) = compiled.validate(json) {
-        let msgs: Vec<String> = errors.map(|e| e.to_string()).collect();
-        return Err(anyhow!("schema validation failed:\n- {}", msgs.join("\n- ")));
-    }
-    Ok(())
+    SchemaCache::new().validate(json, schema_path)
+}
+
+/// Validate against a schema given as a string; empty if `json` is valid
+pub fn schema_violations(json: &Value, schema_str: &str) -> Result<Vec<ValidationError>> {
+    let schema_json: Value = serde_json::from_str(schema_str).context("parsing schema json")?;
+    let compiled = Validator::new(&schema_json).map_err(|e| anyhow!("schema compile error: {e}"))?;
+    Ok(collect_violations(&compiled, json))
+}
+
+/// Check a manifest file in place against the bundled schema.
+/// Returns the list of problems found (empty if the manifest is valid).
+pub fn check_manifest_file(path: &Path) -> Result<Vec<String>> {
+    let s = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let v: Value = match Format::of(path) {
+        Format::Json => match serde_json::from_str(&s) {
+            Ok(v) => v,
+            Err(e) => return Ok(vec![format!("invalid JSON at line {}, column {}: {e}", e.line(), e.column())]),
+        },
+        format => match format.parse(&s) {
+            Ok(v) => v,
+            Err(e) => return Ok(vec![format!("invalid {}: {e:#}", format.name())]),
+        },
+    };
+
+    let problems = schema_violations(&v, MANIFEST_SCHEMA_V1)?;
+    if !problems.is_empty() {
+        return Ok(problems.iter().map(ToString::to_string).collect());
+    }
+
+    // Schema passed; make sure it also maps onto the typed model used by `elm run`
+    if let Err(e) = serde_json::from_value::<ManifestV1>(v) {
+        return Ok(vec![e.to_string()]);
+    }
+    Ok(Vec::new())
+}
+
+/// Result of [`validate_manifest_semantics`]
+#[derive(Debug, Default)]
+pub struct SemanticIssues {
+    /// Problems that will break install or launch
+    pub errors: Vec<String>,
+    /// Suspicious but usable settings
+    pub warnings: Vec<String>,
+}
+
+/// Installer URL schemes the downloader handles
+const SUPPORTED_SCHEMES: &[&str] = &["https", "http"];
+
+/// Check what the schema can't: that the engine ref points at an installed
+/// engine under `engines_dir`, the installer URLs are downloadable, entrypoint
+/// paths stay inside the prefix, and env values are set.
+pub fn validate_manifest_semantics(manifest: &ManifestV1, engines_dir: &Path) -> SemanticIssues {
+    let mut issues = SemanticIssues::default();
+
+    let engine_ref = &manifest.engine.engine_ref;
+    if engine_ref.contains(['/', '\\']) || engine_ref == ".." {
+        issues.errors.push(format!("engine.ref: '{engine_ref}' is not a valid engine id"));
+    } else {
+        let paths = crate::engine::install::engine_paths(engines_dir, engine_ref);
+        if !(paths.marker.exists() && paths.dist.exists()) {
+            issues.errors.push(format!(
+                "engine.ref: '{engine_ref}' is not installed in {} (run: elm update --install)",
+                engines_dir.display()
+            ));
+        }
+    }
+
+    let source = &manifest.installer.source;
+    let urls = std::iter::once(("installer.source.url".to_string(), &source.url))
+        .chain(source.urls.iter().enumerate().map(|(i, u)| (format!("installer.source.urls[{i}]"), u)));
+    for (field, url) in urls {
+        match url.split_once("://") {
+            Some((scheme, rest)) if SUPPORTED_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) => {
+                if rest.is_empty() || rest.starts_with('/') {
+                    issues.errors.push(format!("{field}: '{url}' has no host"));
+                }
+            }
+            Some((scheme, _)) => issues.errors.push(format!(
+                "{field}: unsupported scheme '{scheme}' (use {})",
+                SUPPORTED_SCHEMES.join(" or ")
+            )),
+            None => issues.errors.push(format!("{field}: '{url}' is not a URL")),
+        }
+    }
+
+    for (i, entry) in manifest.launch.entrypoints.iter().enumerate() {
+        let Some(path) = &entry.path else { continue };
+        let escapes = path.split(['/', '\\']).any(|part| part == "..");
+        let absolute = path.starts_with(['/', '\\']) || path.get(1..2) == Some(":");
+        if escapes || absolute {
+            issues.errors.push(format!(
+                "launch.entrypoints[{i}].path: '{path}' must be relative to the prefix and not use '..'"
+            ));
+        }
+    }
+
+    if let Some(base) = manifest.env.as_ref().and_then(|e| e.base.as_ref()) {
+        let mut empty: Vec<&str> = base
+            .iter()
+            .filter(|(_, v)| v.trim().is_empty())
+            .map(|(k, _)| k.as_str())
+            .collect();
+        empty.sort();
+        for key in empty {
+            issues.warnings.push(format!("env.base.{key}: empty value (remove the key to leave it unset)"));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn resolves_relative_refs_to_sibling_schema_files() {
+        let dir = tempfile::tempdir().unwrap();
+        // No `$id`, so only the retriever can find it
+        fs::write(
+            dir.path().join("common.schema.json"),
+            json!({"$defs": {"engine_id": {"type": "string", "pattern": "^[a-z0-9-]+$"}}}).to_string(),
+        )
+        .unwrap();
+        let schema = dir.path().join("profile.schema.json");
+        fs::write(
+            &schema,
+            json!({
+                "type": "object",
+                "required": ["engine"],
+                "properties": {"engine": {"$ref": "common.schema.json#/$defs/engine_id"}}
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let mut cache = SchemaCache::new();
+        assert!(cache.violations(&json!({"engine": "ge-proton-10-26"}), &schema).unwrap().is_empty());
+        let errors = cache.violations(&json!({"engine": "GE Proton"}), &schema).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field(), "engine");
+    }
+
+    #[test]
+    fn resolves_refs_by_sibling_id() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("common.schema.json"),
+            json!({"$id": "urn:elm:test-common", "$defs": {"port": {"type": "integer", "minimum": 1}}}).to_string(),
+        )
+        .unwrap();
+        let schema = dir.path().join("server.schema.json");
+        fs::write(&schema, json!({"properties": {"port": {"$ref": "urn:elm:test-common#/$defs/port"}}}).to_string())
+            .unwrap();
+
+        let mut cache = SchemaCache::new();
+        assert!(cache.validate(&json!({"port": 8080}), &schema).is_ok());
+        let err = cache.validate(&json!({"port": 0}), &schema).unwrap_err();
+        assert!(format!("{err}").contains("port"), "{err}");
+    }
 }
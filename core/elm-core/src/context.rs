@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{load, merge, migrate};
+use crate::config::models::{ChannelV1, ManifestV1, ProfileV1};
+use crate::engine;
+use crate::error::ElmError;
+use crate::prefix::profile;
+
+/// Engine used when no manifest names one
+pub const DEFAULT_ENGINE: &str = "ge-proton-10-26";
+
+/// Where ELM keeps its data and config, and the lookups every frontend needs
+/// on top of them: which engine is active, where a profile's prefix lives,
+/// which manifest a profile runs with.
+#[derive(Debug, Clone)]
+pub struct ElmContext {
+    /// Engines, prefixes, downloads and snapshots (`~/.local/share/elm`)
+    pub data_dir: PathBuf,
+    /// Manifests, profiles and channels (`~/.config/elm`)
+    pub config_dir: PathBuf,
+}
+
+impl ElmContext {
+    /// `data_dir`/`config_dir` if given, else `ELM_DATA_DIR`/`ELM_CONFIG_DIR`,
+    /// else the XDG-style defaults under $HOME
+    pub fn resolve(data_dir: Option<PathBuf>, config_dir: Option<PathBuf>) -> Self {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let from_env = |var: &str| {
+            std::env::var_os(var)
+                .filter(|v| !v.is_empty())
+                .map(|v| expand_tilde(PathBuf::from(v)))
+        };
+        ElmContext {
+            data_dir: data_dir
+                .or_else(|| from_env("ELM_DATA_DIR"))
+                .unwrap_or_else(|| PathBuf::from(format!("{home}/.local/share/elm"))),
+            config_dir: config_dir
+                .or_else(|| from_env("ELM_CONFIG_DIR"))
+                .unwrap_or_else(|| PathBuf::from(format!("{home}/.config/elm"))),
+        }
+    }
+
+    pub fn engines_dir(&self) -> PathBuf {
+        self.data_dir.join("engines")
+    }
+
+    pub fn prefixes_dir(&self) -> PathBuf {
+        self.data_dir.join("prefixes")
+    }
+
+    pub fn downloads_dir(&self) -> PathBuf {
+        self.data_dir.join("downloads")
+    }
+
+    /// Engine id the shared manifest points at, or [`DEFAULT_ENGINE`]
+    pub fn active_engine(&self) -> String {
+        load::find_config(&self.config_dir.join("manifests"), "eve-online")
+            .and_then(|path| load::read_config(&path).ok())
+            .and_then(|v| v.get("engine")?.get("ref")?.as_str().map(|s| s.to_string()))
+            .unwrap_or_else(|| DEFAULT_ENGINE.to_string())
+    }
+
+    /// The Proton or Wine root of an installed engine (see [`engine::resolve_proton_root`])
+    pub fn proton_root(&self, engine_id: &str) -> crate::error::Result<PathBuf> {
+        let engine_dir = self.engines_dir().join(engine_id);
+        let dist = engine_dir.join("dist");
+        if !dist.exists() {
+            return Err(ElmError::EngineNotInstalled { id: engine_id.to_string(), path: dist });
+        }
+        Ok(engine::resolve_proton_root(&dist, engine::installed_layout(&engine_dir).as_ref())?)
+    }
+
+    /// Engine a profile runs on, first found of: one the profile pins itself
+    /// (`engine.ref` in its own `manifests/eve-online.<profile>.*` or in its
+    /// `overrides`), its channel's `defaults.engine`, the `engine.ref` of the
+    /// manifest it runs with, [`DEFAULT_ENGINE`]
+    pub fn engine_for_profile(&self, profile: &str) -> Result<String> {
+        let manifest = self.load_manifest(profile)?;
+        let own_manifest = load::find_config(&self.config_dir.join("manifests"), &format!("eve-online.{profile}"));
+        let pinned_by_overrides = self
+            .profile_config(profile)?
+            .is_some_and(|p| p.overrides.get("engine").and_then(|e| e.get("ref")).is_some());
+        if own_manifest.is_some() || pinned_by_overrides {
+            if let Some(manifest) = &manifest {
+                return Ok(manifest.engine.engine_ref.clone());
+            }
+        }
+        if let Some(channel) = self.load_channel(profile)? {
+            return Ok(channel.defaults.engine);
+        }
+        Ok(manifest.map(|m| m.engine.engine_ref).unwrap_or_else(|| DEFAULT_ENGINE.to_string()))
+    }
+
+    /// Engine the profile owning `prefix` runs on (see [`Self::engine_for_profile`]),
+    /// falling back to the active engine
+    pub fn engine_for_prefix(&self, prefix: &Path) -> String {
+        profile_from_prefix(prefix)
+            .and_then(|p| self.engine_for_profile(&p).ok())
+            .unwrap_or_else(|| self.active_engine())
+    }
+
+    /// Proton root for the engine the profile owning `prefix` runs on (see
+    /// [`Self::engine_for_prefix`])
+    pub fn proton_root_for_prefix(&self, prefix: &Path) -> crate::error::Result<PathBuf> {
+        self.proton_root(&self.engine_for_prefix(prefix))
+    }
+
+    /// The prefix directory of profile `name` (`prefixes/eve-<name>`)
+    pub fn profile_prefix(&self, name: &str) -> PathBuf {
+        profile::profile_dir(&self.prefixes_dir(), name)
+    }
+
+    /// `profiles/<name>.json`, whether or not it exists
+    pub fn profile_config_path(&self, name: &str) -> PathBuf {
+        self.config_dir.join(format!("profiles/{name}.json"))
+    }
+
+    /// The manifest a profile runs with: its own `manifests/eve-online.<profile>.json`
+    /// if there is one, else the one its channel's `defaults.manifest` picks,
+    /// else the shared `manifests/eve-online.json`. `None` when none exists and
+    /// the built-in defaults apply. Manifests may be `.yaml`, `.yml` or `.toml`
+    /// instead (see [`load::CONFIG_EXTENSIONS`]).
+    pub fn manifest_path(&self, profile: &str) -> Option<PathBuf> {
+        let manifests = self.config_dir.join("manifests");
+        load::find_config(&manifests, &format!("eve-online.{profile}"))
+            .or_else(|| self.channel_manifest(profile))
+            .or_else(|| load::find_config(&manifests, "eve-online"))
+    }
+
+    /// The shared manifest, `manifests/eve-online.json` or a `.yaml`, `.yml`
+    /// or `.toml` one; the JSON path, where ELM creates it, when there is none
+    pub fn shared_manifest_path(&self) -> PathBuf {
+        let manifests = self.config_dir.join("manifests");
+        load::find_config(&manifests, "eve-online").unwrap_or_else(|| manifests.join("eve-online.json"))
+    }
+
+    /// A profile's own manifest, `manifests/eve-online.<profile>.*`, whether or
+    /// not it exists; a new one takes the shared manifest's format
+    pub fn profile_manifest_path(&self, profile: &str) -> PathBuf {
+        let manifests = self.config_dir.join("manifests");
+        load::find_config(&manifests, &format!("eve-online.{profile}")).unwrap_or_else(|| {
+            let shared = self.shared_manifest_path();
+            let ext = shared.extension().and_then(|e| e.to_str()).unwrap_or("json");
+            manifests.join(format!("eve-online.{profile}.{ext}"))
+        })
+    }
+
+    /// The manifest the profile's channel selects, if the channel is configured
+    /// and the file it names exists
+    fn channel_manifest(&self, profile: &str) -> Option<PathBuf> {
+        let channel = self.load_channel(profile).ok().flatten()?;
+        let path = self.config_dir.join(channel.manifests.get(&channel.defaults.manifest)?);
+        path.exists().then_some(path)
+    }
+
+    /// The profile's manifest (see [`Self::manifest_path`]) with `profiles/<profile>.json`
+    /// overrides applied; `None` if there is no manifest
+    pub fn load_manifest(&self, profile: &str) -> Result<Option<ManifestV1>> {
+        // Older schema versions are upgraded in memory; `elm config migrate` rewrites the file
+        let manifest: Option<ManifestV1> = match self.manifest_path(profile) {
+            Some(path) => Some(migrate::load_manifest(&path)?),
+            None => None,
+        };
+        let Some(profile_config) = self.profile_config(profile)? else {
+            return Ok(manifest);
+        };
+        let Some(manifest) = manifest else {
+            tracing::debug!("profile {} has overrides but no manifest to apply them to", profile);
+            return Ok(None);
+        };
+        let merged = merge::apply_overrides(manifest, &profile_config.overrides)
+            .with_context(|| format!("applying overrides from {}", self.profile_config_path(profile).display()))?;
+        Ok(Some(merged))
+    }
+
+    /// The channel a profile follows: `channels/<name>.json`, where the name
+    /// comes from `profiles/<profile>.json` (default "stable"). `None` if the
+    /// user hasn't configured that channel.
+    pub fn load_channel(&self, profile: &str) -> Result<Option<ChannelV1>> {
+        let channel_name = match self.profile_config(profile)? {
+            Some(p) => p.channel,
+            None => "stable".to_string(),
+        };
+        let Some(channel_path) = load::find_config(&self.config_dir.join("channels"), &channel_name) else {
+            return Ok(None);
+        };
+        let channel = load::read_config(&channel_path)?;
+        Ok(Some(serde_json::from_value(channel).with_context(|| format!("parsing {}", channel_path.display()))?))
+    }
+
+    /// A profile's `overrides.env.base` (already merged into its manifest, but
+    /// wanted again as its own layer so it wins over a preset)
+    pub fn profile_env(&self, profile: &str) -> Result<HashMap<String, String>> {
+        let Some(profile_config) = self.profile_config(profile)? else {
+            return Ok(HashMap::new());
+        };
+        match profile_config.overrides.get("env").and_then(|e| e.get("base")) {
+            Some(base) => serde_json::from_value(base.clone()).with_context(|| {
+                format!("{}: overrides.env.base must map names to strings", self.profile_config_path(profile).display())
+            }),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// Engine ids referenced by the shared manifest, per-profile manifests and
+    /// profile overrides
+    pub fn engines_in_use(&self) -> HashSet<String> {
+        let mut in_use = HashSet::from([self.active_engine()]);
+        for (dir, pointer) in [("manifests", "/engine/ref"), ("profiles", "/overrides/engine/ref")] {
+            let Ok(entries) = fs::read_dir(self.config_dir.join(dir)) else { continue };
+            for entry in entries.flatten() {
+                let engine = load::read_config(&entry.path())
+                    .ok()
+                    .and_then(|v| v.pointer(pointer)?.as_str().map(|s| s.to_string()));
+                in_use.extend(engine);
+            }
+        }
+        in_use
+    }
+
+    /// `profiles/<profile>.json`, if there is one
+    fn profile_config(&self, profile: &str) -> Result<Option<ProfileV1>> {
+        let path = self.profile_config_path(profile);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&content).with_context(|| format!("parsing {}", path.display()))?))
+    }
+}
+
+/// Profile name from a prefix path such as `.../prefixes/eve-<profile>/pfx`
+pub fn profile_from_prefix(prefix: &Path) -> Option<String> {
+    prefix
+        .components()
+        .rev()
+        .find_map(|c| c.as_os_str().to_str()?.strip_prefix(profile::DIR_PREFIX).map(|p| p.to_string()))
+}
+
+/// Expand a leading `~` (alone or as `~/...`) to $HOME; other paths are returned as is
+pub fn expand_tilde(path: PathBuf) -> PathBuf {
+    let Ok(home) = std::env::var("HOME") else { return path };
+    match path.strip_prefix("~") {
+        Ok(rest) if rest.as_os_str().is_empty() => PathBuf::from(home),
+        Ok(rest) => PathBuf::from(home).join(rest),
+        Err(_) => path,
+    }
+}
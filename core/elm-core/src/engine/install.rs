@@ -1,12 +1,14 @@
 // src/engine/install.rs
 use anyhow::{anyhow, Context, Result};
-use reqwest::blocking::Client;
-use sha2::{Digest, Sha256};
 use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use crate::config::models::EngineV1;
+use super::releases::{self, Release};
+use super::runner::Runner;
+use crate::config::models::{EngineLayout, EngineV1};
+use crate::http;
+use crate::system::disk;
 
 pub struct EnginePaths {
     pub root: PathBuf,
@@ -23,75 +25,335 @@ pub fn engine_paths(engines_dir: &Path, engine_id: &str) -> EnginePaths {
     }
 }
 
-pub fn ensure_engine_installed(engine: &EngineV1, engines_dir: &Path, downloads_dir: &Path) -> Result<PathBuf> {
-    let p = engine_paths(engines_dir, &engine.id);
-    if p.marker.exists() && p.dist.exists() {
-        return Ok(p.dist.clone());
+/// A resolved engine download: where it comes from and how to check it
+pub struct EngineArtifact {
+    pub id: String,
+    pub url: String,
+    /// Fallbacks tried in order after `url`
+    pub mirrors: Vec<String>,
+    /// Expected SHA256 or SHA512 hex digest
+    pub checksum: Option<String>,
+    /// Release tag, for engines installed from a GitHub release
+    pub version: Option<String>,
+    /// GitHub `owner/repo` the release came from
+    pub repo: Option<String>,
+    /// Archive already on disk to install from instead of downloading `url`
+    pub local: Option<PathBuf>,
+    /// How the unpacked engine is run
+    pub runner: Runner,
+    /// Directory inside the archive holding the engine, when the definition
+    /// names it; otherwise it is found after unpacking
+    pub root_subdir: Option<String>,
+}
+
+impl EngineArtifact {
+    /// The artifact described by an engine definition file
+    pub fn from_engine(engine: &EngineV1) -> Result<Self> {
+        let runner = Runner::parse(&engine.layout.runner).with_context(|| format!("engine {}: layout.runner", engine.id))?;
+        let local = match engine.source.kind.as_str() {
+            "url" => None,
+            "file" | "path" => Some(
+                engine
+                    .source
+                    .path
+                    .clone()
+                    .ok_or_else(|| anyhow!("engine {}: source.kind '{}' needs source.path", engine.id, engine.source.kind))?,
+            ),
+            other => return Err(anyhow!("v1 engine source.kind must be 'url', 'file' or 'path', not '{other}'")),
+        };
+        Ok(EngineArtifact {
+            id: engine.id.clone(),
+            url: engine.source.url.clone(),
+            mirrors: if local.is_some() { Vec::new() } else { engine.source.candidates().into_iter().skip(1).collect() },
+            checksum: Some(engine.sha256.clone()),
+            version: None,
+            repo: None,
+            local,
+            runner,
+            root_subdir: Some(engine.layout.proton_root_subdir.clone()),
+        })
     }
-    fs::create_dir_all(&p.root)?;
-    fs::create_dir_all(&p.dist)?;
-    fs::create_dir_all(downloads_dir)?;
 
-    if engine.source.kind != "url" {
-        return Err(anyhow!("v1 engine source.kind must be 'url'"));
+    /// The archive asset of a GitHub release, verified against the release's checksum file if it has one
+    pub fn from_release(release: &Release, repo: &str) -> Result<Self> {
+        let asset = release
+            .tarball()
+            .ok_or_else(|| anyhow!("release {} of {} has no .tar.gz/.tar.xz asset", release.tag, repo))?;
+        Ok(EngineArtifact {
+            id: release.tag.to_lowercase(),
+            url: asset.url.clone(),
+            mirrors: Vec::new(),
+            checksum: releases::fetch_checksum(release, asset)?,
+            version: Some(release.tag.clone()),
+            repo: Some(repo.to_string()),
+            local: None,
+            runner: Runner::Proton,
+            root_subdir: None,
+        })
     }
+}
 
-    let archive_path = downloads_dir.join(format!("{}.tar.gz", engine.id));
-    download_to_file(&engine.source.url, &archive_path)?;
+pub async fn ensure_engine_installed(
+    engine: &EngineV1,
+    engines_dir: &Path,
+    downloads_dir: &Path,
+) -> crate::error::Result<PathBuf> {
+    let artifact = EngineArtifact::from_engine(engine)?;
+    install_artifact(&artifact, engines_dir, downloads_dir, |_, _| {}).await
+}
+
+/// Download, verify and unpack `artifact` into `engines_dir/<id>/dist` unless it is
+/// already installed. `progress` gets (bytes downloaded, total bytes if known).
+/// A `local` archive is verified and unpacked in place, without touching the network.
+/// Failed downloads and checksum mismatches come back as their own [`ElmError`](crate::ElmError) variants.
+pub async fn install_artifact(
+    artifact: &EngineArtifact,
+    engines_dir: &Path,
+    downloads_dir: &Path,
+    progress: impl FnMut(u64, Option<u64>),
+) -> crate::error::Result<PathBuf> {
+    Ok(install(artifact, engines_dir, downloads_dir, progress).await?)
+}
+
+async fn install(
+    artifact: &EngineArtifact,
+    engines_dir: &Path,
+    downloads_dir: &Path,
+    progress: impl FnMut(u64, Option<u64>),
+) -> Result<PathBuf> {
+    let p = engine_paths(engines_dir, &artifact.id);
+    if p.marker.exists() && p.dist.exists() {
+        tracing::debug!("engine {} already installed at {}", artifact.id, p.dist.display());
+        return Ok(p.dist.clone());
+    }
+    fs::create_dir_all(engines_dir)?;
+    disk::ensure_space(engines_dir, disk::ENGINE_SPACE, &format!("install engine {}", artifact.id))?;
 
-    verify_sha256(&archive_path, &engine.sha256)?;
+    let (archive_path, sha256) = match &artifact.local {
+        Some(local) => {
+            tracing::info!("installing engine {} from {}", artifact.id, local.display());
+            // Hashing a multi-GB archive blocks, so it runs off the runtime like the extraction below
+            let (archive, expected) = (local.clone(), artifact.checksum.clone());
+            let sha256 = tokio::task::spawn_blocking(move || -> Result<String> {
+                let sha256 = http::sha256_file(&archive)?;
+                if let Some(expected) = &expected {
+                    http::verify_checksum(&archive, &sha256, expected)?;
+                }
+                Ok(sha256)
+            })
+            .await
+            .map_err(|e| anyhow!("hash task failed: {e}"))
+            .and_then(|r| r)
+            .with_context(|| format!("reading engine archive for {}", artifact.id))?;
+            (local.clone(), sha256)
+        }
+        None => {
+            fs::create_dir_all(downloads_dir)?;
+            let archive_path = downloads_dir.join(format!("{}{}", artifact.id, archive_extension(&artifact.url)));
+            let config = http::HttpConfig::from_env();
+            let client = http::client(&config)?;
+            let urls: Vec<String> =
+                std::iter::once(artifact.url.clone()).chain(artifact.mirrors.iter().cloned()).collect();
+            let sha256 = http::download_from_mirrors(
+                &client,
+                &config,
+                &urls,
+                &archive_path,
+                artifact.checksum.as_deref(),
+                progress,
+            )
+            .await
+            .with_context(|| format!("downloading engine {} (run the install again to retry)", artifact.id))?;
+            (archive_path, sha256)
+        }
+    };
 
-    extract_tar_gz(&archive_path, &p.dist)?;
+    // Unpack into a staging dir next to the engine and move it into place only
+    // once complete, so a failed or interrupted extract never looks installed
+    let staging = engines_dir.join(format!(".{}.partial", artifact.id));
+    if staging.exists() {
+        fs::remove_dir_all(&staging).with_context(|| format!("remove {}", staging.display()))?;
+    }
+    let staged_dist = staging.join("dist");
+    fs::create_dir_all(&staged_dist)?;
+    tracing::info!("extracting {} to {}", archive_path.display(), staged_dist.display());
+    let (archive, into) = (archive_path.clone(), staged_dist.clone());
+    let extracted = tokio::task::spawn_blocking(move || extract_archive(&archive, &into))
+        .await
+        .map_err(|e| anyhow!("extract task failed: {e}"))
+        .and_then(|r| r);
+    // The engine root must be laid out for the declared runner; it's recorded
+    // in the marker so launches don't have to guess between nested dirs
+    let root_subdir = extracted.and_then(|()| staged_root_subdir(artifact, &staged_dist, &archive_path));
+    let root_subdir = match root_subdir {
+        Ok(subdir) => subdir,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+    if p.root.exists() {
+        // Leftover from an install that never got its marker
+        fs::remove_dir_all(&p.root).with_context(|| format!("remove incomplete {}", p.root.display()))?;
+    }
+    fs::rename(&staging, &p.root)
+        .with_context(|| format!("move {} to {}", staging.display(), p.root.display()))?;
 
-    let marker = serde_json::json!({
-        "engine_id": engine.id,
-        "sha256": engine.sha256
+    // The marker goes last: its presence means the engine is complete
+    let layout = EngineLayout { proton_root_subdir: root_subdir, runner: artifact.runner.as_str().to_string() };
+    let mut marker = serde_json::json!({
+        "engine_id": artifact.id,
+        "sha256": sha256,
+        "layout": layout
     });
+    if let Some(version) = &artifact.version {
+        marker["version"] = version.as_str().into();
+    }
+    if let Some(repo) = &artifact.repo {
+        marker["repo"] = repo.as_str().into();
+    }
     std::fs::write(&p.marker, serde_json::to_vec_pretty(&marker)?)?;
 
     Ok(p.dist)
 }
 
-fn download_to_file(url: &str, dest: &Path) -> Result<()> {
-    let client = Client::new();
-    let mut resp = client.get(url).send().with_context(|| format!("GET {url}"))?;
-    resp.error_for_status_ref()?;
-
-    let mut out = File::create(dest).with_context(|| format!("create {}", dest.display()))?;
-    let mut buf = [0u8; 1024 * 64];
-    loop {
-        let n = resp.read(&mut buf)?;
-        if n == 0 { break; }
-        out.write_all(&buf[..n])?;
+/// The directory in `staged_dist` that holds `artifact`'s engine: the one its
+/// definition names, or else the only one laid out for its runner
+fn staged_root_subdir(artifact: &EngineArtifact, staged_dist: &Path, archive_path: &Path) -> Result<String> {
+    let runner = artifact.runner.as_str();
+    if let Some(subdir) = &artifact.root_subdir {
+        if artifact.runner.matches(&staged_dist.join(subdir)) {
+            return Ok(subdir.clone());
+        }
+        return Err(anyhow!(
+            "engine {} expects a {runner} build in {subdir}/ but {} has none there (check layout.proton_root_subdir)",
+            artifact.id,
+            archive_path.display()
+        ));
+    }
+    let mut found: Vec<String> = fs::read_dir(staged_dist)?
+        .flatten()
+        .filter(|e| artifact.runner.matches(&e.path()))
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    found.sort();
+    match found.len() {
+        0 => Err(anyhow!("engine {} is declared as a {runner} build but {} contains none", artifact.id, archive_path.display())),
+        1 => Ok(found.remove(0)),
+        _ => Err(anyhow!(
+            "engine {}: {} contains several {runner} builds ({}); name one in layout.proton_root_subdir",
+            artifact.id,
+            archive_path.display(),
+            found.join(", ")
+        )),
     }
-    Ok(())
 }
 
-fn verify_sha256(path: &Path, expected_hex: &str) -> Result<()> {
-    let mut f = File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buf = [0u8; 1024 * 128];
-    loop {
-        let n = f.read(&mut buf)?;
-        if n == 0 { break; }
-        hasher.update(&buf[..n]);
-    }
-    let got = hex::encode(hasher.finalize());
-    if got.to_lowercase() != expected_hex.to_lowercase() {
-        return Err(anyhow!(
-            "sha256 mismatch for {}: expected {}, got {}",
-            path.display(),
-            expected_hex,
-            got
-        ));
+/// Archive extension of a download URL, defaulting to `.tar.gz`
+fn archive_extension(url: &str) -> &'static str {
+    let name = url.rsplit('/').next().unwrap_or("").split(['?', '#']).next().unwrap_or("").to_lowercase();
+    [".tar.gz", ".tgz", ".tar.xz", ".txz", ".zip"]
+        .into_iter()
+        .find(|ext| name.ends_with(ext))
+        .unwrap_or(".tar.gz")
+}
+
+enum ArchiveKind {
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+/// Detect the archive format from magic bytes, falling back to the file extension
+fn archive_kind(archive: &Path) -> Result<ArchiveKind> {
+    let mut magic = [0u8; 6];
+    let n = File::open(archive)?.read(&mut magic)?;
+    let magic = &magic[..n];
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        return Ok(ArchiveKind::TarGz);
+    }
+    if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        return Ok(ArchiveKind::TarXz);
+    }
+    if magic.starts_with(b"PK\x03\x04") {
+        return Ok(ArchiveKind::Zip);
+    }
+
+    let name = archive.to_string_lossy().to_lowercase();
+    match archive_extension(&name) {
+        ".tar.xz" | ".txz" => Ok(ArchiveKind::TarXz),
+        ".zip" => Ok(ArchiveKind::Zip),
+        _ if name.ends_with(".tar.gz") || name.ends_with(".tgz") => Ok(ArchiveKind::TarGz),
+        _ => Err(anyhow!("unsupported archive format: {}", archive.display())),
     }
-    Ok(())
 }
 
-fn extract_tar_gz(archive: &Path, dest_dir: &Path) -> Result<()> {
+fn extract_archive(archive: &Path, dest_dir: &Path) -> Result<()> {
     let f = File::open(archive)?;
-    let gz = flate2::read::GzDecoder::new(f);
-    let mut ar = tar::Archive::new(gz);
+    match archive_kind(archive)? {
+        ArchiveKind::TarGz => unpack_tar(flate2::read::GzDecoder::new(f), dest_dir),
+        ArchiveKind::TarXz => unpack_tar(xz2::read::XzDecoder::new(f), dest_dir),
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(f).with_context(|| format!("open zip {}", archive.display()))?;
+            zip.extract(dest_dir).with_context(|| format!("unzip to {}", dest_dir.display()))?;
+            Ok(())
+        }
+    }
+}
+
+fn unpack_tar(reader: impl Read, dest_dir: &Path) -> Result<()> {
+    let mut ar = tar::Archive::new(reader);
     ar.unpack(dest_dir).with_context(|| format!("unpack to {}", dest_dir.display()))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A gzipped tarball holding `<subdir>/proton`
+    fn proton_tarball(path: &Path, subdir: &str) {
+        let gz = flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::fast());
+        let mut tar = tar::Builder::new(gz);
+        let script = b"#!/bin/sh\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(script.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tar.append_data(&mut header, format!("{subdir}/proton"), &script[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn installs_a_local_archive_without_downloading() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("GE-Proton-test.tar.gz");
+        proton_tarball(&archive, "GE-Proton-test");
+        let artifact = EngineArtifact {
+            id: "ge-proton-test".to_string(),
+            url: "https://example.invalid/GE-Proton-test.tar.gz".to_string(),
+            mirrors: Vec::new(),
+            checksum: Some(http::sha256_file(&archive).unwrap()),
+            version: None,
+            repo: None,
+            local: Some(archive.clone()),
+            runner: Runner::Proton,
+            root_subdir: None,
+        };
+        let engines_dir = tmp.path().join("engines");
+        let downloads_dir = tmp.path().join("downloads");
+
+        let dist = install_artifact(&artifact, &engines_dir, &downloads_dir, |_, _| {}).await.unwrap();
+
+        let p = engine_paths(&engines_dir, "ge-proton-test");
+        assert_eq!(dist, p.dist);
+        assert!(p.dist.join("GE-Proton-test/proton").is_file());
+        let marker: serde_json::Value = serde_json::from_slice(&fs::read(&p.marker).unwrap()).unwrap();
+        assert_eq!(marker["engine_id"], "ge-proton-test");
+        assert_eq!(marker["sha256"], artifact.checksum.as_deref().unwrap());
+        assert_eq!(marker["layout"]["proton_root_subdir"], "GE-Proton-test");
+        assert_eq!(marker["layout"]["runner"], "proton");
+        // Nothing was fetched
+        assert!(!downloads_dir.exists());
+    }
+}
@@ -1 +1,51 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::models::EngineLayout;
+use runner::Runner;
+
 pub mod install;
+pub mod releases;
+pub mod runner;
+pub mod switch;
+pub mod version;
+
+/// The layout recorded in the `installed.json` marker of the engine at
+/// `engine_dir` (`engines/<id>`). `None` for engines installed before ELM
+/// recorded it, or when the marker is missing or unreadable.
+pub fn installed_layout(engine_dir: &Path) -> Option<EngineLayout> {
+    let marker: serde_json::Value = serde_json::from_slice(&fs::read(engine_dir.join("installed.json")).ok()?).ok()?;
+    serde_json::from_value(marker.get("layout")?.clone()).ok()
+}
+
+/// The Proton (or Wine) root inside an engine's `dist`. With a `layout` this
+/// is `dist/<proton_root_subdir>`; without one, the first subdirectory that
+/// looks like a Proton or Wine build.
+pub fn resolve_proton_root(dist: &Path, layout: Option<&EngineLayout>) -> Result<PathBuf> {
+    if let Some(layout) = layout {
+        let root = dist.join(&layout.proton_root_subdir);
+        let runner = Runner::parse(&layout.runner)?;
+        if !runner.matches(&root) {
+            return Err(anyhow!(
+                "engine layout names {} as its {} root, but {} is not a {} build (reinstall the engine)",
+                layout.proton_root_subdir,
+                runner.as_str(),
+                root.display(),
+                runner.as_str()
+            ));
+        }
+        return Ok(root);
+    }
+
+    let mut candidates: Vec<PathBuf> = fs::read_dir(dist)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| Runner::detect(p).is_ok())
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No Proton or Wine build found in {}", dist.display()))
+}
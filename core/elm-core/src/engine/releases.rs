@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::blocking::Client;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::http::{self, HttpConfig};
+
+/// Where engine updates come from unless the manifest says otherwise
+pub const DEFAULT_REPO: &str = "GloriousEggroll/proton-ge-custom";
+
+const API: &str = "https://api.github.com";
+
+/// Archive types we can install from a release
+const ARCHIVE_SUFFIXES: &[&str] = &[".tar.gz", ".tar.xz", ".tgz", ".txz"];
+
+const CHECKSUM_SUFFIXES: &[&str] = &[".sha512sum", ".sha256sum", ".sha512", ".sha256"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    #[serde(rename = "browser_download_url")]
+    pub url: String,
+    /// Bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    #[serde(rename = "tag_name")]
+    pub tag: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    /// The release's Proton/Wine build archive (skips checksums and other assets)
+    pub fn tarball(&self) -> Option<&ReleaseAsset> {
+        self.assets
+            .iter()
+            .find(|a| ARCHIVE_SUFFIXES.iter().any(|s| a.name.to_lowercase().ends_with(s)))
+    }
+
+    /// The checksum file published next to `asset` (`GE-Proton10-27.sha512sum`, `*.sha256`, ...)
+    pub fn checksum_asset(&self, asset: &ReleaseAsset) -> Option<&ReleaseAsset> {
+        let stem = ARCHIVE_SUFFIXES
+            .iter()
+            .find_map(|s| asset.name.to_lowercase().strip_suffix(s).map(str::to_string))?;
+        self.assets.iter().find(|a| {
+            let name = a.name.to_lowercase();
+            name.starts_with(&stem) && CHECKSUM_SUFFIXES.iter().any(|s| name.ends_with(s))
+        })
+    }
+}
+
+/// Fetch and parse the expected digest of `asset` from the release's checksum
+/// file; `None` if the release doesn't publish one
+pub fn fetch_checksum(release: &Release, asset: &ReleaseAsset) -> Result<Option<String>> {
+    let Some(sum) = release.checksum_asset(asset) else {
+        return Ok(None);
+    };
+    let body = api_get(&sum.url)?;
+    // `sha512sum` format: "<hex>  <file name>", possibly listing several files
+    let digest = body
+        .lines()
+        .find(|l| l.contains(&asset.name))
+        .or_else(|| body.lines().next())
+        .and_then(|l| l.split_whitespace().next())
+        .filter(|h| matches!(h.len(), 64 | 128) && h.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| anyhow!("could not read a checksum from {}", sum.name))?;
+    Ok(Some(digest.to_lowercase()))
+}
+
+/// Check `repo` looks like `owner/name`
+pub fn validate_repo(repo: &str) -> Result<()> {
+    let valid_part = |p: &str| {
+        !p.is_empty() && p.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    match repo.split_once('/') {
+        Some((owner, name)) if valid_part(owner) && valid_part(name) => Ok(()),
+        _ => Err(anyhow!("invalid GitHub repository '{}' (expected owner/name)", repo)),
+    }
+}
+
+/// GET `url`, retrying transient failures per [`HttpConfig::from_env`]
+fn api_get(url: &str) -> Result<String> {
+    let config = HttpConfig::from_env();
+    let client = http::blocking_client(&config)?;
+    let mut attempt = 0;
+    loop {
+        match api_get_once(&client, url) {
+            Err(e) if attempt < config.retries && http::is_transient(&e) => {
+                attempt += 1;
+                std::thread::sleep(config.retry_delay(attempt));
+            }
+            result => return result,
+        }
+    }
+}
+
+fn api_get_once(client: &Client, url: &str) -> Result<String> {
+    let mut req = client.get(url).header(ACCEPT, "application/vnd.github+json");
+    // Authenticated requests get a much higher rate limit
+    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        if !token.is_empty() {
+            req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+    }
+    let resp = req.send().with_context(|| format!("GET {url}"))?;
+
+    let status = resp.status();
+    let rate_limited = resp
+        .headers()
+        .get("x-ratelimit-remaining")
+        .is_some_and(|v| v.as_bytes() == b"0");
+    if status == StatusCode::TOO_MANY_REQUESTS || (status == StatusCode::FORBIDDEN && rate_limited) {
+        let reset = resp
+            .headers()
+            .get("x-ratelimit-reset")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .and_then(|reset| {
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).ok()?;
+                Some(reset.saturating_sub(now.as_secs()).div_ceil(60))
+            });
+        let when = reset.map(|m| format!(" (resets in ~{m} min)")).unwrap_or_default();
+        return Err(anyhow!("GitHub API rate limit exceeded{when}; set GITHUB_TOKEN to raise the limit"));
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err(anyhow!("not found: {url}"));
+    }
+    let resp = resp.error_for_status()?;
+    resp.text().with_context(|| format!("reading {url}"))
+}
+
+/// Published (non-draft) releases of `repo`, newest first, at most `limit`
+pub fn list_releases(repo: &str, limit: usize) -> Result<Vec<Release>> {
+    validate_repo(repo)?;
+    let url = format!("{API}/repos/{repo}/releases?per_page={}", limit.clamp(1, 100));
+    let body = api_get(&url)?;
+    let releases: Vec<Release> = serde_json::from_str(&body).with_context(|| format!("parsing {url}"))?;
+    Ok(releases.into_iter().filter(|r| !r.draft).take(limit).collect())
+}
+
+/// The release GitHub marks as latest (never a draft or prerelease)
+pub fn latest_release(repo: &str) -> Result<Release> {
+    validate_repo(repo)?;
+    let url = format!("{API}/repos/{repo}/releases/latest");
+    let body = api_get(&url)?;
+    serde_json::from_str(&body).with_context(|| format!("parsing {url}"))
+}
@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::integration::steam;
+
+/// Runner names accepted in an engine definition's `layout.runner`
+pub const RUNNERS: &[&str] = &["proton", "wine"];
+
+/// How an engine's Wine is driven
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runner {
+    /// A Proton build (GE-Proton, Valve's): `python3 <root>/proton run <exe>`
+    /// with Steam's compat environment
+    Proton,
+    /// A plain Wine build (Wine-GE and the like): `<root>/bin/wine <exe>`
+    /// with `WINEPREFIX`
+    Wine,
+}
+
+impl Runner {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "proton" => Ok(Runner::Proton),
+            "wine" => Ok(Runner::Wine),
+            other => Err(anyhow!("unknown engine runner '{other}' (expected one of: {})", RUNNERS.join(", "))),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Runner::Proton => "proton",
+            Runner::Wine => "wine",
+        }
+    }
+
+    /// Whether the engine unpacked at `root` is laid out for this runner
+    pub fn matches(self, root: &Path) -> bool {
+        match self {
+            Runner::Proton => root.join("proton").is_file(),
+            Runner::Wine => root.join("bin/wine").is_file(),
+        }
+    }
+
+    /// The runner of the engine unpacked at `root`, from its layout
+    pub fn detect(root: &Path) -> Result<Self> {
+        [Runner::Proton, Runner::Wine].into_iter().find(|r| r.matches(root)).ok_or_else(|| {
+            anyhow!(
+                "{} is neither a Proton build (no proton script) nor a Wine build (no bin/wine)",
+                root.display()
+            )
+        })
+    }
+}
+
+/// Command that runs `program` (a Windows exe, or a Wine tool such as
+/// `wineboot`) in the prefix at `prefix_dir` with the engine at `root`. Both
+/// runners keep the Wine prefix in `pfx/`, so prefixes look the same either way.
+pub fn command(root: &Path, prefix_dir: &Path, program: impl AsRef<OsStr>) -> Result<Command> {
+    command_with(root, prefix_dir, program, steam::steam_root)
+}
+
+/// [`command`] with the Steam install supplied by `steam_root` instead of looked
+/// up; the result can be inspected through `Command::as_std` without running it
+pub fn command_with(
+    root: &Path,
+    prefix_dir: &Path,
+    program: impl AsRef<OsStr>,
+    steam_root: impl FnOnce() -> Result<PathBuf>,
+) -> Result<Command> {
+    match Runner::detect(root)? {
+        Runner::Proton => {
+            let mut cmd = Command::new("python3");
+            cmd.arg(root.join("proton"))
+                .env("STEAM_COMPAT_DATA_PATH", prefix_dir)
+                .env("STEAM_COMPAT_CLIENT_INSTALL_PATH", steam_root()?)
+                .arg("run")
+                .arg(program);
+            Ok(cmd)
+        }
+        Runner::Wine => {
+            let mut cmd = Command::new(root.join("bin/wine"));
+            cmd.env("WINEPREFIX", prefix_dir.join("pfx")).arg(program);
+            Ok(cmd)
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::install::engine_paths;
+use crate::config::edit::set_engine_ref;
+
+/// A first launch on a new engine that ends abnormally within this long rolls back
+pub const ROLLBACK_WINDOW: Duration = Duration::from_secs(60);
+
+const STATE_FILE: &str = "engine-switch.json";
+
+/// The last engine change `elm update` made to a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineSwitch {
+    pub previous: String,
+    pub current: String,
+    /// Manifest whose `engine.ref` was changed
+    pub manifest: PathBuf,
+    /// Unix seconds
+    pub switched_at: u64,
+    /// Set once EVE has run properly on `current`; no automatic rollback after that
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+fn state_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(STATE_FILE)
+}
+
+fn save(data_dir: &Path, switch: &EngineSwitch) -> Result<()> {
+    fs::create_dir_all(data_dir)?;
+    let path = state_path(data_dir);
+    fs::write(&path, serde_json::to_vec_pretty(switch)?).with_context(|| format!("writing {}", path.display()))
+}
+
+/// Remember that `manifest` moved from `previous` to `current`
+pub fn record(data_dir: &Path, manifest: &Path, previous: &str, current: &str) -> Result<()> {
+    let switched_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    save(
+        data_dir,
+        &EngineSwitch {
+            previous: previous.to_string(),
+            current: current.to_string(),
+            manifest: manifest.to_path_buf(),
+            switched_at,
+            confirmed: false,
+        },
+    )
+}
+
+pub fn load(data_dir: &Path) -> Result<Option<EngineSwitch>> {
+    let path = state_path(data_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    Ok(Some(serde_json::from_str(&content).with_context(|| format!("parsing {}", path.display()))?))
+}
+
+/// Mark the current engine as known good
+pub fn confirm(data_dir: &Path) -> Result<()> {
+    if let Some(mut switch) = load(data_dir)? {
+        switch.confirmed = true;
+        save(data_dir, &switch)?;
+    }
+    Ok(())
+}
+
+/// Point the manifest back at the engine it used before the last update.
+/// Fails if there is nothing to roll back or the previous engine has been removed.
+pub fn rollback(data_dir: &Path) -> Result<EngineSwitch> {
+    let switch = load(data_dir)?.ok_or_else(|| anyhow!("no engine update to roll back"))?;
+    let previous = engine_paths(&data_dir.join("engines"), &switch.previous);
+    if !previous.marker.exists() {
+        return Err(anyhow!(
+            "previous engine {} is no longer installed (removed by elm clean?)",
+            switch.previous
+        ));
+    }
+    set_engine_ref(&switch.manifest, &switch.previous)?;
+    fs::remove_file(state_path(data_dir))?;
+    Ok(switch)
+}
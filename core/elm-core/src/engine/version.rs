@@ -0,0 +1,103 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// An engine release version parsed from a tag or engine id such as
+/// `GE-Proton10-27`, `ge-proton9-4-rc2` or `proton-tkg-8.0.r10`.
+///
+/// Versions order by their numeric components, so `GE-Proton10-10` is newer
+/// than `GE-Proton10-9`. A pre-release (`-rc2`, `-beta1`) sorts before the
+/// release it precedes.
+#[derive(Debug, Clone)]
+pub struct Version {
+    /// Lowercased leading name, e.g. `ge-proton`
+    pub flavor: String,
+    pub parts: Vec<u64>,
+    /// Pre-release label and number, e.g. (`rc`, 2)
+    pub pre: Option<(String, u64)>,
+    raw: String,
+}
+
+const PRE_LABELS: &[&str] = &["rc", "beta", "alpha", "pre"];
+
+/// Parse a release tag or engine id. Never fails: tags without numbers
+/// become a version with no parts, which sorts before any numbered one.
+pub fn parse(tag: &str) -> Version {
+    let lower = tag.to_lowercase();
+    let flavor_end = lower.find(|c: char| c.is_ascii_digit()).unwrap_or(lower.len());
+    let flavor = lower[..flavor_end].trim_end_matches(['-', '_', '.', ' ']).to_string();
+
+    let mut parts = Vec::new();
+    let mut pre = None;
+    let mut label = String::new();
+    let mut rest = &lower[flavor_end..];
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if digits > 0 {
+            let n = rest[..digits].parse().unwrap_or(u64::MAX);
+            match PRE_LABELS.iter().find(|l| label.ends_with(*l)) {
+                Some(l) if pre.is_none() => pre = Some((l.to_string(), n)),
+                _ => parts.push(n),
+            }
+            label.clear();
+            rest = &rest[digits..];
+        } else {
+            let c = rest.chars().next().unwrap_or_default();
+            if c.is_ascii_alphabetic() {
+                label.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    // A trailing label without a number ("-rc") still marks a pre-release
+    if pre.is_none() {
+        if let Some(l) = PRE_LABELS.iter().find(|l| label.ends_with(*l)) {
+            pre = Some((l.to_string(), 0));
+        }
+    }
+
+    Version { flavor, parts, pre, raw: tag.to_string() }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.parts.len().max(other.parts.len());
+        for i in 0..len {
+            let a = self.parts.get(i).copied().unwrap_or(0);
+            let b = other.parts.get(i).copied().unwrap_or(0);
+            match a.cmp(&b) {
+                Ordering::Equal => {}
+                ord => return ord,
+            }
+        }
+        let pre = match (&self.pre, &other.pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some((la, na)), Some((lb, nb))) => {
+                let rank = |l: &str| PRE_LABELS.iter().rev().position(|p| *p == l);
+                rank(la).cmp(&rank(lb)).then(na.cmp(nb))
+            }
+        };
+        pre.then_with(|| self.flavor.cmp(&other.flavor))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::rollback::verify::CorruptSnapshot;
+
+/// Failures a frontend may want to tell apart, returned by elm-core's entry
+/// points (engine install, launch, restore, [`crate::ElmContext`] lookups).
+/// Internals still use `anyhow`; a typed failure raised inside them survives
+/// the trip back out, anything else arrives as [`ElmError::Other`].
+#[derive(Debug, Error)]
+pub enum ElmError {
+    #[error("engine {id} is not installed (nothing at {}). Run: elm update --install", path.display())]
+    EngineNotInstalled { id: String, path: PathBuf },
+
+    /// Every source was tried; `reason` has what went wrong with each
+    #[error("{reason}")]
+    Download { urls: Vec<String>, reason: String },
+
+    #[error("{algorithm} mismatch for {}: expected {expected}, got {got}", path.display())]
+    ChecksumMismatch { path: PathBuf, algorithm: &'static str, expected: String, got: String },
+
+    #[error(transparent)]
+    CorruptSnapshot(#[from] CorruptSnapshot),
+
+    #[error("launch cancelled")]
+    LaunchCancelled,
+
+    /// Proton exited unsuccessfully, with `exit_code` or killed by `signal`
+    #[error("{}", launch_status(.exit_code, .signal))]
+    LaunchFailed { exit_code: Option<i32>, signal: Option<i32> },
+
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+pub type Result<T, E = ElmError> = std::result::Result<T, E>;
+
+impl From<anyhow::Error> for ElmError {
+    /// Recover the typed error an internal function raised, if it was one
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<ElmError>() {
+            Ok(elm) => return elm,
+            Err(err) => err,
+        };
+        match err.downcast::<CorruptSnapshot>() {
+            Ok(corrupt) => ElmError::CorruptSnapshot(corrupt),
+            Err(err) => ElmError::Other(err),
+        }
+    }
+}
+
+fn launch_status(exit_code: &Option<i32>, signal: &Option<i32>) -> String {
+    match (exit_code, signal) {
+        (Some(code), _) => format!("launch failed with exit code {code}"),
+        (None, Some(sig)) => format!("launch killed by signal {sig}"),
+        (None, None) => "launch ended with unknown status".to_string(),
+    }
+}
@@ -0,0 +1,583 @@
+use anyhow::{anyhow, Context, Result};
+use futures_util::StreamExt;
+use reqwest::Client;
+use reqwest::header::{HeaderName, ACCEPT_RANGES, CONTENT_LENGTH, RANGE};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256, Sha512};
+use std::fs::{self, File};
+use std::io::{Read, SeekFrom};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::error::ElmError;
+
+const USER_AGENT: &str = concat!("elm/", env!("CARGO_PKG_VERSION"));
+
+/// Download cap set by the frontend in bytes/s (0 for none); see [`set_limit_rate`]
+static LIMIT_RATE: AtomicU64 = AtomicU64::new(0);
+
+/// Cap every download started from now on at `bytes_per_sec`, taking
+/// precedence over `ELM_HTTP_LIMIT_RATE`. `None` lifts the cap again.
+pub fn set_limit_rate(bytes_per_sec: Option<u64>) {
+    LIMIT_RATE.store(bytes_per_sec.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Connection count set by the frontend (0 for unset); see [`set_connections`]
+static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Use up to `connections` parallel range requests for large downloads,
+/// taking precedence over `ELM_HTTP_CONNECTIONS`
+pub fn set_connections(connections: usize) {
+    CONNECTIONS.store(connections as u64, Ordering::Relaxed);
+}
+
+/// Files smaller than this are always fetched over one connection
+const CHUNKED_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Timeout and retry policy for ELM's HTTP requests
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Give up on a connection that sends nothing for this long
+    pub timeout: Duration,
+    /// Extra attempts after a transient failure (connection error, timeout, 5xx)
+    pub retries: u32,
+    /// Wait before the first retry; doubles with each further attempt
+    pub backoff: Duration,
+    /// Hold downloads to this many bytes per second
+    pub limit_rate: Option<u64>,
+    /// Parallel range requests for a large download, when the server takes them
+    pub connections: usize,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            timeout: Duration::from_secs(60),
+            retries: 3,
+            backoff: Duration::from_secs(2),
+            limit_rate: None,
+            connections: 4,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Defaults, overridden by `ELM_HTTP_TIMEOUT` (seconds), `ELM_HTTP_RETRIES`,
+    /// `ELM_HTTP_LIMIT_RATE` (KB/s) and `ELM_HTTP_CONNECTIONS`, with the values
+    /// from [`set_limit_rate`] and [`set_connections`] on top
+    pub fn from_env() -> Self {
+        let mut config = HttpConfig::default();
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        if let Some(secs) = var("ELM_HTTP_TIMEOUT").filter(|s| *s > 0) {
+            config.timeout = Duration::from_secs(secs);
+        }
+        if let Some(retries) = var("ELM_HTTP_RETRIES") {
+            config.retries = retries.min(u32::MAX as u64) as u32;
+        }
+        if let Some(kbps) = var("ELM_HTTP_LIMIT_RATE").filter(|k| *k > 0) {
+            config.limit_rate = Some(kbps.saturating_mul(1024));
+        }
+        if let Some(connections) = var("ELM_HTTP_CONNECTIONS").filter(|c| *c > 0) {
+            config.connections = connections.min(16) as usize;
+        }
+        match LIMIT_RATE.load(Ordering::Relaxed) {
+            0 => {}
+            rate => config.limit_rate = Some(rate),
+        }
+        match CONNECTIONS.load(Ordering::Relaxed) {
+            0 => {}
+            connections => config.connections = connections.min(16) as usize,
+        }
+        config
+    }
+
+    /// Delay before retry number `attempt` (1-based)
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(1 << attempt.saturating_sub(1).min(10))
+    }
+}
+
+/// Async client for downloads. The timeout applies to each read, not the
+/// whole transfer, so large archives on slow links still complete.
+pub fn client(config: &HttpConfig) -> Result<Client> {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(config.timeout)
+        .read_timeout(config.timeout)
+        .build()
+        .context("building HTTP client")
+}
+
+/// Blocking client for small API requests; the timeout covers the whole request
+pub fn blocking_client(config: &HttpConfig) -> Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(config.timeout)
+        .build()
+        .context("building HTTP client")
+}
+
+/// A download whose body stopped short of its Content-Length
+#[derive(Debug)]
+struct IncompleteDownload {
+    url: String,
+    downloaded: u64,
+    total: u64,
+}
+
+impl std::fmt::Display for IncompleteDownload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "download of {} ended early ({} of {} bytes); re-run to resume",
+            self.url, self.downloaded, self.total
+        )
+    }
+}
+
+impl std::error::Error for IncompleteDownload {}
+
+/// Sleeps between chunks to hold a transfer to `rate` bytes/s, measured over
+/// one-second intervals so a stall doesn't earn a burst afterwards
+struct Throttle {
+    rate: u64,
+    interval_start: Instant,
+    sent: u64,
+}
+
+impl Throttle {
+    fn new(rate: u64) -> Self {
+        Throttle { rate, interval_start: Instant::now(), sent: 0 }
+    }
+
+    async fn consume(&mut self, bytes: u64) {
+        self.sent += bytes;
+        let due = Duration::from_secs_f64(self.sent as f64 / self.rate as f64);
+        let elapsed = self.interval_start.elapsed();
+        if due > elapsed {
+            tokio::time::sleep(due - elapsed).await;
+        }
+        if self.interval_start.elapsed() >= Duration::from_secs(1) {
+            self.interval_start = Instant::now();
+            self.sent = 0;
+        }
+    }
+}
+
+/// Whether `err` is worth retrying: connection problems, timeouts, truncated
+/// bodies and 5xx responses, but not 4xx or local I/O errors
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if cause.is::<IncompleteDownload>() {
+            return true;
+        }
+        let Some(e) = cause.downcast_ref::<reqwest::Error>() else { return false };
+        match e.status() {
+            Some(status) => status.is_server_error(),
+            None => e.is_connect() || e.is_timeout() || e.is_body() || e.is_request(),
+        }
+    })
+}
+
+/// [`download_to_file`], retrying transient failures with exponential backoff.
+/// Each retry resumes from the partial file. A large fresh download from a
+/// server that takes range requests goes through [`download_chunked`] instead.
+pub async fn download_with_retry(
+    client: &Client,
+    config: &HttpConfig,
+    url: &str,
+    dest: &Path,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<String> {
+    if config.connections > 1 && !part_path(dest).exists() {
+        match ranged_length(client, url).await {
+            Ok(Some(total)) if total >= CHUNKED_MIN_SIZE => {
+                return download_chunked(client, config, url, dest, total, &mut progress).await;
+            }
+            Ok(_) => debug!("{url}: small or no range support; using one connection"),
+            Err(e) => debug!("{url}: probing for range support failed ({e:#}); using one connection"),
+        }
+    }
+
+    let mut attempt = 0;
+    loop {
+        match download_to_file(client, config, url, dest, &mut progress).await {
+            Err(e) if attempt < config.retries && is_transient(&e) => {
+                attempt += 1;
+                let delay = config.retry_delay(attempt);
+                warn!(
+                    "{:#}; retrying in {}s (attempt {} of {})",
+                    e,
+                    delay.as_secs(),
+                    attempt,
+                    config.retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Partial download path for `dest` (`<dest>.part`)
+pub fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// Download `url` to `dest`, resuming from `<dest>.part` if a previous attempt was interrupted.
+/// `progress` is called with (bytes downloaded, total bytes if known) as data arrives.
+/// `dest` only appears once the download has completed, and is fetched no faster
+/// than `config.limit_rate`.
+/// Returns the lowercase hex SHA256 of the file, computed as bytes stream in.
+pub async fn download_to_file(
+    client: &Client,
+    config: &HttpConfig,
+    url: &str,
+    dest: &Path,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<String> {
+    let part = part_path(dest);
+    let mut offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+
+    debug!("GET {url}");
+    let mut req = client.get(url);
+    if offset > 0 {
+        debug!("resuming {} at {offset} bytes", part.display());
+        req = req.header(RANGE, format!("bytes={offset}-"));
+    }
+    let mut resp = req.send().await.with_context(|| format!("GET {url}"))?;
+
+    if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // Stale or oversized partial file; start over
+        debug!("{url}: partial file not resumable, starting over");
+        fs::remove_file(&part).with_context(|| format!("remove {}", part.display()))?;
+        offset = 0;
+        resp = client.get(url).send().await.with_context(|| format!("GET {url}"))?;
+    }
+    resp.error_for_status_ref()?;
+
+    let mut hasher = Sha256::new();
+    let mut out = if offset > 0 && resp.status() == StatusCode::PARTIAL_CONTENT {
+        // Resuming: only the bytes already on disk need hashing from the file,
+        // off the runtime since a large partial file takes a while to read
+        let on_disk = part.clone();
+        hasher = tokio::task::spawn_blocking(move || -> Result<Sha256> {
+            let mut hasher = Sha256::new();
+            let mut f = File::open(&on_disk).with_context(|| format!("open {}", on_disk.display()))?;
+            hash_reader(&mut f, &mut hasher)?;
+            Ok(hasher)
+        })
+        .await
+        .map_err(|e| anyhow!("hash task failed: {e}"))
+        .and_then(|r| r)?;
+        tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&part)
+            .await
+            .with_context(|| format!("open {}", part.display()))?
+    } else {
+        // Server ignored the range (200): restart from scratch
+        offset = 0;
+        tokio::fs::File::create(&part).await.with_context(|| format!("create {}", part.display()))?
+    };
+
+    let total = resp.content_length().map(|len| len + offset);
+    let mut downloaded = offset;
+    let mut throttle = config.limit_rate.map(Throttle::new);
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.with_context(|| format!("reading {url}"))?;
+        out.write_all(&chunk).await?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+        // Report progress after the pause, so the rate it shows is the capped one
+        if let Some(throttle) = &mut throttle {
+            throttle.consume(chunk.len() as u64).await;
+        }
+        progress(downloaded, total);
+    }
+    out.flush().await?;
+
+    if let Some(total) = total {
+        if downloaded < total {
+            return Err(IncompleteDownload { url: url.to_string(), downloaded, total }.into());
+        }
+    }
+
+    fs::rename(&part, dest).with_context(|| format!("rename {} to {}", part.display(), dest.display()))?;
+    let sha256 = hex::encode(hasher.finalize());
+    debug!("downloaded {url} to {} ({downloaded} bytes, sha256 {sha256})", dest.display());
+    Ok(sha256)
+}
+
+/// Size of `url` if the server says it takes byte range requests
+async fn ranged_length(client: &Client, url: &str) -> Result<Option<u64>> {
+    debug!("HEAD {url}");
+    let resp = client.head(url).send().await.with_context(|| format!("HEAD {url}"))?;
+    resp.error_for_status_ref()?;
+    let header = |name: HeaderName| resp.headers().get(name).and_then(|v| v.to_str().ok());
+    if header(ACCEPT_RANGES) != Some("bytes") {
+        return Ok(None);
+    }
+    Ok(header(CONTENT_LENGTH).and_then(|len| len.parse().ok()))
+}
+
+/// Download `url` (`total` bytes) to `dest` over [`HttpConfig::connections`]
+/// concurrent range requests, each written straight to its place in
+/// `<dest>.part` and retried on its own. `progress` works as for
+/// [`download_to_file`]; the rate cap is shared between the connections.
+/// Returns the SHA256 of the assembled file.
+pub async fn download_chunked(
+    client: &Client,
+    config: &HttpConfig,
+    url: &str,
+    dest: &Path,
+    total: u64,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<String> {
+    let part = part_path(dest);
+    File::create(&part)
+        .and_then(|f| f.set_len(total))
+        .with_context(|| format!("create {}", part.display()))?;
+
+    let connections = config.connections.max(1) as u64;
+    let chunk_size = total.div_ceil(connections);
+    debug!("downloading {url} over {connections} connections ({chunk_size} bytes each)");
+    let downloaded = AtomicU64::new(0);
+    let ranges = (0..connections)
+        .map(|i| (i * chunk_size, ((i + 1) * chunk_size).min(total)))
+        .filter(|(start, end)| start < end);
+    let fetches = futures_util::future::try_join_all(
+        ranges.map(|(start, end)| download_range(client, config, url, &part, start, end, &downloaded)),
+    );
+    tokio::pin!(fetches);
+
+    let result = loop {
+        tokio::select! {
+            result = &mut fetches => break result,
+            _ = tokio::time::sleep(Duration::from_millis(250)) => {
+                progress(downloaded.load(Ordering::Relaxed), Some(total));
+            }
+        }
+    };
+    if let Err(e) = result {
+        // A file with holes in it can't be resumed from
+        let _ = fs::remove_file(&part);
+        return Err(e);
+    }
+    progress(total, Some(total));
+
+    // The pieces arrived out of order, so hash the file as a whole, off the
+    // runtime like any other long blocking read
+    let assembled = part.clone();
+    let sha256 = tokio::task::spawn_blocking(move || sha256_file(&assembled))
+        .await
+        .map_err(|e| anyhow!("hash task failed: {e}"))
+        .and_then(|r| r)?;
+    fs::rename(&part, dest).with_context(|| format!("rename {} to {}", part.display(), dest.display()))?;
+    debug!("downloaded {url} to {} ({total} bytes, sha256 {sha256})", dest.display());
+    Ok(sha256)
+}
+
+/// Fetch bytes `start..end` of `url` into the same range of `part`, resuming
+/// within the range after a transient failure
+async fn download_range(
+    client: &Client,
+    config: &HttpConfig,
+    url: &str,
+    part: &Path,
+    start: u64,
+    end: u64,
+    downloaded: &AtomicU64,
+) -> Result<()> {
+    let mut throttle = config.limit_rate.map(|rate| Throttle::new((rate / config.connections.max(1) as u64).max(1)));
+    let mut pos = start;
+    let mut attempt = 0;
+    loop {
+        match fetch_range(client, url, part, &mut pos, end, downloaded, &mut throttle).await {
+            Err(e) if attempt < config.retries && is_transient(&e) => {
+                attempt += 1;
+                let delay = config.retry_delay(attempt);
+                warn!("{:#}; retrying in {}s (attempt {} of {})", e, delay.as_secs(), attempt, config.retries);
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn fetch_range(
+    client: &Client,
+    url: &str,
+    part: &Path,
+    pos: &mut u64,
+    end: u64,
+    downloaded: &AtomicU64,
+    throttle: &mut Option<Throttle>,
+) -> Result<()> {
+    debug!("GET {url} bytes {}-{}", *pos, end - 1);
+    let resp = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", *pos, end - 1))
+        .send()
+        .await
+        .with_context(|| format!("GET {url}"))?;
+    resp.error_for_status_ref()?;
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(anyhow!("{url} answered a range request with {}", resp.status()));
+    }
+
+    let mut out = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(part)
+        .await
+        .with_context(|| format!("open {}", part.display()))?;
+    out.seek(SeekFrom::Start(*pos)).await?;
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.with_context(|| format!("reading {url}"))?;
+        let take = chunk.len().min((end - *pos) as usize);
+        out.write_all(&chunk[..take]).await?;
+        *pos += take as u64;
+        downloaded.fetch_add(take as u64, Ordering::Relaxed);
+        if let Some(throttle) = throttle {
+            throttle.consume(take as u64).await;
+        }
+        if *pos == end {
+            break;
+        }
+    }
+    out.flush().await?;
+
+    if *pos < end {
+        return Err(IncompleteDownload { url: url.to_string(), downloaded: *pos, total: end }.into());
+    }
+    Ok(())
+}
+
+/// Download the first of `urls` that succeeds and, when `checksum` (SHA256 or
+/// SHA512 hex) is given, matches it. Each URL gets the usual retries; one that
+/// fails or serves the wrong bytes is skipped for the next. Returns the SHA256.
+pub async fn download_from_mirrors(
+    client: &Client,
+    config: &HttpConfig,
+    urls: &[String],
+    dest: &Path,
+    checksum: Option<&str>,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<String> {
+    let mut errors = Vec::new();
+    for (i, url) in urls.iter().enumerate() {
+        let is_last = i + 1 == urls.len();
+        let result = match download_with_retry(client, config, url, dest, &mut progress).await {
+            Ok(sha256) => {
+                let verified = match checksum {
+                    // A SHA512 rereads the whole file, so keep it off the runtime
+                    Some(expected) => {
+                        let (path, got, expected) = (dest.to_path_buf(), sha256.clone(), expected.to_string());
+                        tokio::task::spawn_blocking(move || verify_checksum(&path, &got, &expected))
+                            .await
+                            .map_err(|e| anyhow!("hash task failed: {e}"))
+                            .and_then(|r| r)
+                    }
+                    None => Ok(()),
+                };
+                match verified {
+                    Err(e) => {
+                        // Don't leave a bad file around to be picked up next time
+                        let _ = fs::remove_file(dest);
+                        Err(e.context(format!("{} failed verification and was deleted", dest.display())))
+                    }
+                    Ok(()) => Ok(sha256),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(sha256) => return Ok(sha256),
+            Err(e) if !is_last => {
+                warn!("download from {url} failed, trying the next source");
+                // A partial file from one server can't be resumed from another
+                let _ = fs::remove_file(part_path(dest));
+                errors.push(format!("{url}: {e:#}"));
+            }
+            // A bad checksum is reported as such; anything else is a failed download
+            Err(e) if errors.is_empty() => {
+                if e.downcast_ref::<ElmError>().is_some() {
+                    return Err(e);
+                }
+                return Err(ElmError::Download { urls: urls.to_vec(), reason: format!("{e:#}") }.into());
+            }
+            Err(e) => {
+                errors.push(format!("{url}: {e:#}"));
+                let reason = format!("all {} download sources failed:\n- {}", urls.len(), errors.join("\n- "));
+                return Err(ElmError::Download { urls: urls.to_vec(), reason }.into());
+            }
+        }
+    }
+    Err(anyhow!("no download URL given for {}", dest.display()))
+}
+
+/// SHA256 of an existing file, as lowercase hex
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut f = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    hash_reader(&mut f, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Compare a computed digest against the expected one (case-insensitive)
+pub fn verify_sha256(path: &Path, got_hex: &str, expected_hex: &str) -> Result<()> {
+    if got_hex.to_lowercase() != expected_hex.to_lowercase() {
+        return Err(ElmError::ChecksumMismatch {
+            path: path.to_path_buf(),
+            algorithm: "sha256",
+            expected: expected_hex.to_string(),
+            got: got_hex.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// SHA512 of an existing file, as lowercase hex
+pub fn sha512_file(path: &Path) -> Result<String> {
+    let mut f = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut hasher = Sha512::new();
+    hash_reader(&mut f, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Verify `path` against an expected SHA256 or SHA512 (picked by digest length).
+/// `sha256_hex` is the already computed SHA256, so only SHA512 rehashes the file.
+pub fn verify_checksum(path: &Path, sha256_hex: &str, expected_hex: &str) -> Result<()> {
+    if expected_hex.len() != 128 {
+        return verify_sha256(path, sha256_hex, expected_hex);
+    }
+    let got = sha512_file(path)?;
+    if got != expected_hex.to_lowercase() {
+        return Err(ElmError::ChecksumMismatch {
+            path: path.to_path_buf(),
+            algorithm: "sha512",
+            expected: expected_hex.to_string(),
+            got,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn hash_reader(r: &mut impl Read, hasher: &mut impl Digest) -> Result<()> {
+    let mut buf = [0u8; 1024 * 128];
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 { break; }
+        hasher.update(&buf[..n]);
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Installer framework a Windows setup executable was built with, which
+/// decides the flags that make it run without a GUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallerKind {
+    /// Squirrel.Windows, used by the current EVE launcher (`eve-online-*Setup.exe`)
+    Squirrel,
+    /// Nullsoft Scriptable Install System
+    Nsis,
+    InnoSetup,
+    Unknown,
+}
+
+/// Byte markers each framework embeds in its setup executable, checked in this order
+const MARKERS: &[(InstallerKind, &[u8])] = &[
+    (InstallerKind::Squirrel, b"Squirrel"),
+    (InstallerKind::Nsis, b"Nullsoft"),
+    (InstallerKind::InnoSetup, b"Inno Setup"),
+];
+
+impl InstallerKind {
+    /// Arguments for an unattended install; `None` if we don't know how
+    pub fn silent_args(self) -> Option<&'static [&'static str]> {
+        match self {
+            InstallerKind::Squirrel => Some(&["--silent"]),
+            InstallerKind::Nsis => Some(&["/S"]),
+            InstallerKind::InnoSetup => Some(&["/VERYSILENT", "/SUPPRESSMSGBOXES", "/NORESTART"]),
+            InstallerKind::Unknown => None,
+        }
+    }
+}
+
+impl fmt::Display for InstallerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InstallerKind::Squirrel => "Squirrel",
+            InstallerKind::Nsis => "NSIS",
+            InstallerKind::InnoSetup => "Inno Setup",
+            InstallerKind::Unknown => "unknown",
+        })
+    }
+}
+
+/// Identify the installer framework by scanning the executable for its markers
+pub fn detect(installer: &Path) -> Result<InstallerKind> {
+    let mut file = File::open(installer).with_context(|| format!("open {}", installer.display()))?;
+    let overlap = MARKERS.iter().map(|(_, m)| m.len()).max().unwrap_or(0);
+    let mut found = [false; MARKERS.len()];
+
+    // Scan in chunks, carrying the tail over so markers split across reads still match
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut carried = 0;
+    loop {
+        let n = file.read(&mut buf[carried..])?;
+        if n == 0 {
+            break;
+        }
+        let window = &buf[..carried + n];
+        for (i, (_, marker)) in MARKERS.iter().enumerate() {
+            found[i] |= window.windows(marker.len()).any(|w| w == *marker);
+        }
+        carried = overlap.min(window.len());
+        let tail_start = window.len() - carried;
+        buf.copy_within(tail_start..tail_start + carried, 0);
+    }
+
+    Ok(MARKERS
+        .iter()
+        .zip(found)
+        .find(|(_, hit)| *hit)
+        .map(|((kind, _), _)| *kind)
+        .unwrap_or(InstallerKind::Unknown))
+}
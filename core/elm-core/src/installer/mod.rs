@@ -1,37 +1,92 @@
 use anyhow::{anyhow, Context, Result};
-use reqwest::blocking::Client;
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Duration;
 use tokio::process::Command;
+use tracing::{debug, info};
 
-use crate::config::models::ManifestV1;
+use crate::config::models::{InstallerSource, ManifestV1};
+use crate::engine::runner;
+use crate::http;
+use crate::system::disk;
 
-/// Download a file from URL to destination
-fn download_file(url: &str, dest: &Path) -> Result<()> {
-    println!("Downloading: {}", url);
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(300))
-        .build()?;
+pub mod kind;
 
-    let mut resp = client.get(url).send().with_context(|| format!("GET {url}"))?;
-    resp.error_for_status_ref()?;
+/// How long an installer may run before it's assumed hung and killed
+pub const DEFAULT_INSTALLER_TIMEOUT: Duration = Duration::from_secs(30 * 60);
 
-    let mut out = File::create(dest).with_context(|| format!("create {}", dest.display()))?;
-    let mut buf = [0u8; 1024 * 64];
-    let mut total = 0u64;
+/// How to run an installer
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// Run without the installer GUI; fails if the installer type isn't recognised
+    pub silent: bool,
+    /// Kill the installer if it hasn't finished after this long
+    pub timeout: Duration,
+}
 
-    loop {
-        let n = resp.read(&mut buf)?;
-        if n == 0 { break; }
-        out.write_all(&buf[..n])?;
-        total += n as u64;
-        if total % (1024 * 1024) == 0 {
-            println!("  Downloaded {} MB", total / (1024 * 1024));
-        }
+impl Default for InstallOptions {
+    fn default() -> Self {
+        InstallOptions { silent: false, timeout: DEFAULT_INSTALLER_TIMEOUT }
     }
-    println!("  Complete: {} bytes", total);
-    Ok(())
+}
+
+/// Run an installer under Proton until it exits, it outlives `timeout`, or
+/// the user presses Ctrl-C. On timeout or Ctrl-C the installer and the
+/// prefix's wineserver are killed and an error explains the partial install.
+async fn run_under_proton(
+    mut cmd: Command,
+    prefix_dir: &Path,
+    proton_root: &Path,
+    timeout: Duration,
+) -> Result<ExitStatus> {
+    debug!("installer command: {:?}", cmd.as_std());
+    let mut child = cmd.kill_on_drop(true).spawn().context("starting installer")?;
+
+    let reason = tokio::select! {
+        status = child.wait() => return status.context("waiting for installer"),
+        _ = tokio::time::sleep(timeout) => format!("timed out after {} min", timeout.as_secs().div_ceil(60)),
+        _ = tokio::signal::ctrl_c() => "cancelled".to_string(),
+    };
+
+    child.kill().await.ok();
+    // Proton's python wrapper doesn't take the Windows processes down with it
+    let _ = crate::prefix::shutdown(prefix_dir, proton_root).await;
+    Err(anyhow!(
+        "installer {reason} and was stopped; {} may hold a partial install (re-run with `elm run --reinstall`, or delete the prefix to start over)",
+        prefix_dir.display()
+    ))
+}
+
+/// Download a file to destination from the first of `urls` that works (and
+/// matches `sha256`, if given), resuming a previous partial download.
+/// Returns the SHA256 of the downloaded file.
+async fn download_file(urls: &[String], dest: &Path, sha256: Option<&str>) -> Result<String> {
+    info!("downloading {}", urls.first().map(String::as_str).unwrap_or_default());
+    let config = http::HttpConfig::from_env();
+    let client = http::client(&config)?;
+
+    let mut last_mb = 0;
+    let mut total = 0u64;
+    let sha256 = http::download_from_mirrors(&client, &config, urls, dest, sha256, |done, _| {
+        total = done;
+        if done / (1024 * 1024) > last_mb {
+            last_mb = done / (1024 * 1024);
+            debug!("downloaded {} MB", last_mb);
+        }
+    })
+    .await?;
+    info!("download complete: {} bytes", total);
+    Ok(sha256)
+}
+
+/// The file name an installer downloaded from `url` is cached under
+fn installer_filename(url: &str) -> &str {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|u| u.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("eve-online-setup.exe")
 }
 
 /// Install game from manifest into prefix
@@ -42,23 +97,22 @@ pub async fn install_from_manifest(
     downloads_dir: &Path,
 ) -> Result<PathBuf> {
     fs::create_dir_all(downloads_dir)?;
+    disk::ensure_space(prefix_dir, disk::EVE_INSTALL_SPACE, "install EVE")?;
 
     // Download the installer
-    let installer_filename = manifest.installer.source.url
-        .split('/')
-        .last()
-        .unwrap_or("installer.exe");
-    let installer_path = downloads_dir.join(installer_filename);
+    let source = &manifest.installer.source;
+    let installer_path = downloads_dir.join(installer_filename(&source.url));
 
     if !installer_path.exists() {
-        download_file(&manifest.installer.source.url, &installer_path)?;
+        download_file(&source.candidates(), &installer_path, source.sha256.as_deref()).await?;
     } else {
-        println!("Using cached installer: {}", installer_path.display());
+        info!("using cached installer: {}", installer_path.display());
+        if let Some(expected_sha) = &source.sha256 {
+            http::verify_sha256(&installer_path, &http::sha256_file(&installer_path)?, expected_sha)?;
+        }
     }
-
-    // Verify SHA256 if provided
-    if let Some(expected_sha) = &manifest.installer.source.sha256 {
-        verify_sha256(&installer_path, expected_sha)?;
+    if let Some(expected_sha) = &source.sha256 {
+        info!("sha256 verified: {}", expected_sha);
     }
 
     // Create install directory in prefix
@@ -68,58 +122,20 @@ pub async fn install_from_manifest(
     fs::create_dir_all(&install_dir)?;
 
     // Run the installer with Proton
-    println!("Running installer...");
-    run_installer(&installer_path, prefix_dir, proton_root).await?;
+    info!("running installer {}", installer_path.display());
+    run_installer(&installer_path, prefix_dir, proton_root, DEFAULT_INSTALLER_TIMEOUT).await?;
 
     Ok(install_dir)
 }
 
-fn verify_sha256(path: &Path, expected_hex: &str) -> Result<()> {
-    use sha2::{Digest, Sha256};
-
-    let mut f = File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buf = [0u8; 1024 * 128];
-    loop {
-        let n = f.read(&mut buf)?;
-        if n == 0 { break; }
-        hasher.update(&buf[..n]);
-    }
-    let got = hex::encode(hasher.finalize());
-    if got.to_lowercase() != expected_hex.to_lowercase() {
-        return Err(anyhow!(
-            "sha256 mismatch for {}: expected {}, got {}",
-            path.display(),
-            expected_hex,
-            got
-        ));
-    }
-    println!("SHA256 verified: {}", expected_hex);
-    Ok(())
-}
-
 async fn run_installer(
     installer_exe: &Path,
     prefix_dir: &Path,
     proton_root: &Path,
+    timeout: Duration,
 ) -> Result<()> {
-    let proton = proton_root.join("proton");
-    if !proton.exists() {
-        return Err(anyhow!("proton not found: {}", proton.display()));
-    }
-
-    let home = std::env::var("HOME").unwrap_or_default();
-    let steam_path = format!("{home}/.steam/steam");
-
-    let status = Command::new("python3")
-        .arg(&proton)
-        .env("STEAM_COMPAT_DATA_PATH", prefix_dir)
-        .env("STEAM_COMPAT_CLIENT_INSTALL_PATH", &steam_path)
-        .arg("run")
-        .arg(installer_exe)
-        .status()
-        .await
-        .context("running installer")?;
+    let cmd = runner::command(proton_root, prefix_dir, installer_exe)?;
+    let status = run_under_proton(cmd, prefix_dir, proton_root, timeout).await?;
 
     if !status.success() {
         return Err(anyhow!("installer failed with status: {status}"));
@@ -128,62 +144,134 @@ async fn run_installer(
     Ok(())
 }
 
-/// Run silent/unattended install for EVE launcher specifically
+/// Installer to use when there's no manifest to take `installer.source` from
+pub const EVE_LAUNCHER_URL: &str = "https://launcher.ccpgames.com/eve-online/release/win32/x64/eve-online-1.9.4+Setup.exe";
+
+/// Install the EVE launcher into `prefix_dir`, returning the launcher's path
+///
+/// `source` is the manifest's `installer.source`; without one the bundled
+/// [`EVE_LAUNCHER_URL`] is used and nothing is verified. See [`InstallOptions`]
+/// for unattended installs and the timeout.
 pub async fn install_eve_launcher(
     prefix_dir: &Path,
     proton_root: &Path,
     downloads_dir: &Path,
+    source: Option<&InstallerSource>,
+    options: &InstallOptions,
 ) -> Result<PathBuf> {
-    const EVE_LAUNCHER_URL: &str = "https://launcher.ccpgames.com/eve-online/release/win32/x64/eve-online-1.9.4+Setup.exe";
+    let urls = source.map(InstallerSource::candidates).unwrap_or_else(|| vec![EVE_LAUNCHER_URL.to_string()]);
+    let expected_sha = source.and_then(|s| s.sha256.as_deref());
 
     fs::create_dir_all(downloads_dir)?;
+    disk::ensure_space(prefix_dir, disk::EVE_INSTALL_SPACE, "install EVE")?;
 
-    let installer_path = downloads_dir.join("eve-online-1.9.4+Setup.exe");
+    let installer_path = downloads_dir.join(installer_filename(&urls[0]));
 
     if !installer_path.exists() {
-        download_file(EVE_LAUNCHER_URL, &installer_path)?;
+        let sha256 = download_file(&urls, &installer_path, expected_sha)
+            .await
+            .context("could not download the EVE launcher installer; run again to retry")?;
+        debug!("installer sha256: {}", sha256);
     } else {
-        println!("Using cached installer: {}", installer_path.display());
+        info!("using cached installer: {}", installer_path.display());
+        if let Some(expected_sha) = expected_sha {
+            let sha256 = http::sha256_file(&installer_path)?;
+            if let Err(e) = http::verify_sha256(&installer_path, &sha256, expected_sha) {
+                // Don't keep reusing a bad cached copy
+                fs::remove_file(&installer_path).ok();
+                return Err(e.context("installer was deleted; run again to download it fresh"));
+            }
+        }
+    }
+    if let Some(expected_sha) = expected_sha {
+        info!("sha256 verified: {}", expected_sha);
     }
 
     // Create EVE directory structure
     let eve_dir = prefix_dir.join("pfx/drive_c/EVE");
     fs::create_dir_all(&eve_dir)?;
 
-    // Run the installer
-    println!("Running EVE Launcher installer...");
-    println!("Note: Complete the installer GUI when it appears.");
+    let installer_kind = kind::detect(&installer_path)?;
+    info!("installer type: {}", installer_kind);
+    let installer_args: &[&str] = if options.silent {
+        installer_kind.silent_args().ok_or_else(|| {
+            anyhow!(
+                "cannot install silently: {} is not a recognised installer type (run without --silent)",
+                installer_path.display()
+            )
+        })?
+    } else {
+        &[]
+    };
 
-    let proton = proton_root.join("proton");
-    let home = std::env::var("HOME").unwrap_or_default();
-    let steam_path = format!("{home}/.steam/steam");
+    // Run the installer; without `silent` the caller tells the user to click through its GUI
+    if options.silent {
+        info!("running EVE Launcher installer unattended ({})", installer_args.join(" "));
+    } else {
+        info!("running EVE Launcher installer");
+    }
 
-    let status = Command::new("python3")
-        .arg(&proton)
-        .env("STEAM_COMPAT_DATA_PATH", prefix_dir)
-        .env("STEAM_COMPAT_CLIENT_INSTALL_PATH", &steam_path)
-        .arg("run")
-        .arg(&installer_path)
-        .status()
-        .await
-        .context("running EVE installer")?;
+    let mut cmd = runner::command(proton_root, prefix_dir, &installer_path)?;
+    cmd.args(installer_args);
+    let status = run_under_proton(cmd, prefix_dir, proton_root, options.timeout).await?;
 
     if !status.success() {
         return Err(anyhow!("EVE installer failed with status: {status}"));
     }
 
-    // Check common install locations
-    let launcher_path = prefix_dir.join("pfx/drive_c/EVE/Launcher/evelauncher.exe");
-    let alt_path = prefix_dir.join("pfx/drive_c/Program Files/CCP/EVE/Launcher/evelauncher.exe");
+    let launcher_path = find_eve_launcher(prefix_dir).ok_or_else(|| {
+        anyhow!(
+            "EVE installer finished but no launcher was found under {} (checked {})",
+            prefix_dir.join("pfx/drive_c").display(),
+            LAUNCHER_ROOTS.join(", ")
+        )
+    })?;
+    debug!("EVE Launcher installed at {}", launcher_path.display());
+    Ok(launcher_path)
+}
 
-    if launcher_path.exists() {
-        println!("EVE Launcher installed at: {}", launcher_path.display());
-        Ok(launcher_path)
-    } else if alt_path.exists() {
-        println!("EVE Launcher installed at: {}", alt_path.display());
-        Ok(alt_path)
-    } else {
-        println!("Warning: Could not verify launcher location. Check prefix manually.");
-        Ok(eve_dir)
+/// Where the EVE launcher installs itself, relative to `drive_c`, most likely first
+const LAUNCHER_ROOTS: &[&str] = &[
+    "users/steamuser/AppData/Local/eve-online",
+    "CCP",
+    "EVE",
+    "Program Files",
+    "Program Files (x86)",
+];
+
+/// Launcher executables, lowercase
+const LAUNCHER_EXES: &[&str] = &["eve-online.exe", "evelauncher.exe"];
+
+/// How deep to look below each root; the launcher sits a few levels down at most
+const LAUNCHER_SEARCH_DEPTH: usize = 4;
+
+/// Locate the installed EVE launcher executable in a prefix
+pub fn find_eve_launcher(prefix_dir: &Path) -> Option<PathBuf> {
+    fn search(dir: &Path, depth: usize) -> Option<PathBuf> {
+        let mut subdirs = Vec::new();
+        for entry in fs::read_dir(dir).ok()?.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_file() {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                if LAUNCHER_EXES.contains(&name.as_str()) {
+                    return Some(path);
+                }
+            } else if file_type.is_dir() {
+                subdirs.push(path);
+            }
+        }
+        if depth == 0 {
+            return None;
+        }
+        subdirs.sort();
+        subdirs.iter().find_map(|d| search(d, depth - 1))
     }
+
+    let drive_c = prefix_dir.join("pfx/drive_c");
+    LAUNCHER_ROOTS
+        .iter()
+        .map(|root| drive_c.join(root))
+        .filter(|root| root.is_dir())
+        .find_map(|root| search(&root, LAUNCHER_SEARCH_DEPTH))
 }
@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Icon theme name used when no EVE icon has been installed
+pub const FALLBACK_ICON: &str = "applications-games";
+
+/// `~/.local/share/applications/elm-eve-<profile>.desktop`
+pub fn desktop_file_path(home: &str, profile: &str) -> PathBuf {
+    PathBuf::from(format!("{home}/.local/share/applications/elm-eve-{profile}.desktop"))
+}
+
+/// The EVE icon fetched by install.sh, if present
+pub fn default_icon(home: &str) -> Option<PathBuf> {
+    let icon = PathBuf::from(format!("{home}/.local/share/icons/eve-online.png"));
+    icon.exists().then_some(icon)
+}
+
+/// Whether an EVE client window (titled "EVE" or "EVE - <character>") is
+/// mapped, asked of the X server (Wine's windows live there even under
+/// Wayland). `None` if `xdotool` isn't installed or there is no display.
+pub fn eve_window_shown() -> Option<bool> {
+    let output = std::process::Command::new("xdotool")
+        .args(["search", "--onlyvisible", "--name", "^EVE( - .*)?$"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    match output.status.code() {
+        Some(0) => Some(true),
+        // xdotool exits 1 when nothing matched
+        Some(1) if output.stdout.is_empty() => Some(false),
+        _ => None,
+    }
+}
+
+/// Quote an Exec argument per the Desktop Entry spec when it needs it
+fn exec_arg(arg: &str) -> String {
+    if arg.chars().any(|c| c.is_whitespace() || "\"'\\`$".contains(c)) {
+        let escaped: String = arg
+            .chars()
+            .flat_map(|c| match c {
+                '"' | '`' | '$' | '\\' => vec!['\\', c],
+                _ => vec![c],
+            })
+            .collect();
+        format!("\"{escaped}\"")
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Desktop entry that launches `elm run --profile <profile>`
+pub fn desktop_entry(elm_bin: &Path, profile: &str, icon: Option<&Path>) -> String {
+    let icon = icon
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| FALLBACK_ICON.to_string());
+    let name = if profile == "default" {
+        "EVE Online".to_string()
+    } else {
+        format!("EVE Online ({profile})")
+    };
+    format!(
+        "[Desktop Entry]\n\
+         Name={name}\n\
+         Comment=Launch EVE Online via ELM (profile: {profile})\n\
+         Exec={bin} run --profile {profile_arg}\n\
+         Icon={icon}\n\
+         Terminal=false\n\
+         Type=Application\n\
+         Categories=Game;\n\
+         Keywords=EVE;Online;Space;MMO;CCP;\n\
+         StartupNotify=true\n\
+         StartupWMClass=eve.exe\n",
+        bin = exec_arg(&elm_bin.display().to_string()),
+        profile_arg = exec_arg(profile),
+    )
+}
+
+/// Write the desktop entry, returning its path
+pub fn write_desktop_file(home: &str, elm_bin: &Path, profile: &str, icon: Option<&Path>) -> Result<PathBuf> {
+    let path = desktop_file_path(home, profile);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    fs::write(&path, desktop_entry(elm_bin, profile, icon))
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,4 @@
+pub mod desktop;
+pub mod notify;
+pub mod steam;
+pub mod vdf;
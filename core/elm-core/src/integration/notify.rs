@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::debug;
+use zbus::zvariant::Value;
+
+/// Set to `0`, `off` or `false` to turn desktop notifications off everywhere
+pub const NOTIFY_VAR: &str = "ELM_NOTIFY";
+
+const APP_NAME: &str = "ELM";
+
+/// A desktop notification
+#[derive(Debug, Clone, Default)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    /// Icon theme name, e.g. `dialog-error`
+    pub icon: String,
+    /// Id of an earlier notification to update in place rather than show a new one
+    pub replaces: Option<u32>,
+    /// 0-100, drawn as a progress bar by servers that honour the `value` hint
+    pub progress: Option<u8>,
+    /// Buttons as (key, label) pairs; only the D-Bus backend can show them
+    pub actions: Vec<(String, String)>,
+}
+
+/// Somewhere to send notifications
+pub trait Notifier {
+    /// Show `n`. Returns the notification's id when the backend reports one,
+    /// to pass as `replaces` for an update.
+    fn notify(&self, n: &Notification) -> Result<Option<u32>>;
+
+    /// Show `n` and wait for one of its actions to be clicked. Returns the
+    /// action's key, or `None` once it is dismissed or `timeout` passes.
+    /// Backends without actions show it and return `None` straight away.
+    fn ask(&self, n: &Notification, timeout: Duration) -> Result<Option<String>> {
+        let _ = timeout;
+        self.notify(n)?;
+        Ok(None)
+    }
+}
+
+/// `org.freedesktop.Notifications` on the session bus
+pub struct DBusNotifier {
+    conn: zbus::blocking::Connection,
+}
+
+impl DBusNotifier {
+    pub fn connect() -> Result<Self> {
+        let conn = zbus::blocking::Connection::session().context("connecting to the session bus")?;
+        Ok(DBusNotifier { conn })
+    }
+}
+
+impl Notifier for DBusNotifier {
+    fn notify(&self, n: &Notification) -> Result<Option<u32>> {
+        let mut hints: HashMap<&str, Value> = HashMap::new();
+        if let Some(progress) = n.progress {
+            hints.insert("value", Value::from(i32::from(progress.min(100))));
+        }
+        let actions: Vec<&str> = n.actions.iter().flat_map(|(key, label)| [key.as_str(), label.as_str()]).collect();
+        let reply = self
+            .conn
+            .call_method(
+                Some("org.freedesktop.Notifications"),
+                "/org/freedesktop/Notifications",
+                Some("org.freedesktop.Notifications"),
+                "Notify",
+                &(APP_NAME, n.replaces.unwrap_or(0), n.icon.as_str(), n.summary.as_str(), n.body.as_str(), actions, hints, -1i32),
+            )
+            .context("sending notification over D-Bus")?;
+        let id: u32 = reply.body().deserialize().context("reading notification id")?;
+        Ok(Some(id))
+    }
+
+    fn ask(&self, n: &Notification, timeout: Duration) -> Result<Option<String>> {
+        // Subscribe before sending, so a quick click isn't missed
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.Notifications")?
+            .build();
+        let signals = zbus::blocking::MessageIterator::for_match_rule(rule, &self.conn, None)
+            .context("subscribing to notification signals")?;
+        let Some(id) = self.notify(n)? else { return Ok(None) };
+
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for msg in signals.flatten() {
+                let header = msg.header();
+                let answer = match header.member().map(|m| m.as_str()) {
+                    Some("ActionInvoked") => match msg.body().deserialize::<(u32, String)>() {
+                        Ok((signal_id, key)) if signal_id == id => Some(key),
+                        _ => continue,
+                    },
+                    Some("NotificationClosed") => match msg.body().deserialize::<(u32, u32)>() {
+                        Ok((signal_id, _)) if signal_id == id => None,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                let _ = tx.send(answer);
+                return;
+            }
+        });
+
+        match rx.recv_timeout(timeout) {
+            Ok(answer) => Ok(answer),
+            Err(_) => {
+                debug!("no answer to notification {id} within {}s", timeout.as_secs());
+                let _ = self.conn.call_method(
+                    Some("org.freedesktop.Notifications"),
+                    "/org/freedesktop/Notifications",
+                    Some("org.freedesktop.Notifications"),
+                    "CloseNotification",
+                    &(id,),
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// The `notify-send` command, for systems without a reachable session bus.
+/// It can't update a notification, so every call shows a new one.
+pub struct NotifySend;
+
+impl Notifier for NotifySend {
+    fn notify(&self, n: &Notification) -> Result<Option<u32>> {
+        let mut cmd = Command::new("notify-send");
+        cmd.arg(format!("--app-name={APP_NAME}")).arg(format!("--icon={}", n.icon));
+        if let Some(progress) = n.progress {
+            cmd.arg(format!("--hint=int:value:{}", progress.min(100)));
+        }
+        cmd.arg(&n.summary).arg(&n.body).stdout(Stdio::null()).stderr(Stdio::null());
+        cmd.spawn().context("running notify-send (is libnotify installed?)")?;
+        Ok(None)
+    }
+}
+
+/// Drops everything; used when notifications are turned off
+pub struct Disabled;
+
+impl Notifier for Disabled {
+    fn notify(&self, _: &Notification) -> Result<Option<u32>> {
+        Ok(None)
+    }
+}
+
+/// The best available backend: D-Bus, else `notify-send`, or [`Disabled`]
+/// when [`NOTIFY_VAR`] turns notifications off
+pub fn notifier() -> Box<dyn Notifier> {
+    let off = std::env::var(NOTIFY_VAR).is_ok_and(|v| matches!(v.to_ascii_lowercase().as_str(), "0" | "off" | "false"));
+    if off {
+        return Box::new(Disabled);
+    }
+    match DBusNotifier::connect() {
+        Ok(dbus) => Box::new(dbus),
+        Err(e) => {
+            debug!("D-Bus notifications unavailable ({e:#}); using notify-send");
+            Box::new(NotifySend)
+        }
+    }
+}
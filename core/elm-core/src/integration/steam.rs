@@ -0,0 +1,180 @@
+use super::vdf::{self, VdfValue};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A non-Steam game entry for `shortcuts.vdf`
+pub struct Shortcut {
+    pub app_name: String,
+    pub exe: PathBuf,
+    pub launch_options: String,
+    pub icon: Option<PathBuf>,
+}
+
+impl Shortcut {
+    /// Steam quotes the exe and start dir; the appid is derived from the quoted exe + name
+    fn quoted_exe(&self) -> String {
+        format!("\"{}\"", self.exe.display())
+    }
+
+    fn start_dir(&self) -> String {
+        let dir = self.exe.parent().unwrap_or(Path::new("/"));
+        format!("\"{}\"", dir.display())
+    }
+
+    /// Same derivation Steam uses for non-Steam games, so artwork and
+    /// controller configs attach to the right entry
+    pub fn app_id(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(self.quoted_exe().as_bytes());
+        hasher.update(self.app_name.as_bytes());
+        hasher.finalize() | 0x8000_0000
+    }
+
+    fn to_vdf(&self) -> VdfValue {
+        let s = |v: &str| VdfValue::String(v.to_string());
+        let icon = self
+            .icon
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        VdfValue::Map(vec![
+            ("appid".into(), VdfValue::Int32(self.app_id() as i32)),
+            ("AppName".into(), s(&self.app_name)),
+            ("Exe".into(), s(&self.quoted_exe())),
+            ("StartDir".into(), s(&self.start_dir())),
+            ("icon".into(), s(&icon)),
+            ("ShortcutPath".into(), s("")),
+            ("LaunchOptions".into(), s(&self.launch_options)),
+            ("IsHidden".into(), VdfValue::Int32(0)),
+            ("AllowDesktopConfig".into(), VdfValue::Int32(1)),
+            ("AllowOverlay".into(), VdfValue::Int32(1)),
+            ("OpenVR".into(), VdfValue::Int32(0)),
+            ("Devkit".into(), VdfValue::Int32(0)),
+            ("DevkitGameID".into(), s("")),
+            ("DevkitOverrideAppID".into(), VdfValue::Int32(0)),
+            ("LastPlayTime".into(), VdfValue::Int32(0)),
+            ("FlatpakAppID".into(), s("")),
+            ("tags".into(), VdfValue::Map(Vec::new())),
+        ])
+    }
+}
+
+/// Overrides where ELM looks for Steam
+pub const STEAM_PATH_VAR: &str = "ELM_STEAM_PATH";
+
+/// Where Steam installs itself, relative to $HOME, most likely first: the
+/// usual symlink, the native install, and the Flatpak's data dir
+const STEAM_ROOTS: &[&str] = &[
+    ".steam/steam",
+    ".local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/data/Steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+];
+
+/// The Steam install Proton needs as `STEAM_COMPAT_CLIENT_INSTALL_PATH`:
+/// `ELM_STEAM_PATH` if set, otherwise the first of the usual locations that exists.
+/// Fails with the places it looked, since Proton's own error is cryptic.
+pub fn steam_root() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os(STEAM_PATH_VAR).filter(|p| !p.is_empty()) {
+        let path = PathBuf::from(path);
+        if !path.is_dir() {
+            return Err(anyhow!("{STEAM_PATH_VAR} is set to {}, which is not a directory", path.display()));
+        }
+        return Ok(path);
+    }
+
+    let home = PathBuf::from(std::env::var("HOME").unwrap_or_default());
+    let candidates: Vec<PathBuf> = STEAM_ROOTS.iter().map(|rel| home.join(rel)).collect();
+    candidates.iter().find(|p| p.is_dir()).cloned().ok_or_else(|| {
+        let looked: Vec<String> = candidates.iter().map(|p| p.display().to_string()).collect();
+        anyhow!(
+            "Steam not found (looked in {}); install Steam and start it once, or set {STEAM_PATH_VAR} to your Steam directory",
+            looked.join(", ")
+        )
+    })
+}
+
+/// Steam account directories under `<steam_root>/userdata`, skipping the anonymous `0`
+pub fn user_dirs(steam_root: &Path) -> Result<Vec<PathBuf>> {
+    let userdata = steam_root.join("userdata");
+    if !userdata.exists() {
+        return Ok(Vec::new());
+    }
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&userdata)
+        .with_context(|| format!("reading {}", userdata.display()))?
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name != "0" && name.chars().all(|c| c.is_ascii_digit()) && e.path().is_dir()
+        })
+        .map(|e| e.path())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+pub fn shortcuts_path(user_dir: &Path) -> PathBuf {
+    user_dir.join("config/shortcuts.vdf")
+}
+
+/// True if a Steam client process is running (it rewrites shortcuts.vdf on exit)
+pub fn steam_running() -> bool {
+    std::process::Command::new("pgrep")
+        .args(["-x", "steam"])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Add `shortcut` to `shortcuts_vdf`, replacing an existing entry with the same
+/// AppName. Backs up the original file first. Returns true if an entry was replaced.
+pub fn upsert_shortcut(shortcuts_vdf: &Path, shortcut: &Shortcut) -> Result<bool> {
+    let mut root = if shortcuts_vdf.exists() {
+        let data = fs::read(shortcuts_vdf).with_context(|| format!("reading {}", shortcuts_vdf.display()))?;
+        vdf::parse(&data).with_context(|| format!("parsing {}", shortcuts_vdf.display()))?
+    } else {
+        VdfValue::Map(vec![("shortcuts".into(), VdfValue::Map(Vec::new()))])
+    };
+
+    let VdfValue::Map(top) = &mut root else {
+        unreachable!("vdf::parse returns a map");
+    };
+    if !top.iter().any(|(k, _)| k.eq_ignore_ascii_case("shortcuts")) {
+        top.push(("shortcuts".into(), VdfValue::Map(Vec::new())));
+    }
+    let Some((_, VdfValue::Map(entries))) = top.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case("shortcuts")) else {
+        return Err(anyhow!("{}: 'shortcuts' is not a map", shortcuts_vdf.display()));
+    };
+
+    let existing = entries.iter().position(|(_, v)| {
+        v.get("AppName").or_else(|| v.get("appname")).and_then(VdfValue::as_str) == Some(shortcut.app_name.as_str())
+    });
+    let replaced = match existing {
+        Some(i) => {
+            entries[i].1 = shortcut.to_vdf();
+            true
+        }
+        None => {
+            // Entries are keyed "0", "1", ...; take the next free index
+            let next = entries
+                .iter()
+                .filter_map(|(k, _)| k.parse::<usize>().ok())
+                .max()
+                .map(|n| n + 1)
+                .unwrap_or(0);
+            entries.push((next.to_string(), shortcut.to_vdf()));
+            false
+        }
+    };
+
+    let data = vdf::write(&root)?;
+    if let Some(parent) = shortcuts_vdf.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    crate::config::backup::backup_file(shortcuts_vdf)?;
+    let tmp = shortcuts_vdf.with_extension("vdf.tmp");
+    fs::write(&tmp, data).with_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, shortcuts_vdf).with_context(|| format!("replacing {}", shortcuts_vdf.display()))?;
+    Ok(replaced)
+}
@@ -0,0 +1,153 @@
+//! Binary VDF, the KeyValues format Steam uses for `shortcuts.vdf`.
+//!
+//! Each node is a type byte, a NUL-terminated key, then the value; a map ends
+//! with `0x08`. Unknown types are rejected rather than guessed so a file we
+//! can't fully understand is never rewritten.
+
+use anyhow::{anyhow, Result};
+
+const TYPE_MAP: u8 = 0x00;
+const TYPE_STRING: u8 = 0x01;
+const TYPE_INT32: u8 = 0x02;
+const TYPE_FLOAT32: u8 = 0x03;
+const TYPE_UINT64: u8 = 0x07;
+const MAP_END: u8 = 0x08;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VdfValue {
+    Map(Vec<(String, VdfValue)>),
+    String(String),
+    Int32(i32),
+    Float32(f32),
+    UInt64(u64),
+}
+
+impl VdfValue {
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a whole binary VDF document into its top-level map
+pub fn parse(data: &[u8]) -> Result<VdfValue> {
+    let mut reader = Reader { data, pos: 0 };
+    let entries = reader.read_map()?;
+    if reader.pos != data.len() {
+        return Err(anyhow!("trailing data at offset {}", reader.pos));
+    }
+    Ok(VdfValue::Map(entries))
+}
+
+/// Serialize a top-level map back to binary VDF
+pub fn write(root: &VdfValue) -> Result<Vec<u8>> {
+    let VdfValue::Map(entries) = root else {
+        return Err(anyhow!("VDF root must be a map"));
+    };
+    let mut out = Vec::new();
+    write_map(&mut out, entries);
+    Ok(out)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| anyhow!("unexpected end of VDF data"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let slice = self
+            .data
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| anyhow!("unexpected end of VDF data"))?;
+        self.pos += N;
+        Ok(slice.try_into().expect("slice has length N"))
+    }
+
+    fn cstring(&mut self) -> Result<String> {
+        let rest = &self.data[self.pos..];
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| anyhow!("unterminated string at offset {}", self.pos))?;
+        let s = std::str::from_utf8(&rest[..len])
+            .map_err(|_| anyhow!("non-UTF-8 string at offset {}", self.pos))?
+            .to_string();
+        self.pos += len + 1;
+        Ok(s)
+    }
+
+    fn read_map(&mut self) -> Result<Vec<(String, VdfValue)>> {
+        let mut entries = Vec::new();
+        loop {
+            let ty = self.byte()?;
+            if ty == MAP_END {
+                return Ok(entries);
+            }
+            let key = self.cstring()?;
+            let value = match ty {
+                TYPE_MAP => VdfValue::Map(self.read_map()?),
+                TYPE_STRING => VdfValue::String(self.cstring()?),
+                TYPE_INT32 => VdfValue::Int32(i32::from_le_bytes(self.bytes()?)),
+                TYPE_FLOAT32 => VdfValue::Float32(f32::from_le_bytes(self.bytes()?)),
+                TYPE_UINT64 => VdfValue::UInt64(u64::from_le_bytes(self.bytes()?)),
+                other => {
+                    return Err(anyhow!(
+                        "unsupported VDF type 0x{:02x} at offset {}",
+                        other,
+                        self.pos
+                    ))
+                }
+            };
+            entries.push((key, value));
+        }
+    }
+}
+
+fn write_cstring(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    out.push(0);
+}
+
+fn write_map(out: &mut Vec<u8>, entries: &[(String, VdfValue)]) {
+    for (key, value) in entries {
+        let ty = match value {
+            VdfValue::Map(_) => TYPE_MAP,
+            VdfValue::String(_) => TYPE_STRING,
+            VdfValue::Int32(_) => TYPE_INT32,
+            VdfValue::Float32(_) => TYPE_FLOAT32,
+            VdfValue::UInt64(_) => TYPE_UINT64,
+        };
+        out.push(ty);
+        write_cstring(out, key);
+        match value {
+            VdfValue::Map(children) => write_map(out, children),
+            VdfValue::String(s) => write_cstring(out, s),
+            VdfValue::Int32(n) => out.extend_from_slice(&n.to_le_bytes()),
+            VdfValue::Float32(n) => out.extend_from_slice(&n.to_le_bytes()),
+            VdfValue::UInt64(n) => out.extend_from_slice(&n.to_le_bytes()),
+        }
+    }
+    out.push(MAP_END);
+}
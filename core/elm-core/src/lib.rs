@@ -1,6 +1,14 @@
 pub mod config;
+pub mod context;
 pub mod engine;
+pub mod error;
+pub mod http;
 pub mod installer;
+pub mod integration;
 pub mod prefix;
 pub mod runtime;
+pub mod system;
 pub mod rollback;
+
+pub use context::ElmContext;
+pub use error::ElmError;
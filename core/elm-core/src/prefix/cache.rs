@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::rollback::snapshot::glob_match;
+use crate::system::disk;
+
+/// Data in a prefix that EVE or the engine recreate on their own, so clearing
+/// it only costs a re-download or a rebuild
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    /// The shared `ResFiles` cache; the launcher downloads whatever is missing
+    GameCache,
+    /// Game logs and crash reports
+    Logs,
+    /// DXVK and VKD3D-Proton shader caches, rebuilt as the game runs
+    ShaderCache,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 3] = [CacheKind::GameCache, CacheKind::Logs, CacheKind::ShaderCache];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CacheKind::GameCache => "Game cache (ResFiles)",
+            CacheKind::Logs => "Logs and crash reports",
+            CacheKind::ShaderCache => "Shader cache",
+        }
+    }
+}
+
+/// Where each kind of cache lives, relative to `drive_c`. `*` matches within
+/// one path component. EVE's chat and game logs (`Documents/EVE/logs`) are
+/// the player's own records and deliberately not listed.
+pub const CACHE_PATHS: &[(CacheKind, &str)] = &[
+    (CacheKind::GameCache, "CCP/EVE/ResFiles"),
+    (CacheKind::GameCache, "users/*/AppData/Local/CCP/EVE/SharedCache/ResFiles"),
+    (CacheKind::Logs, "users/*/AppData/Roaming/EVE Online/logs"),
+    (CacheKind::Logs, "users/*/AppData/Local/CCP/EVE/*/logs"),
+    (CacheKind::Logs, "users/*/AppData/Local/CrashDumps"),
+    (CacheKind::ShaderCache, "CCP/EVE/*/bin64/*.dxvk-cache"),
+    (CacheKind::ShaderCache, "CCP/EVE/*/bin64/*.vkd3d-proton.cache"),
+    (CacheKind::ShaderCache, "users/*/AppData/Local/CCP/EVE/*/shadercache"),
+];
+
+/// A cache directory or file found in a prefix
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub kind: CacheKind,
+    pub path: PathBuf,
+    /// Space on disk (see [`disk::usage`]), i.e. what removing it frees
+    pub size: u64,
+}
+
+/// Every cache under the prefix at `prefix_dir` (see [`CACHE_PATHS`]). ELM's
+/// own launch logs in `<prefix>/logs` aren't included.
+pub fn find_caches(prefix_dir: &Path) -> Vec<CacheEntry> {
+    fn walk(drive_c: &Path, dir: &Path, found: &mut Vec<CacheEntry>) {
+        let Ok(entries) = fs::read_dir(dir) else { return };
+        for entry in entries.flatten() {
+            // Doesn't follow symlinks, so dosdevices and the like are never entered
+            let Ok(file_type) = entry.file_type() else { continue };
+            let path = entry.path();
+            let Ok(rel) = path.strip_prefix(drive_c) else { continue };
+            let rel = rel.to_string_lossy();
+            if let Some((kind, _)) = CACHE_PATHS.iter().find(|(_, pattern)| glob_match(pattern, &rel, false)) {
+                if file_type.is_dir() || file_type.is_file() {
+                    found.push(CacheEntry { kind: *kind, size: disk::usage(&path), path });
+                }
+            } else if file_type.is_dir() && CACHE_PATHS.iter().any(|(_, pattern)| glob_match(pattern, &rel, true)) {
+                walk(drive_c, &path, found);
+            }
+        }
+    }
+    let drive_c = prefix_dir.join("pfx/drive_c");
+    let mut found = Vec::new();
+    walk(&drive_c, &drive_c, &mut found);
+    found.sort_by_key(|c| std::cmp::Reverse(c.size));
+    found
+}
+
+/// Total size of `entries` of each kind, in [`CacheKind::ALL`] order
+pub fn totals(entries: &[CacheEntry]) -> [(CacheKind, u64); 3] {
+    CacheKind::ALL.map(|kind| (kind, entries.iter().filter(|e| e.kind == kind).map(|e| e.size).sum()))
+}
+
+/// Delete `entries`; returns the bytes freed. Stops at the first failure.
+pub fn clear(entries: &[CacheEntry]) -> Result<u64> {
+    let mut freed = 0;
+    for entry in entries {
+        let removed = if entry.path.is_dir() { fs::remove_dir_all(&entry.path) } else { fs::remove_file(&entry.path) };
+        match removed {
+            Ok(()) => freed += entry.size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("remove {}", entry.path.display())),
+        }
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(root: &Path, rel: &str, len: usize) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; len]).unwrap();
+    }
+
+    #[test]
+    fn finds_eve_caches_and_leaves_look_alikes() {
+        let prefix = tempfile::tempdir().unwrap();
+        let drive_c = prefix.path().join("pfx/drive_c");
+        touch(&drive_c, "CCP/EVE/ResFiles/ab/cdef_1234", 4096);
+        touch(&drive_c, "users/steamuser/AppData/Roaming/EVE Online/logs/launcher.log", 100);
+        touch(&drive_c, "CCP/EVE/tq/bin64/exefile.dxvk-cache", 100);
+        // Same names, other places: another program's logs, the player's chat logs, mod files
+        touch(&drive_c, "Program Files/Other/logs/app.log", 100);
+        touch(&drive_c, "users/steamuser/Documents/EVE/logs/Chatlogs/Local.txt", 100);
+        touch(&drive_c, "users/steamuser/Documents/ResFiles/keep.txt", 100);
+        touch(&drive_c, "Program Files/Other/shadercache/blob", 100);
+
+        let caches = find_caches(prefix.path());
+        let mut found: Vec<(CacheKind, String)> = caches
+            .iter()
+            .map(|c| (c.kind, c.path.strip_prefix(&drive_c).unwrap().to_string_lossy().to_string()))
+            .collect();
+        found.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            found,
+            vec![
+                (CacheKind::GameCache, "CCP/EVE/ResFiles".to_string()),
+                (CacheKind::ShaderCache, "CCP/EVE/tq/bin64/exefile.dxvk-cache".to_string()),
+                (CacheKind::Logs, "users/steamuser/AppData/Roaming/EVE Online/logs".to_string()),
+            ]
+        );
+
+        let freed = clear(&caches).unwrap();
+        assert!(freed > 0);
+        assert!(!drive_c.join("CCP/EVE/ResFiles").exists());
+        assert!(drive_c.join("Program Files/Other/logs/app.log").exists());
+        assert!(drive_c.join("users/steamuser/Documents/EVE/logs/Chatlogs/Local.txt").exists());
+        assert!(drive_c.join("users/steamuser/Documents/ResFiles/keep.txt").exists());
+        assert!(drive_c.join("Program Files/Other/shadercache/blob").exists());
+    }
+
+    #[test]
+    fn totals_group_by_kind() {
+        let entry = |kind, size| CacheEntry { kind, path: PathBuf::new(), size };
+        let entries = [entry(CacheKind::Logs, 5), entry(CacheKind::GameCache, 10), entry(CacheKind::Logs, 2)];
+        assert_eq!(
+            totals(&entries),
+            [(CacheKind::GameCache, 10), (CacheKind::Logs, 7), (CacheKind::ShaderCache, 0)]
+        );
+    }
+}
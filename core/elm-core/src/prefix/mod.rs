@@ -1,9 +1,34 @@
 use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::process::Command;
+use tracing::debug;
 
-pub async fn ensure_prefix_initialized(prefix_dir: &Path, proton_root: &Path) -> Result<()> {
+use crate::config::models::ComponentsConfig;
+use crate::engine::runner;
+
+pub mod cache;
+pub mod profile;
+
+/// Proton env that applies the manifest's component toggles:
+/// DXVK off falls back to WineD3D, VKD3D off drops Proton's d3d12 overrides.
+pub fn components_env(components: &ComponentsConfig) -> HashMap<String, String> {
+    let mut env = HashMap::new();
+    if !components.dxvk.enabled {
+        env.insert("PROTON_USE_WINED3D".to_string(), "1".to_string());
+    }
+    if !components.vkd3d.enabled {
+        env.insert("PROTON_NO_D3D12".to_string(), "1".to_string());
+    }
+    env
+}
+
+pub async fn ensure_prefix_initialized(
+    prefix_dir: &Path,
+    proton_root: &Path,
+    components: Option<&ComponentsConfig>,
+) -> Result<()> {
     fs::create_dir_all(prefix_dir).with_context(|| format!("create prefix {}", prefix_dir.display()))?;
 
     // Heuristic: if pfx/drive_c exists, assume initialized (Proton layout)
@@ -11,29 +36,292 @@ pub async fn ensure_prefix_initialized(prefix_dir: &Path, proton_root: &Path) ->
         return Ok(());
     }
 
-    let proton = proton_root.join("proton");
-    if !proton.exists() {
-        return Err(anyhow!("proton runner not found at {}", proton.display()));
+    let status = wineboot_command(prefix_dir, proton_root, components)?
+        .status()
+        .await
+        .context("running wineboot")?;
+
+    if !status.success() {
+        return Err(anyhow!("wineboot failed with status: {status}"));
+    }
+    Ok(())
+}
+
+/// The `wineboot` run that creates a prefix, with the engine's environment
+/// (Proton's or plain Wine's) and the component toggles. Built without
+/// running anything, so it can be inspected or run by the caller.
+pub fn wineboot_command(prefix_dir: &Path, proton_root: &Path, components: Option<&ComponentsConfig>) -> Result<Command> {
+    let mut cmd = runner::command(proton_root, prefix_dir, "wineboot")?;
+    cmd.env("WINEPREFIX", prefix_dir.join("pfx"))
+        .envs(components.map(components_env).unwrap_or_default());
+    Ok(cmd)
+}
+
+/// Run `wineboot -u` in an existing prefix, bringing it up to date with the
+/// engine at `proton_root` (e.g. after restoring a snapshot taken under another one)
+pub async fn update_prefix(prefix_dir: &Path, proton_root: &Path) -> Result<()> {
+    let status = wineboot_command(prefix_dir, proton_root, None)?
+        .arg("-u")
+        .status()
+        .await
+        .context("running wineboot -u")?;
+    if !status.success() {
+        return Err(anyhow!("wineboot -u failed with status: {status}"));
+    }
+    Ok(())
+}
+
+/// Directory holding the engine's `wine`/`wineserver`: Proton's `files/bin`
+/// (`dist/bin` on older Proton), or `bin` in a plain Wine build
+pub fn proton_wine_bin(proton_root: &Path) -> Result<PathBuf> {
+    ["files/bin", "dist/bin", "bin"]
+        .iter()
+        .map(|d| proton_root.join(d))
+        .find(|d| d.join("wine").exists())
+        .ok_or_else(|| anyhow!("no bundled wine found under {}", proton_root.display()))
+}
+
+/// Stop whatever Wine is still running in `prefix_dir`: `wineserver -k`, then
+/// wait for the server to exit. Call before anything that replaces or removes
+/// the prefix, so a lingering wineserver can't write into the new files.
+/// Succeeds quietly when nothing is running.
+pub async fn shutdown(prefix_dir: &Path, proton_root: &Path) -> Result<()> {
+    let pfx = prefix_dir.join("pfx");
+    if !pfx.exists() {
+        return Ok(());
+    }
+    let wineserver = proton_wine_bin(proton_root)?.join("wineserver");
+    debug!("stopping wineserver for {}", pfx.display());
+    // Both exit non-zero when no server is running for the prefix
+    for flag in ["-k", "-w"] {
+        Command::new(&wineserver)
+            .arg(flag)
+            .env("WINEPREFIX", &pfx)
+            .status()
+            .await
+            .with_context(|| format!("running {} {flag}", wineserver.display()))?;
+    }
+    Ok(())
+}
+
+/// Look up an executable on PATH
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).map(|d| d.join(program)).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// Run winetricks verbs (e.g. `vcrun2019`, `corefonts`) against the prefix using Proton's Wine
+pub async fn run_winetricks(prefix_dir: &Path, proton_root: &Path, verbs: &[String]) -> Result<()> {
+    if verbs.is_empty() {
+        return Err(anyhow!("no winetricks verbs given"));
+    }
+    let winetricks = find_in_path("winetricks").ok_or_else(|| {
+        anyhow!("winetricks not found in PATH (install it from your distro, e.g. 'sudo apt install winetricks')")
+    })?;
+    if !prefix_dir.join("pfx/drive_c").exists() {
+        return Err(anyhow!("prefix not initialized: {}", prefix_dir.display()));
     }
 
-    // Find Steam installation path
-    let home = std::env::var("HOME").unwrap_or_default();
-    let steam_path = format!("{home}/.steam/steam");
+    let wine_bin = proton_wine_bin(proton_root)?;
+    let path = match std::env::var_os("PATH") {
+        Some(p) => std::env::join_paths(std::iter::once(wine_bin.clone()).chain(std::env::split_paths(&p)))?,
+        None => wine_bin.clone().into_os_string(),
+    };
 
-    // Initialize prefix with required Proton environment
-    let status = Command::new("python3")
-        .arg(&proton)
-        .env("STEAM_COMPAT_DATA_PATH", prefix_dir)
-        .env("STEAM_COMPAT_CLIENT_INSTALL_PATH", &steam_path)
+    let status = Command::new(&winetricks)
         .env("WINEPREFIX", prefix_dir.join("pfx"))
-        .arg("run")
-        .arg("wineboot")
+        .env("WINE", wine_bin.join("wine"))
+        .env("WINESERVER", wine_bin.join("wineserver"))
+        .env("PATH", path)
+        .arg("-q")
+        .args(verbs)
         .status()
         .await
-        .context("running wineboot")?;
+        .context("running winetricks")?;
 
     if !status.success() {
-        return Err(anyhow!("wineboot failed with status: {status}"));
+        return Err(anyhow!("winetricks failed with status: {status}"));
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct VerifyCheck {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Result of `verify`: one entry per structural check on the prefix
+#[derive(Debug)]
+pub struct VerifyReport {
+    pub checks: Vec<VerifyCheck>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|c| c.ok)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &VerifyCheck> {
+        self.checks.iter().filter(|c| !c.ok)
+    }
+}
+
+/// Check that a Proton prefix looks complete, not just that `pfx/drive_c` exists.
+/// A wineboot that was killed part-way typically leaves registry files or the
+/// Windows tree missing.
+pub fn verify(prefix_dir: &Path) -> VerifyReport {
+    let pfx = prefix_dir.join("pfx");
+    let mut checks = Vec::new();
+
+    for reg in ["system.reg", "user.reg", "userdef.reg"] {
+        let path = pfx.join(reg);
+        let ok = fs::metadata(&path).map(|m| m.is_file() && m.len() > 0).unwrap_or(false);
+        checks.push(VerifyCheck {
+            name: reg,
+            ok,
+            detail: if ok { "present".to_string() } else { format!("missing or empty: {}", path.display()) },
+        });
+    }
+
+    for (name, drive, expected) in [("dosdevices/c:", "c:", "../drive_c"), ("dosdevices/z:", "z:", "/")] {
+        let link = pfx.join("dosdevices").join(drive);
+        let (ok, detail) = match fs::read_link(&link) {
+            Ok(target) if link.exists() => (true, format!("-> {}", target.display())),
+            Ok(target) => (false, format!("dangling link -> {}", target.display())),
+            Err(_) => (false, format!("missing symlink (expected -> {expected})")),
+        };
+        checks.push(VerifyCheck { name, ok, detail });
+    }
+
+    let system32 = pfx.join("drive_c/windows/system32");
+    let populated = fs::read_dir(&system32).map(|d| d.count()).unwrap_or(0);
+    checks.push(VerifyCheck {
+        name: "drive_c/windows",
+        // A fresh prefix has hundreds of DLLs here; a handful means wineboot never finished
+        ok: populated >= 50,
+        detail: format!("{populated} entries in system32"),
+    });
+
+    VerifyReport { checks }
+}
+
+/// Directory Proton logs are redirected to (via PROTON_LOG_DIR) for this prefix
+pub fn proton_logs_dir(prefix_dir: &Path) -> PathBuf {
+    prefix_dir.join("logs")
+}
+
+/// Whether a file name looks like a Proton log (`steam-<appid>.log`)
+pub fn is_proton_log(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy())
+        .map(|n| n.starts_with("steam-") && n.ends_with(".log"))
+        .unwrap_or(false)
+}
+
+/// Proton overwrites `steam-<appid>.log` on every launch; move the previous one aside
+/// with a timestamp and keep only the newest `keep` rotated logs.
+pub fn rotate_proton_logs(prefix_dir: &Path, keep: usize) -> Result<()> {
+    let logs_dir = proton_logs_dir(prefix_dir);
+    fs::create_dir_all(&logs_dir).with_context(|| format!("create {}", logs_dir.display()))?;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut rotated = Vec::new();
+    for entry in fs::read_dir(&logs_dir)?.flatten() {
+        let path = entry.path();
+        if !is_proton_log(&path) {
+            continue;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        // Already rotated: steam-<appid>.<timestamp>.log
+        if stem.contains('.') {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            rotated.push((modified, path));
+            continue;
+        }
+        let target = logs_dir.join(format!("{stem}.{timestamp}.log"));
+        fs::rename(&path, &target).with_context(|| format!("rotate {}", path.display()))?;
+        rotated.push((Some(std::time::SystemTime::now()), target));
+    }
+
+    rotated.sort_by_key(|r| std::cmp::Reverse(r.0));
+    for (_, old) in rotated.into_iter().skip(keep) {
+        fs::remove_file(&old).with_context(|| format!("remove {}", old.display()))?;
     }
     Ok(())
 }
+
+/// Whether a file is captured Proton stdout/stderr (`launch-<timestamp>.log`)
+pub fn is_launch_log(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy())
+        .map(|n| n.starts_with("launch-") && n.ends_with(".log"))
+        .unwrap_or(false)
+}
+
+/// Fresh `logs/launch-<timestamp>.log` path for capturing a launch's output,
+/// pruning all but the newest `keep - 1` earlier captures
+pub fn next_launch_log(prefix_dir: &Path, keep: usize) -> Result<PathBuf> {
+    let logs_dir = proton_logs_dir(prefix_dir);
+    fs::create_dir_all(&logs_dir).with_context(|| format!("create {}", logs_dir.display()))?;
+
+    let mut logs: Vec<PathBuf> = fs::read_dir(&logs_dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| is_launch_log(p))
+        .collect();
+    // Timestamped names sort chronologically
+    logs.sort();
+    logs.reverse();
+    for old in logs.into_iter().skip(keep.saturating_sub(1)) {
+        fs::remove_file(&old).with_context(|| format!("remove {}", old.display()))?;
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    Ok(logs_dir.join(format!("launch-{timestamp}.log")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::models::ToggleConfig;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn wineboot_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let wine = dir.path().join("engine/bin/wine");
+        fs::create_dir_all(wine.parent().unwrap()).unwrap();
+        fs::write(&wine, "#!/bin/sh\n").unwrap();
+        let prefix_dir = dir.path().join("prefix");
+        let components = ComponentsConfig {
+            dxvk: ToggleConfig { enabled: false },
+            vkd3d: ToggleConfig { enabled: true },
+        };
+
+        let cmd = wineboot_command(&prefix_dir, &dir.path().join("engine"), Some(&components)).unwrap();
+        let cmd = cmd.as_std();
+        assert_eq!(cmd.get_program(), wine.as_os_str());
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), [OsStr::new("wineboot")]);
+        let env: HashMap<_, _> = cmd.get_envs().collect();
+        let pfx = prefix_dir.join("pfx");
+        assert_eq!(env.get(OsStr::new("WINEPREFIX")), Some(&Some(pfx.as_os_str())));
+        assert_eq!(env.get(OsStr::new("PROTON_USE_WINED3D")), Some(&Some(OsStr::new("1"))));
+        assert!(!env.contains_key(OsStr::new("PROTON_NO_D3D12")));
+
+        let cmd = wineboot_command(&prefix_dir, &dir.path().join("engine"), None).unwrap();
+        let env: HashMap<_, _> = cmd.as_std().get_envs().collect();
+        assert_eq!(env.len(), 1, "{env:?}");
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Metadata file kept at the root of every profile's prefix directory
+pub const PROFILE_FILE: &str = "profile.json";
+
+/// Directory name prefix for profile prefixes: `prefixes/eve-<name>`
+pub(crate) const DIR_PREFIX: &str = "eve-";
+
+/// Siblings a snapshot restore leaves next to a prefix while it works, e.g. `eve-main.restoring`
+pub(crate) const STAGING_SUFFIXES: &[&str] = &["restoring", "unpack", "old"];
+
+/// What ELM knows about a profile, stored in `prefixes/eve-<name>/profile.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProfileMeta {
+    pub name: String,
+    pub display_name: String,
+    /// Unix seconds
+    pub created: u64,
+    /// Engine the profile last ran (or was created) with
+    pub engine_ref: Option<String>,
+    /// Unix seconds; `None` until the first `elm run`
+    pub last_launched: Option<u64>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl ProfileMeta {
+    pub fn new(name: &str, engine_ref: Option<String>) -> Self {
+        ProfileMeta {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            created: now(),
+            engine_ref,
+            last_launched: None,
+        }
+    }
+}
+
+/// `prefixes/eve-<name>`
+pub fn profile_dir(prefixes_dir: &Path, name: &str) -> PathBuf {
+    prefixes_dir.join(format!("{DIR_PREFIX}{name}"))
+}
+
+pub fn read(prefix_dir: &Path) -> Result<Option<ProfileMeta>> {
+    let path = prefix_dir.join(PROFILE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    Ok(Some(serde_json::from_str(&content).with_context(|| format!("parsing {}", path.display()))?))
+}
+
+pub fn write(prefix_dir: &Path, meta: &ProfileMeta) -> Result<()> {
+    fs::create_dir_all(prefix_dir).with_context(|| format!("create {}", prefix_dir.display()))?;
+    let path = prefix_dir.join(PROFILE_FILE);
+    fs::write(&path, serde_json::to_vec_pretty(meta)?).with_context(|| format!("writing {}", path.display()))
+}
+
+/// The profile's metadata, or what can be inferred for a prefix created
+/// before `profile.json` existed (name from the directory, created from its mtime)
+pub fn load(prefix_dir: &Path) -> Result<ProfileMeta> {
+    if let Some(meta) = read(prefix_dir)? {
+        return Ok(meta);
+    }
+    let dir_name = prefix_dir.file_name().unwrap_or_default().to_string_lossy();
+    let name = dir_name.strip_prefix(DIR_PREFIX).unwrap_or(&dir_name);
+    let mut meta = ProfileMeta::new(name, None);
+    if let Ok(modified) = fs::metadata(prefix_dir).and_then(|m| m.modified()) {
+        meta.created = modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    }
+    Ok(meta)
+}
+
+/// Record a launch of the profile with `engine_ref`, creating `profile.json` if needed
+pub fn record_launch(prefix_dir: &Path, engine_ref: &str) -> Result<()> {
+    let mut meta = load(prefix_dir)?;
+    meta.last_launched = Some(now());
+    meta.engine_ref = Some(engine_ref.to_string());
+    write(prefix_dir, &meta)
+}
+
+/// Give a copied or imported prefix its new name, keeping the rest of its metadata
+pub fn rename(prefix_dir: &Path, name: &str) -> Result<()> {
+    let mut meta = load(prefix_dir)?;
+    if meta.display_name == meta.name {
+        meta.display_name = name.to_string();
+    }
+    meta.name = name.to_string();
+    write(prefix_dir, &meta)
+}
+
+/// Every profile under `prefixes_dir` with its prefix directory, sorted by name
+pub fn list(prefixes_dir: &Path) -> Result<Vec<(PathBuf, ProfileMeta)>> {
+    if !prefixes_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut profiles = Vec::new();
+    for entry in fs::read_dir(prefixes_dir).with_context(|| format!("reading {}", prefixes_dir.display()))?.flatten() {
+        let path = entry.path();
+        let dir_name = entry.file_name().to_string_lossy().to_string();
+        let staging = dir_name
+            .rsplit_once('.')
+            .is_some_and(|(_, suffix)| STAGING_SUFFIXES.contains(&suffix));
+        if !path.is_dir() || !dir_name.starts_with(DIR_PREFIX) || staging {
+            continue;
+        }
+        let meta = load(&path)?;
+        profiles.push((path, meta));
+    }
+    profiles.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    Ok(profiles)
+}
@@ -0,0 +1,50 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use super::restore::restore_prefix;
+use super::snapshot::{read_metadata, write_archive, SnapshotMetadata, SnapshotOptions};
+
+/// File extension of exported profile bundles
+pub const BUNDLE_EXTENSION: &str = "elmprofile";
+
+/// Write `prefix_dir` and `metadata` (which should carry a [`super::snapshot::BundleConfig`])
+/// to a single `.elmprofile` file: a snapshot archive that needs no index or base
+/// (the default [`super::snapshot::DEFAULT_EXCLUDES`] are left out)
+pub fn export_profile(prefix_dir: &Path, out: &Path, metadata: &SnapshotMetadata) -> Result<()> {
+    if !prefix_dir.is_dir() {
+        return Err(anyhow!("prefix not found: {}", prefix_dir.display()));
+    }
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+
+    // Write next to the destination and rename, so a failed export leaves no partial bundle
+    let partial = out.with_file_name(format!(
+        ".{}.partial",
+        out.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    if let Err(e) = write_archive(prefix_dir, &partial, metadata, &SnapshotOptions::default(), None, &mut |_| {}) {
+        fs::remove_file(&partial).ok();
+        return Err(e);
+    }
+    fs::rename(&partial, out).with_context(|| format!("move bundle to {}", out.display()))
+}
+
+/// Unpack a bundle's prefix into `prefix_dir` (which must not exist yet) and
+/// return its metadata. `active_engine` is only used to warn about a mismatch.
+pub fn import_profile(bundle: &Path, prefix_dir: &Path, active_engine: Option<&str>) -> Result<SnapshotMetadata> {
+    let metadata = read_metadata(bundle)?
+        .ok_or_else(|| anyhow!("{} is not a profile bundle (no metadata)", bundle.display()))?;
+    if metadata.bundle.is_none() {
+        return Err(anyhow!(
+            "{} is a plain snapshot, not a profile bundle (restore it with elm rollback)",
+            bundle.display()
+        ));
+    }
+    if prefix_dir.exists() {
+        return Err(anyhow!("{} already exists", prefix_dir.display()));
+    }
+    restore_prefix(bundle, prefix_dir, active_engine)?;
+    Ok(metadata)
+}
@@ -1,2 +1,4 @@
 pub mod snapshot;
 pub mod restore;
+pub mod verify;
+pub mod bundle;
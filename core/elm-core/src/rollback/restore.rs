@@ -1,28 +1,326 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-pub fn restore_prefix(snapshot_tar_zst: &Path, prefix_dir: &Path) -> Result<()> {
-    if prefix_dir.exists() {
-        fs::remove_dir_all(prefix_dir).with_context(|| format!("remove {}", prefix_dir.display()))?;
+use super::snapshot::{glob_match, read_index, read_metadata, SnapshotIndex, SnapshotMetadata};
+use super::verify::verify_snapshot;
+
+/// Restore `prefix_dir` from a snapshot. `active_engine` is the engine the prefix will
+/// run under; a warning is printed if the snapshot was taken with a different one.
+/// Partial (settings-only) snapshots go through [`restore_merge`] instead.
+/// A snapshot that doesn't read back is [`ElmError::CorruptSnapshot`](crate::ElmError::CorruptSnapshot), and the
+/// prefix is untouched.
+pub fn restore_prefix(
+    snapshot_tar_zst: &Path,
+    prefix_dir: &Path,
+    active_engine: Option<&str>,
+) -> crate::error::Result<()> {
+    Ok(restore_full(snapshot_tar_zst, prefix_dir, active_engine)?)
+}
+
+fn restore_full(snapshot_tar_zst: &Path, prefix_dir: &Path, active_engine: Option<&str>) -> Result<()> {
+    let chain = verified_chain(snapshot_tar_zst)?;
+
+    let metadata = read_metadata(snapshot_tar_zst)?;
+    if metadata.as_ref().is_some_and(|m| m.partial) {
+        return merge_chain(&chain, prefix_dir, metadata.as_ref(), active_engine);
+    }
+    warn_engine_mismatch(metadata.as_ref(), active_engine);
+
+    // Extract next to the prefix (same filesystem) so the final swap is a rename;
+    // if anything fails the original prefix is left as it was
+    let restoring = sibling(prefix_dir, "restoring");
+    if restoring.exists() {
+        fs::remove_dir_all(&restoring).with_context(|| format!("remove {}", restoring.display()))?;
+    }
+    fs::create_dir_all(&restoring).with_context(|| format!("create {}", restoring.display()))?;
+
+    let result = (|| {
+        // Full base first, then each incremental delta on top
+        for snapshot in &chain {
+            apply_snapshot(snapshot, &restoring)?;
+        }
+
+        // Deltas only carry changed files; drop anything deleted since the base
+        if chain.len() > 1 {
+            if let Some(index) = read_index(snapshot_tar_zst)? {
+                prune_to_index(&restoring, &index)?;
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        fs::remove_dir_all(&restoring).ok();
+        return Err(e);
     }
+
+    let excludes = metadata.map(|m| m.excludes).unwrap_or_default();
+    swap_into_place(&restoring, prefix_dir, &excludes)
+}
+
+/// Extract a snapshot over `prefix_dir` without deleting anything first: files in
+/// the snapshot replace their counterparts, everything else in the prefix stays.
+/// The prefix is created if it doesn't exist.
+pub fn restore_merge(
+    snapshot_tar_zst: &Path,
+    prefix_dir: &Path,
+    active_engine: Option<&str>,
+) -> crate::error::Result<()> {
+    let chain = verified_chain(snapshot_tar_zst)?;
+    Ok(merge_chain(&chain, prefix_dir, read_metadata(snapshot_tar_zst)?.as_ref(), active_engine)?)
+}
+
+fn merge_chain(
+    chain: &[PathBuf],
+    prefix_dir: &Path,
+    metadata: Option<&SnapshotMetadata>,
+    active_engine: Option<&str>,
+) -> Result<()> {
+    warn_engine_mismatch(metadata, active_engine);
     fs::create_dir_all(prefix_dir).with_context(|| format!("create {}", prefix_dir.display()))?;
+    for snapshot in chain {
+        apply_snapshot(snapshot, prefix_dir)?;
+    }
+    Ok(())
+}
+
+/// The snapshot's chain, base first, after checking every archive reads back
+fn verified_chain(snapshot_tar_zst: &Path) -> Result<Vec<PathBuf>> {
+    let chain = snapshot_chain(snapshot_tar_zst)?;
+    for snapshot in &chain {
+        verify_snapshot(snapshot)?;
+    }
+    Ok(chain)
+}
+
+fn warn_engine_mismatch(metadata: Option<&SnapshotMetadata>, active_engine: Option<&str>) {
+    if let (Some(recorded), Some(active)) = (recorded_engine(metadata, active_engine), active_engine) {
+        tracing::warn!("snapshot was taken with engine '{recorded}' but the active engine is '{active}'");
+    }
+}
+
+/// The engine `metadata` was taken with, if it is known and isn't `active_engine`
+fn recorded_engine<'a>(metadata: Option<&'a SnapshotMetadata>, active_engine: Option<&str>) -> Option<&'a str> {
+    let recorded = metadata?.engine_id.as_deref()?;
+    (recorded != active_engine?).then_some(recorded)
+}
+
+/// The engine a snapshot was taken with, when that isn't `engine`. A prefix
+/// restored from it was last set up by another Wine, so it wants a
+/// [`crate::prefix::update_prefix`] before it runs reliably under `engine`.
+pub fn engine_mismatch(snapshot_tar_zst: &Path, engine: &str) -> Result<Option<String>> {
+    let metadata = read_metadata(snapshot_tar_zst)?;
+    Ok(recorded_engine(metadata.as_ref(), Some(engine)).map(str::to_string))
+}
+
+/// `<prefix>.<suffix>` next to the prefix
+fn sibling(prefix_dir: &Path, suffix: &str) -> PathBuf {
+    let name = prefix_dir.file_name().unwrap_or_default().to_string_lossy();
+    prefix_dir.with_file_name(format!("{name}.{suffix}"))
+}
+
+/// Replace `prefix_dir` with the fully restored `restoring` dir. Paths matching
+/// `excludes` were never archived, so they're moved over from the old prefix
+/// (the game cache and logs survive a rollback).
+fn swap_into_place(restoring: &Path, prefix_dir: &Path, excludes: &[String]) -> Result<()> {
+    if !prefix_dir.exists() {
+        return fs::rename(restoring, prefix_dir).with_context(|| format!("move restored prefix to {}", prefix_dir.display()));
+    }
+
+    let old = sibling(prefix_dir, "old");
+    if old.exists() {
+        fs::remove_dir_all(&old).with_context(|| format!("remove {}", old.display()))?;
+    }
+    fs::rename(prefix_dir, &old).with_context(|| format!("move {} aside", prefix_dir.display()))?;
+    if let Err(e) = fs::rename(restoring, prefix_dir) {
+        // Put the original back rather than leave no prefix at all
+        fs::rename(&old, prefix_dir).ok();
+        fs::remove_dir_all(restoring).ok();
+        return Err(e).with_context(|| format!("move restored prefix to {}", prefix_dir.display()));
+    }
+    // Leave the old prefix in place if this fails, so nothing is lost
+    carry_excluded(&old, prefix_dir, Path::new(""), excludes)
+        .with_context(|| format!("keep excluded paths from {}", old.display()))?;
+    fs::remove_dir_all(&old).with_context(|| format!("remove old prefix {}", old.display()))?;
+    Ok(())
+}
+
+/// Move whatever under `from` matches `excludes` to the same place under `to`,
+/// unless the restored prefix already has it
+fn carry_excluded(from: &Path, to: &Path, rel: &Path, excludes: &[String]) -> Result<()> {
+    if excludes.is_empty() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(from.join(rel))?.flatten() {
+        let child_rel = rel.join(entry.file_name());
+        let src = entry.path();
+        let dst = to.join(&child_rel);
+        let is_dir = fs::symlink_metadata(&src)?.is_dir();
+        let excluded = excludes.iter().any(|pattern| glob_match(pattern, &child_rel.to_string_lossy(), false));
+        match fs::symlink_metadata(&dst) {
+            Err(_) if excluded => {
+                if let Some(parent) = dst.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&src, &dst).with_context(|| format!("move {}", dst.display()))?;
+            }
+            Ok(m) if m.is_dir() && is_dir => carry_excluded(from, to, &child_rel, excludes)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Snapshots needed to restore `snapshot`, base first
+fn snapshot_chain(snapshot: &Path) -> Result<Vec<PathBuf>> {
+    let mut chain = vec![snapshot.to_path_buf()];
+    let mut seen = HashSet::new();
+    let mut current = snapshot.to_path_buf();
+
+    while let Some(base) = read_index(&current)?.and_then(|i| i.base) {
+        let base_path = current.with_file_name(&base);
+        if !seen.insert(base_path.clone()) {
+            return Err(anyhow!("snapshot chain loops at {}", base_path.display()));
+        }
+        if !base_path.exists() {
+            return Err(anyhow!("base snapshot missing: {}", base_path.display()));
+        }
+        chain.push(base_path.clone());
+        current = base_path;
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Unpack one archive and move its "prefix" directory contents into `prefix_dir`
+fn apply_snapshot(snapshot_tar_zst: &Path, prefix_dir: &Path) -> Result<()> {
+    let staging = sibling(prefix_dir, "unpack");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging).with_context(|| format!("create {}", staging.display()))?;
+
+    let result = (|| {
+        let f = fs::File::open(snapshot_tar_zst).with_context(|| format!("open {}", snapshot_tar_zst.display()))?;
+        let decoder = zstd::Decoder::new(f).context("zstd decoder")?;
+        let mut archive = tar::Archive::new(decoder);
+        archive.set_preserve_permissions(true);
+        archive.set_preserve_mtime(true);
+        archive.set_unpack_xattrs(true);
+        // Only root can hand files back to their recorded owners
+        // SAFETY: geteuid has no preconditions and can't fail
+        archive.set_preserve_ownerships(unsafe { libc::geteuid() } == 0);
+        archive.unpack(&staging).context("untar snapshot")?;
+
+        // Archives store everything under a "prefix" directory
+        let extracted = staging.join("prefix");
+        if extracted.exists() {
+            merge_tree(&extracted, prefix_dir)?;
+        }
+        Ok(())
+    })();
 
-    let f = fs::File::open(snapshot_tar_zst).with_context(|| format!("open {}", snapshot_tar_zst.display()))?;
-    let decoder = zstd::Decoder::new(f).context("zstd decoder")?;
-    let mut archive = tar::Archive::new(decoder);
+    fs::remove_dir_all(&staging).ok();
+    result
+}
 
-    // Unpack to parent so it recreates a "prefix" directory
-    let parent = prefix_dir.parent().unwrap_or(prefix_dir);
-    archive.unpack(parent).context("untar snapshot")?;
+/// Move everything under `src` into `dst`, replacing existing entries
+fn merge_tree(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)?.flatten() {
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        let from_meta = fs::symlink_metadata(&from)?;
+        let to_meta = fs::symlink_metadata(&to).ok();
 
-    // Move extracted "prefix" into place
-    let extracted = parent.join("prefix");
-    if extracted.exists() && extracted != prefix_dir {
-        if prefix_dir.exists() {
-            fs::remove_dir_all(prefix_dir)?;
+        match to_meta {
+            Some(m) if m.is_dir() && from_meta.is_dir() => merge_tree(&from, &to)?,
+            Some(m) => {
+                if m.is_dir() {
+                    fs::remove_dir_all(&to)?;
+                } else {
+                    fs::remove_file(&to)?;
+                }
+                fs::rename(&from, &to).with_context(|| format!("move {}", to.display()))?;
+            }
+            None => fs::rename(&from, &to).with_context(|| format!("move {}", to.display()))?,
         }
-        fs::rename(extracted, prefix_dir).context("rename extracted prefix")?;
     }
+
+    // An existing dst keeps its own mode unless told otherwise, and moving
+    // entries into it just bumped its mtime
+    let src_meta = fs::metadata(src)?;
+    fs::set_permissions(dst, src_meta.permissions()).with_context(|| format!("set mode of {}", dst.display()))?;
+    fs::File::open(dst).and_then(|d| d.set_modified(src_meta.modified()?)).ok();
     Ok(())
 }
+
+/// Remove anything under `prefix_dir` the index doesn't list
+fn prune_to_index(prefix_dir: &Path, index: &SnapshotIndex) -> Result<()> {
+    fn walk(dir: &Path, rel: &Path, index: &SnapshotIndex) -> Result<()> {
+        for entry in fs::read_dir(dir)?.flatten() {
+            let path = entry.path();
+            let child_rel = rel.join(entry.file_name());
+            let meta = fs::symlink_metadata(&path)?;
+            if !index.entries.contains_key(child_rel.to_string_lossy().as_ref()) {
+                if meta.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+            } else if meta.is_dir() {
+                walk(&path, &child_rel, index)?;
+            }
+        }
+        Ok(())
+    }
+    walk(prefix_dir, Path::new(""), index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollback::snapshot::{snapshot_prefix, SnapshotOptions};
+
+    const SETTINGS: &str = "pfx/drive_c/users/steamuser/AppData/Local/CCP/EVE/core_user_1.dat";
+    const RESFILE: &str = "pfx/drive_c/CCP/EVE/ResFiles/ab/abcdef_1";
+    const LOG: &str = "pfx/drive_c/users/steamuser/AppData/Roaming/EVE Online/logs/launcher.log";
+
+    fn write(root: &Path, rel: &str, contents: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn full_restore_keeps_excluded_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("prefix");
+        write(&prefix, SETTINGS, "before");
+        write(&prefix, RESFILE, "cache");
+        write(&prefix, LOG, "old log");
+
+        let metadata = SnapshotMetadata::new(&prefix, None, None);
+        let snapshots = dir.path().join("snapshots");
+        let snapshot =
+            snapshot_prefix(&prefix, &snapshots, "eve-pre-update", &metadata, &SnapshotOptions::default(), |_| {})
+                .unwrap();
+
+        write(&prefix, SETTINGS, "after");
+        write(&prefix, "pfx/drive_c/CCP/EVE/ResFiles/cd/cdef_2", "newer cache");
+        write(&prefix, LOG, "new log");
+        write(&prefix, "pfx/drive_c/stray.txt", "added after the snapshot");
+
+        restore_prefix(&snapshot, &prefix, None).unwrap();
+
+        let read = |rel: &str| fs::read_to_string(prefix.join(rel)).unwrap();
+        assert_eq!(read(SETTINGS), "before");
+        assert!(!prefix.join("pfx/drive_c/stray.txt").exists());
+        // Never archived, so carried over from the prefix that was replaced
+        assert_eq!(read(RESFILE), "cache");
+        assert_eq!(read("pfx/drive_c/CCP/EVE/ResFiles/cd/cdef_2"), "newer cache");
+        assert_eq!(read(LOG), "new log");
+        assert!(!sibling(&prefix, "old").exists());
+    }
+}
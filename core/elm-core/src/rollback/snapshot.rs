@@ -1,68 +1,779 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
+use std::io::Read;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::collections::HashSet;
 
-pub fn snapshot_prefix(prefix_dir: &Path, snapshots_dir: &Path, snapshot_name: &str) -> Result<PathBuf> {
+use crate::system::disk;
+
+/// Sidecar index written next to every snapshot (`<name>.index.json`).
+/// Lists every path in the prefix so an incremental snapshot can be
+/// restored from its base plus the delta.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SnapshotIndex {
+    /// File name of the snapshot this one is a delta against
+    pub base: Option<String>,
+    /// Paths relative to the prefix root
+    pub entries: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IndexEntry {
+    pub kind: EntryKind,
+    pub size: u64,
+    pub mtime: i64,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+/// Capture-time context, stored as `metadata.json`, the first entry of every snapshot archive
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SnapshotMetadata {
+    /// Engine that was active when the snapshot was taken
+    pub engine_id: Option<String>,
+    pub profile: Option<String>,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    pub source_prefix: PathBuf,
+    /// Profile config carried by exported profile bundles; absent in regular snapshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<BundleConfig>,
+    /// Only part of the prefix was archived (see [`SnapshotOptions::includes`]),
+    /// so restoring merges into the prefix instead of replacing it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Globs left out of the archive (see [`SnapshotOptions::excludes`]); a
+    /// full restore keeps whatever matches them from the prefix it replaces
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excludes: Vec<String>,
+}
+
+/// Config that travels with a profile bundle so it can be recreated on another machine
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BundleConfig {
+    /// The exporting machine's `manifests/eve-online.json`
+    pub manifest: Option<serde_json::Value>,
+    /// The exported profile's `profiles/<name>.json`, if it had one
+    pub profile: Option<serde_json::Value>,
+}
+
+impl SnapshotMetadata {
+    pub fn new(source_prefix: &Path, engine_id: Option<String>, profile: Option<String>) -> Self {
+        let created = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        SnapshotMetadata {
+            engine_id,
+            profile,
+            created,
+            source_prefix: source_prefix.to_path_buf(),
+            bundle: None,
+            partial: false,
+            excludes: Vec::new(),
+        }
+    }
+}
+
+const METADATA_ENTRY: &str = "metadata.json";
+
+/// Read the metadata entry of a snapshot without decompressing the rest of the archive.
+/// Returns `None` for snapshots created before metadata was recorded.
+pub fn read_metadata(snapshot: &Path) -> Result<Option<SnapshotMetadata>> {
+    let f = File::open(snapshot).with_context(|| format!("open {}", snapshot.display()))?;
+    let decoder = zstd::Decoder::new(f).context("zstd decoder")?;
+    let mut archive = tar::Archive::new(decoder);
+    let Some(entry) = archive.entries()?.next() else {
+        return Ok(None);
+    };
+    let mut entry = entry?;
+    if entry.path()?.as_ref() != Path::new(METADATA_ENTRY) {
+        return Ok(None);
+    }
+    let mut buf = Vec::new();
+    entry.read_to_end(&mut buf)?;
+    Ok(Some(serde_json::from_slice(&buf).context("parsing snapshot metadata")?))
+}
+
+/// Index path for a snapshot archive: `foo.tar.zst` -> `foo.index.json`
+pub fn index_path(snapshot: &Path) -> PathBuf {
+    let name = snapshot.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name.strip_suffix(".tar.zst").unwrap_or(&name);
+    snapshot.with_file_name(format!("{stem}.index.json"))
+}
+
+/// Read the index of a snapshot, if it has one (older snapshots don't)
+pub fn read_index(snapshot: &Path) -> Result<Option<SnapshotIndex>> {
+    let path = index_path(snapshot);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let s = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    Ok(Some(serde_json::from_str(&s).with_context(|| format!("parsing {}", path.display()))?))
+}
+
+/// Paths skipped by default: EVE's redownloadable resource cache, logs and crash dumps
+pub const DEFAULT_EXCLUDES: &[&str] = &["**/ResFiles/**", "**/logs/**", "**/Crash Reports/**"];
+
+/// Where EVE keeps per-user state in a prefix: client settings, overviews and
+/// character profiles (`CCP/EVE`), and the launcher's accounts and settings
+pub const SETTINGS_PATHS: &[&str] = &[
+    "drive_c/users/*/AppData/Local/CCP/EVE/**",
+    "drive_c/users/*/AppData/Local/eve-online/**",
+    "drive_c/users/*/AppData/Roaming/EVE Online/**",
+];
+
+/// The launcher's own binaries, which live alongside its settings
+const LAUNCHER_BINARIES: &[&str] = &[
+    "drive_c/users/*/AppData/Local/eve-online/app-*/**",
+    "drive_c/users/*/AppData/Local/eve-online/packages/**",
+    "drive_c/users/*/AppData/Local/eve-online/*.exe",
+];
+
+/// What goes into a snapshot. Globs are matched against paths relative to the
+/// prefix root; `*` and `?` stay within one path component, `**` spans any
+/// number of them.
+#[derive(Debug, Clone)]
+pub struct SnapshotOptions {
+    /// Archive only paths matching one of these (everything when empty)
+    pub includes: Vec<String>,
+    pub excludes: Vec<String>,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        SnapshotOptions {
+            includes: Vec::new(),
+            excludes: DEFAULT_EXCLUDES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl SnapshotOptions {
+    /// Just [`SETTINGS_PATHS`]: a small backup that restores onto a fresh install
+    pub fn settings_only() -> Self {
+        let mut options = SnapshotOptions {
+            includes: SETTINGS_PATHS.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        options.excludes.extend(LAUNCHER_BINARIES.iter().map(|s| s.to_string()));
+        options
+    }
+
+    fn skips(&self, rel: &Path) -> bool {
+        let rel = rel.to_string_lossy();
+        if rel.is_empty() {
+            return false;
+        }
+        // Directories above an included path are walked, not skipped
+        let included = self.includes.is_empty() || self.includes.iter().any(|pattern| glob_match(pattern, &rel, true));
+        !included || self.excludes.iter().any(|pattern| glob_match(pattern, &rel, false))
+    }
+}
+
+/// Match `path` against `pattern` component by component. With `partial`, a
+/// path that runs out before the pattern does (a possible ancestor of a match) counts.
+pub(crate) fn glob_match(pattern: &str, path: &str, partial: bool) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    match_components(&pattern, &path, partial)
+}
+
+fn match_components(pattern: &[&str], path: &[&str], partial: bool) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_components(rest, &path[skip..], partial)),
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                match_component(first.as_bytes(), name.as_bytes()) && match_components(rest, path_rest, partial)
+            }
+            None => partial,
+        },
+    }
+}
+
+/// `*` and `?` wildcards within a single path component
+fn match_component(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_component(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_component(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_component(rest, &name[1..]),
+    }
+}
+
+/// Running totals passed to a snapshot's progress callback after each archived entry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SnapshotProgress {
+    /// Files, directories and symlinks archived so far
+    pub entries: u64,
+    /// File data read so far
+    pub bytes: u64,
+}
+
+/// `progress` is called after every archived entry; pass `|_| {}` to ignore it
+pub fn snapshot_prefix(
+    prefix_dir: &Path,
+    snapshots_dir: &Path,
+    snapshot_name: &str,
+    metadata: &SnapshotMetadata,
+    options: &SnapshotOptions,
+    mut progress: impl FnMut(SnapshotProgress),
+) -> Result<PathBuf> {
+    write_snapshot(prefix_dir, snapshots_dir, snapshot_name, metadata, options, None, &mut progress)
+}
+
+/// Snapshot only what changed (by size and mtime) since `base_snapshot`
+pub fn snapshot_prefix_incremental(
+    prefix_dir: &Path,
+    snapshots_dir: &Path,
+    snapshot_name: &str,
+    base_snapshot: &Path,
+    metadata: &SnapshotMetadata,
+    options: &SnapshotOptions,
+    mut progress: impl FnMut(SnapshotProgress),
+) -> Result<PathBuf> {
+    // Restoring a partial delta can't tell deleted files from ones it never covered
+    if !options.includes.is_empty() {
+        return Err(anyhow!("only full snapshots can be incremental"));
+    }
+    let base_index = read_index(base_snapshot)?
+        .ok_or_else(|| anyhow!("base snapshot has no index: {}", index_path(base_snapshot).display()))?;
+    let base_name = base_snapshot
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid base snapshot path: {}", base_snapshot.display()))?
+        .to_string_lossy()
+        .to_string();
+    // The chain is resolved relative to the snapshots dir on restore
+    if !snapshots_dir.join(&base_name).exists() {
+        return Err(anyhow!("base snapshot must live in {}", snapshots_dir.display()));
+    }
+    let base = Some((base_name, base_index));
+    write_snapshot(prefix_dir, snapshots_dir, snapshot_name, metadata, options, base, &mut progress)
+}
+
+fn write_snapshot(
+    prefix_dir: &Path,
+    snapshots_dir: &Path,
+    snapshot_name: &str,
+    metadata: &SnapshotMetadata,
+    options: &SnapshotOptions,
+    base: Option<(String, SnapshotIndex)>,
+    progress: &mut dyn FnMut(SnapshotProgress),
+) -> Result<PathBuf> {
     fs::create_dir_all(snapshots_dir).with_context(|| format!("create {}", snapshots_dir.display()))?;
     let out_path = snapshots_dir.join(format!("{snapshot_name}.tar.zst"));
 
-    let out = File::create(&out_path).with_context(|| format!("create {}", out_path.display()))?;
+    let index = write_archive(prefix_dir, &out_path, metadata, options, base, progress)?;
+    let index_file = index_path(&out_path);
+    fs::write(&index_file, serde_json::to_vec(&index)?).with_context(|| format!("write {}", index_file.display()))?;
+
+    Ok(out_path)
+}
+
+/// Write `metadata.json` plus the prefix (under `prefix/`) to a zstd tarball at `out_path`,
+/// returning the index of what it contains
+pub(crate) fn write_archive(
+    prefix_dir: &Path,
+    out_path: &Path,
+    metadata: &SnapshotMetadata,
+    options: &SnapshotOptions,
+    base: Option<(String, SnapshotIndex)>,
+    progress: &mut dyn FnMut(SnapshotProgress),
+) -> Result<SnapshotIndex> {
+    let (base_name, base_entries) = match base {
+        Some((name, index)) => (Some(name), index.entries),
+        None => (None, BTreeMap::new()),
+    };
+    // Compression usually shrinks this, but a prefix full of game data won't shrink much
+    let estimate = pending_size(prefix_dir, Path::new(""), &base_entries, options);
+    let out_dir = out_path.parent().unwrap_or(Path::new("."));
+    disk::ensure_space(out_dir, estimate, &format!("snapshot {}", prefix_dir.display()))?;
+
+    let out = File::create(out_path).with_context(|| format!("create {}", out_path.display()))?;
     let encoder = zstd::Encoder::new(out, 3).context("zstd encoder")?;
     let mut tar_builder = tar::Builder::new(encoder);
     tar_builder.follow_symlinks(false);
 
+    // Metadata goes first so read_metadata only has to decode one entry
+    let metadata = SnapshotMetadata {
+        partial: !options.includes.is_empty(),
+        excludes: options.excludes.clone(),
+        ..metadata.clone()
+    };
+    let metadata_json = serde_json::to_vec_pretty(&metadata)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(metadata_json.len() as u64);
+    header.set_mode(0o644);
+    // A blank owner can't be parsed back when root restores with ownership kept
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(metadata.created);
+    header.set_cksum();
+    tar_builder
+        .append_data(&mut header, METADATA_ENTRY, metadata_json.as_slice())
+        .context("write snapshot metadata")?;
+
+    let mut walk = Walk {
+        visited: HashSet::new(),
+        links: HashMap::new(),
+        base: &base_entries,
+        options,
+        index: BTreeMap::new(),
+        progress,
+        totals: SnapshotProgress::default(),
+    };
+
     // Walk directory manually to handle symlinks properly
-    let mut visited_inodes: HashSet<u64> = HashSet::new();
-    append_dir_recursive(&mut tar_builder, prefix_dir, Path::new("prefix"), &mut visited_inodes)?;
+    append_dir_recursive(&mut tar_builder, prefix_dir, Path::new("prefix"), Path::new(""), &mut walk)?;
 
     let encoder = tar_builder.into_inner().context("finish tar")?;
     encoder.finish().context("finish zstd")?;
 
-    Ok(out_path)
+    Ok(SnapshotIndex { base: base_name, entries: walk.index })
+}
+
+/// Bytes of file data under `path` that a snapshot on top of `base` would archive
+fn pending_size(path: &Path, rel: &Path, base: &BTreeMap<String, IndexEntry>, options: &SnapshotOptions) -> u64 {
+    if options.skips(rel) {
+        return 0;
+    }
+    let Ok(meta) = fs::symlink_metadata(path) else { return 0 };
+    if meta.is_dir() {
+        return fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| pending_size(&e.path(), &rel.join(e.file_name()), base, options))
+                    .sum()
+            })
+            .unwrap_or(0);
+    }
+    if !meta.is_file() {
+        return 0;
+    }
+    let unchanged = base.get(rel.to_string_lossy().as_ref()).is_some_and(|prev| {
+        prev.kind == EntryKind::File && prev.size == meta.len() && prev.mtime == meta.mtime()
+    });
+    if unchanged {
+        0
+    } else {
+        meta.len()
+    }
+}
+
+struct Walk<'a> {
+    /// Directory inodes, to avoid infinite loops
+    visited: HashSet<u64>,
+    /// Archived files with more than one link, by (device, inode): where the
+    /// first copy went in the archive, and its index entry
+    links: HashMap<(u64, u64), (PathBuf, IndexEntry)>,
+    /// Entries of the base snapshot for incremental mode (empty for full snapshots)
+    base: &'a BTreeMap<String, IndexEntry>,
+    options: &'a SnapshotOptions,
+    index: BTreeMap<String, IndexEntry>,
+    progress: &'a mut dyn FnMut(SnapshotProgress),
+    totals: SnapshotProgress,
 }
 
 fn append_dir_recursive<W: std::io::Write>(
     builder: &mut tar::Builder<W>,
     src_path: &Path,
     tar_path: &Path,
-    visited: &mut HashSet<u64>,
+    rel_path: &Path,
+    walk: &mut Walk,
 ) -> Result<()> {
+    // Excluded paths leave no trace, not even in the index
+    if walk.options.skips(rel_path) {
+        return Ok(());
+    }
     let metadata = fs::symlink_metadata(src_path)?;
 
     // Track inodes to avoid infinite loops
     let inode = metadata.ino();
-    if metadata.is_dir() && !visited.insert(inode) {
+    if metadata.is_dir() && !walk.visited.insert(inode) {
         return Ok(()); // Already visited this directory
     }
 
+    let rel = rel_path.to_string_lossy().to_string();
+    let mut entry = IndexEntry {
+        kind: EntryKind::File,
+        size: metadata.len(),
+        mtime: metadata.mtime(),
+        sha256: None,
+    };
+
+    // Mode, owner and mtime come from the lstat above for every entry type,
+    // so neither symlinks nor directories are re-stat'ed through
+    let mut header = tar::Header::new_gnu();
+    header.set_metadata(&metadata);
+
     if metadata.is_symlink() {
         // Store symlink as-is
         let target = fs::read_link(src_path)?;
-        let mut header = tar::Header::new_gnu();
-        header.set_entry_type(tar::EntryType::Symlink);
         header.set_size(0);
+        append_xattrs(builder, src_path);
         builder.append_link(&mut header, tar_path, &target).ok();
+        entry.kind = EntryKind::Symlink;
     } else if metadata.is_dir() {
-        builder.append_dir(tar_path, src_path).ok();
+        header.set_size(0);
+        append_xattrs(builder, src_path);
+        builder.append_data(&mut header, tar_path, std::io::empty()).ok();
+        entry.kind = EntryKind::Dir;
 
         if let Ok(entries) = fs::read_dir(src_path) {
-            for entry in entries.flatten() {
-                let child_src = entry.path();
-                let child_tar = tar_path.join(entry.file_name());
-                append_dir_recursive(builder, &child_src, &child_tar, visited)?;
+            for child in entries.flatten() {
+                let child_src = child.path();
+                let child_tar = tar_path.join(child.file_name());
+                let child_rel = rel_path.join(child.file_name());
+                append_dir_recursive(builder, &child_src, &child_tar, &child_rel, walk)?;
             }
         }
     } else if metadata.is_file() {
-        if let Ok(mut file) = File::open(src_path) {
-            let mut header = tar::Header::new_gnu();
-            header.set_metadata(&metadata);
+        // Unchanged since the base snapshot: record it, but don't archive it again
+        if let Some(prev) = walk.base.get(&rel) {
+            if prev.kind == EntryKind::File && prev.size == entry.size && prev.mtime == entry.mtime {
+                walk.index.insert(rel, prev.clone());
+                return Ok(());
+            }
+        }
+
+        // Later links to an already archived file become tar hardlinks to it
+        let key = (metadata.dev(), metadata.ino());
+        if metadata.nlink() > 1 {
+            if let Some((first, first_entry)) = walk.links.get(&key) {
+                let first_entry = first_entry.clone();
+                header.set_entry_type(tar::EntryType::Link);
+                header.set_size(0);
+                if builder.append_link(&mut header, tar_path, first).is_err() {
+                    return Ok(());
+                }
+                walk.totals.entries += 1;
+                (walk.progress)(walk.totals);
+                walk.index.insert(rel, first_entry);
+                return Ok(());
+            }
+        }
+
+        if let Ok(file) = File::open(src_path) {
+            let mut reader = HashingReader { inner: file, hasher: Sha256::new() };
             header.set_size(metadata.len());
-            header.set_cksum();
-            builder.append_data(&mut header, tar_path, &mut file).ok();
+            append_xattrs(builder, src_path);
+            if builder.append_data(&mut header, tar_path, &mut reader).is_err() {
+                return Ok(());
+            }
+            entry.sha256 = Some(hex::encode(reader.hasher.finalize()));
+            walk.totals.bytes += entry.size;
+            if metadata.nlink() > 1 {
+                walk.links.insert(key, (tar_path.to_path_buf(), entry.clone()));
+            }
+        } else {
+            return Ok(());
         }
+    } else {
+        return Ok(());
     }
 
+    walk.totals.entries += 1;
+    (walk.progress)(walk.totals);
+    if !rel.is_empty() {
+        walk.index.insert(rel, entry);
+    }
     Ok(())
 }
+
+/// Namespace of the extended attributes kept in snapshots. Wine stores DOS file
+/// attributes in `user.wine.dosattrib`; `security.*` and friends are host-specific
+/// and usually can't be set back by an unprivileged restore.
+const XATTR_NAMESPACE: &str = "user.";
+
+/// Write a PAX header carrying `src_path`'s user xattrs (as `SCHILY.xattr.*`
+/// records, which tar unpacks onto the next entry). Nothing is written when
+/// there are none or the filesystem doesn't support them.
+fn append_xattrs<W: std::io::Write>(builder: &mut tar::Builder<W>, src_path: &Path) {
+    let Ok(names) = xattr::list(src_path) else { return };
+    let mut records = Vec::new();
+    for name in names {
+        let name = name.to_string_lossy();
+        if !name.starts_with(XATTR_NAMESPACE) {
+            continue;
+        }
+        let Ok(Some(value)) = xattr::get(src_path, name.as_ref()) else { continue };
+        pax_record(&mut records, &format!("SCHILY.xattr.{name}"), &value);
+    }
+    if records.is_empty() {
+        return;
+    }
+
+    let mut header = tar::Header::new_ustar();
+    header.set_entry_type(tar::EntryType::XHeader);
+    header.set_path("PaxHeader").ok();
+    header.set_mode(0o644);
+    header.set_size(records.len() as u64);
+    header.set_cksum();
+    builder.append(&header, records.as_slice()).ok();
+}
+
+/// Append one `"<len> <key>=<value>\n"` PAX record, where `<len>` counts the whole record
+fn pax_record(out: &mut Vec<u8>, key: &str, value: &[u8]) {
+    let rest = key.len() + value.len() + 3; // ' ', '=' and '\n'
+    let mut len = rest + 1;
+    while len != rest + len.to_string().len() {
+        len = rest + len.to_string().len();
+    }
+    out.extend_from_slice(format!("{len} {key}=").as_bytes());
+    out.extend_from_slice(value);
+    out.push(b'\n');
+}
+
+/// Hashes file contents as the tar builder streams them
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotInfo {
+    /// Snapshot name, without the `.tar.zst` extension
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub created: std::time::SystemTime,
+    /// Profile the snapshot belongs to, when the name follows ELM's conventions
+    pub profile: Option<String>,
+    /// Base snapshot file name, for incremental snapshots
+    pub base: Option<String>,
+}
+
+/// All snapshots in `snapshots_dir`, oldest first
+pub fn list_snapshots(snapshots_dir: &Path) -> Result<Vec<SnapshotInfo>> {
+    if !snapshots_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(snapshots_dir).with_context(|| format!("reading {}", snapshots_dir.display()))?.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(name) = file_name.strip_suffix(".tar.zst") else { continue };
+        let Ok(meta) = entry.metadata() else { continue };
+
+        snapshots.push(SnapshotInfo {
+            name: name.to_string(),
+            size: meta.len(),
+            created: meta.created().or_else(|_| meta.modified()).unwrap_or(std::time::UNIX_EPOCH),
+            profile: profile_from_name(name),
+            base: read_index(&path).ok().flatten().and_then(|i| i.base),
+            path,
+        });
+    }
+    snapshots.sort_by_key(|s| s.created);
+    Ok(snapshots)
+}
+
+/// Profile name from ELM's snapshot naming: `eve-<profile>-pre-update-<ts>` or `<profile>-backup`
+pub fn profile_from_name(name: &str) -> Option<String> {
+    if let Some((prefix, _)) = name.rsplit_once("-pre-update-") {
+        return Some(prefix.strip_prefix("eve-").unwrap_or(prefix).to_string());
+    }
+    name.strip_suffix("-backup").map(|p| p.to_string())
+}
+
+/// Snapshots that belong to `profile`, oldest first: by ELM's naming, or the
+/// profile recorded in their metadata for snapshots named otherwise. Matches
+/// the whole name, so `alt` never picks up `alt-pvp`'s snapshots.
+pub fn snapshots_for_profile(snapshots_dir: &Path, profile: &str) -> Result<Vec<SnapshotInfo>> {
+    Ok(list_snapshots(snapshots_dir)?
+        .into_iter()
+        .filter(|s| {
+            let owner = s
+                .profile
+                .clone()
+                .or_else(|| read_metadata(&s.path).ok().flatten().and_then(|m| m.profile));
+            owner.as_deref() == Some(profile)
+        })
+        .collect())
+}
+
+/// Remove a snapshot archive and its index
+pub fn delete_snapshot(snapshot: &Path) -> Result<()> {
+    fs::remove_file(snapshot).with_context(|| format!("remove {}", snapshot.display()))?;
+    let index = index_path(snapshot);
+    if index.exists() {
+        fs::remove_file(&index).with_context(|| format!("remove {}", index.display()))?;
+    }
+    Ok(())
+}
+
+/// Snapshots to remove so at most `keep` remain per profile, oldest first.
+/// Snapshots that are the base of a kept incremental snapshot are never selected.
+pub fn prune_candidates(snapshots: &[SnapshotInfo], keep: usize) -> Vec<SnapshotInfo> {
+    let mut by_profile: BTreeMap<Option<String>, Vec<&SnapshotInfo>> = BTreeMap::new();
+    for s in snapshots {
+        by_profile.entry(s.profile.clone()).or_default().push(s);
+    }
+
+    let mut doomed: Vec<SnapshotInfo> = Vec::new();
+    for group in by_profile.values() {
+        let excess = group.len().saturating_sub(keep);
+        doomed.extend(group.iter().take(excess).map(|s| (*s).clone()));
+    }
+
+    // Keep any base still needed by a surviving snapshot (and that base's own base)
+    loop {
+        let needed: HashSet<String> = snapshots
+            .iter()
+            .filter(|s| !doomed.iter().any(|d| d.path == s.path))
+            .filter_map(|s| s.base.clone())
+            .collect();
+        let before = doomed.len();
+        doomed.retain(|d| !needed.contains(&format!("{}.tar.zst", d.name)));
+        if doomed.len() == before {
+            break;
+        }
+    }
+    doomed.sort_by_key(|s| s.created);
+    doomed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_names_match_whole() {
+        assert_eq!(profile_from_name("eve-alt-pre-update-1700000000").as_deref(), Some("alt"));
+        assert_eq!(profile_from_name("eve-alt-pvp-pre-update-1700000000").as_deref(), Some("alt-pvp"));
+        assert_eq!(profile_from_name("alt-pvp-backup").as_deref(), Some("alt-pvp"));
+        assert_eq!(profile_from_name("before-patch"), None);
+    }
+
+    #[test]
+    fn deleting_a_profile_keeps_similarly_named_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = [
+            "eve-alt-pre-update-1700000000",
+            "alt-backup",
+            "eve-alt-pvp-pre-update-1700000000",
+            "alt-pvp-backup",
+        ];
+        for name in names {
+            fs::write(dir.path().join(format!("{name}.tar.zst")), "").unwrap();
+            fs::write(dir.path().join(format!("{name}.index.json")), "{}").unwrap();
+        }
+
+        let mut doomed: Vec<String> =
+            snapshots_for_profile(dir.path(), "alt").unwrap().into_iter().map(|s| s.name).collect();
+        doomed.sort();
+        assert_eq!(doomed, ["alt-backup", "eve-alt-pre-update-1700000000"]);
+        for name in &doomed {
+            delete_snapshot(&dir.path().join(format!("{name}.tar.zst"))).unwrap();
+        }
+
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "alt-pvp-backup.index.json",
+                "alt-pvp-backup.tar.zst",
+                "eve-alt-pvp-pre-update-1700000000.index.json",
+                "eve-alt-pvp-pre-update-1700000000.tar.zst",
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_keeps_modes_and_mtimes() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("eve-main");
+        let bin = prefix.join("pfx/drive_c/tools");
+        fs::create_dir_all(&bin).unwrap();
+        let script = bin.join("run.sh");
+        let secret = prefix.join("pfx/user.reg");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::write(&secret, "[Software]\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+        fs::set_permissions(&secret, fs::Permissions::from_mode(0o600)).unwrap();
+        fs::set_permissions(&bin, fs::Permissions::from_mode(0o700)).unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        for file in [&script, &secret] {
+            File::options().write(true).open(file).unwrap().set_modified(mtime).unwrap();
+        }
+
+        let snapshots = dir.path().join("snapshots");
+        let metadata = SnapshotMetadata::new(&prefix, None, None);
+        let snapshot =
+            snapshot_prefix(&prefix, &snapshots, "eve-main-backup", &metadata, &SnapshotOptions::default(), |_| {})
+                .unwrap();
+        fs::remove_dir_all(&prefix).unwrap();
+        crate::rollback::restore::restore_prefix(&snapshot, &prefix, None).unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode(&script), 0o750);
+        assert_eq!(mode(&secret), 0o600);
+        assert_eq!(mode(&bin), 0o700);
+        for file in [&script, &secret] {
+            assert_eq!(fs::metadata(file).unwrap().modified().unwrap(), mtime, "{}", file.display());
+        }
+    }
+
+    #[test]
+    fn hardlinks_are_archived_once_and_restored_as_links() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("eve-main");
+        let system32 = prefix.join("pfx/drive_c/windows/system32");
+        fs::create_dir_all(&system32).unwrap();
+        let original = system32.join("d3d11.dll");
+        let linked = system32.join("dxgi.dll");
+        fs::write(&original, "dll bytes").unwrap();
+        fs::hard_link(&original, &linked).unwrap();
+
+        let snapshots = dir.path().join("snapshots");
+        let metadata = SnapshotMetadata::new(&prefix, None, None);
+        let snapshot =
+            snapshot_prefix(&prefix, &snapshots, "eve-main-backup", &metadata, &SnapshotOptions::default(), |_| {})
+                .unwrap();
+
+        let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(&snapshot).unwrap()).unwrap());
+        let mut types: Vec<tar::EntryType> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap())
+            .filter(|e| e.path().unwrap().extension().is_some_and(|ext| ext == "dll"))
+            .map(|e| e.header().entry_type())
+            .collect();
+        types.sort_by_key(|t| t.as_byte());
+        assert_eq!(types, [tar::EntryType::Regular, tar::EntryType::Link]);
+
+        fs::remove_dir_all(&prefix).unwrap();
+        crate::rollback::restore::restore_prefix(&snapshot, &prefix, None).unwrap();
+        assert_eq!(fs::read_to_string(&linked).unwrap(), "dll bytes");
+        assert_eq!(fs::metadata(&original).unwrap().ino(), fs::metadata(&linked).unwrap().ino());
+    }
+}
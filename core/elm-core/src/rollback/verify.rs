@@ -0,0 +1,65 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::snapshot::read_index;
+
+/// A snapshot archive that can't be fully read back. Restores check for this
+/// before touching the target prefix, so seeing it means the prefix is untouched.
+#[derive(Debug)]
+pub struct CorruptSnapshot {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for CorruptSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "snapshot {} is corrupt: {}", self.path.display(), self.reason)
+    }
+}
+
+impl std::error::Error for CorruptSnapshot {}
+
+/// Read every entry of a snapshot without extracting it, checking file contents
+/// against the snapshot index when there is one.
+pub fn verify_snapshot(snapshot: &Path) -> Result<()> {
+    let corrupt = |reason: String| CorruptSnapshot { path: snapshot.to_path_buf(), reason };
+
+    let index = read_index(snapshot).map_err(|e| corrupt(format!("unreadable index: {e}")))?;
+    let f = File::open(snapshot).map_err(|e| corrupt(e.to_string()))?;
+    let decoder = zstd::Decoder::new(f).map_err(|e| corrupt(format!("zstd: {e}")))?;
+    let mut archive = tar::Archive::new(decoder);
+
+    let entries = archive.entries().map_err(|e| corrupt(e.to_string()))?;
+    let mut buf = [0u8; 1024 * 128];
+    for entry in entries {
+        let mut entry = entry.map_err(|e| corrupt(e.to_string()))?;
+        let path = entry.path().map_err(|e| corrupt(e.to_string()))?.to_path_buf();
+
+        // Hardlinks carry no data of their own; their target's entry is checked instead
+        let is_link = entry.header().entry_type() == tar::EntryType::Link;
+        let expected = index.as_ref().filter(|_| !is_link).and_then(|i| {
+            let rel = path.strip_prefix("prefix").ok()?;
+            i.entries.get(rel.to_string_lossy().as_ref())?.sha256.clone()
+        });
+
+        // Reading the body is what proves the archive isn't truncated
+        let mut hasher = Sha256::new();
+        loop {
+            let n = entry.read(&mut buf).map_err(|e| corrupt(format!("{}: {e}", path.display())))?;
+            if n == 0 { break; }
+            hasher.update(&buf[..n]);
+        }
+
+        if let Some(expected) = expected {
+            let got = hex::encode(hasher.finalize());
+            if got != expected {
+                return Err(corrupt(format!("checksum mismatch for {}", path.display())).into());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+/// The sources of a launch's environment. [`resolve`] applies them in field
+/// order, so a later layer wins over an earlier one for the same variable.
+#[derive(Debug, Clone, Default)]
+pub struct EnvLayers {
+    /// The manifest's `env.base` (or ELM's defaults) and component toggles
+    pub base: HashMap<String, String>,
+    /// A preset picked for this launch only (`elm run --preset`)
+    pub preset: HashMap<String, String>,
+    /// The profile's `overrides.env.base`
+    pub profile: HashMap<String, String>,
+    /// `--env KEY=VALUE` flags
+    pub cli: HashMap<String, String>,
+    /// Set by ELM for flags such as `--dx12`, `--hud` and `--gpu`
+    pub internal: HashMap<String, String>,
+}
+
+/// The final launch environment: manifest base < preset < profile overrides
+/// < `--env` flags < ELM's own flag-driven variables
+pub fn resolve(layers: &EnvLayers) -> HashMap<String, String> {
+    let mut env = HashMap::new();
+    for layer in [&layers.base, &layers.preset, &layers.profile, &layers.cli, &layers.internal] {
+        env.extend(layer.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    env
+}
+
+/// Parse a `KEY=VALUE` assignment; the value may be empty or contain `=`
+pub fn parse_assignment(s: &str) -> Result<(String, String)> {
+    let (key, value) = s.split_once('=').ok_or_else(|| anyhow!("expected KEY=VALUE, got '{s}'"))?;
+    let valid = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(anyhow!("invalid environment variable name '{key}'"));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// A suspicious variable combination found by [`lint`]
+#[derive(Debug, Clone)]
+pub struct EnvWarning {
+    /// The variables involved
+    pub vars: Vec<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for EnvWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.vars.join(" + "), self.message)
+    }
+}
+
+/// Variables Proton stopped reading, and what replaced them
+const OBSOLETE: &[(&str, &str)] = &[
+    ("PROTON_USE_D9VK", "DXVK has handled D3D9 by default since Proton 5.0"),
+    ("PROTON_NO_D3D10", "Proton no longer has a separate D3D10 toggle"),
+    ("PROTON_USE_WINED3D11", "renamed to PROTON_USE_WINED3D"),
+];
+
+/// Check a resolved launch environment for combinations that don't do what
+/// they look like they do. `engine` is the engine id (e.g. `ge-proton-10-26`),
+/// for rules that depend on the Proton build. Nothing here stops a launch.
+pub fn lint(env: &HashMap<String, String>, engine: &str) -> Vec<EnvWarning> {
+    let on = |k: &str| env.get(k).is_some_and(|v| !v.is_empty() && v != "0");
+    let set = |k: &str| env.contains_key(k);
+    let mut warnings = Vec::new();
+    let mut warn = |vars: &[&str], message: &str| {
+        warnings.push(EnvWarning {
+            vars: vars.iter().map(|v| v.to_string()).collect(),
+            message: message.to_string(),
+        });
+    };
+
+    if on("DXVK_ASYNC") && !engine.to_ascii_lowercase().starts_with("ge-") {
+        warn(
+            &["DXVK_ASYNC"],
+            "ignored: only DXVK builds carrying the async patch (GE-Proton) read it; upstream DXVK 2.0+ compiles pipelines early instead",
+        );
+    }
+    if on("PROTON_USE_WINED3D") {
+        let mut dxvk: Vec<&str> = env
+            .iter()
+            .filter(|(k, v)| k.starts_with("DXVK_") && !v.is_empty())
+            .map(|(k, _)| k.as_str())
+            .collect();
+        if !dxvk.is_empty() {
+            dxvk.sort();
+            dxvk.insert(0, "PROTON_USE_WINED3D");
+            warn(&dxvk, "WineD3D replaces DXVK, so the DXVK settings have no effect");
+        }
+        if set("VKD3D_FEATURE_LEVEL") {
+            warn(
+                &["PROTON_USE_WINED3D", "VKD3D_FEATURE_LEVEL"],
+                "WineD3D is a fallback for broken Vulkan, but VKD3D_FEATURE_LEVEL asks for DX12 over Vulkan; one of them is probably a leftover",
+            );
+        }
+        if on("PROTON_ENABLE_NVAPI") {
+            warn(&["PROTON_USE_WINED3D", "PROTON_ENABLE_NVAPI"], "NVAPI needs DXVK and is off under WineD3D");
+        }
+    }
+    if on("PROTON_NO_D3D12") && set("VKD3D_FEATURE_LEVEL") {
+        warn(&["PROTON_NO_D3D12", "VKD3D_FEATURE_LEVEL"], "D3D12 is disabled, so the VKD3D feature level is unused");
+    }
+    if set("WINE_FULLSCREEN_FSR_STRENGTH") && !on("WINE_FULLSCREEN_FSR") {
+        warn(&["WINE_FULLSCREEN_FSR_STRENGTH"], "has no effect without WINE_FULLSCREEN_FSR=1");
+    }
+    if set("MANGOHUD_CONFIG") && !on("MANGOHUD") {
+        warn(&["MANGOHUD_CONFIG"], "has no effect without MANGOHUD=1 (or --hud)");
+    }
+    for (var, why) in OBSOLETE {
+        if set(var) {
+            warn(&[*var], &format!("no longer used: {why}"));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let layers = EnvLayers {
+            base: layer(&[("A", "base"), ("B", "base"), ("C", "base"), ("D", "base"), ("E", "base")]),
+            preset: layer(&[("B", "preset"), ("C", "preset"), ("D", "preset"), ("E", "preset")]),
+            profile: layer(&[("C", "profile"), ("D", "profile"), ("E", "profile")]),
+            cli: layer(&[("D", "cli"), ("E", "cli")]),
+            internal: layer(&[("E", "internal")]),
+        };
+        assert_eq!(
+            resolve(&layers),
+            layer(&[("A", "base"), ("B", "preset"), ("C", "profile"), ("D", "cli"), ("E", "internal")])
+        );
+    }
+
+    #[test]
+    fn parses_assignments() {
+        let parsed = |s: &str| parse_assignment(s).ok();
+        assert_eq!(parsed("DXVK_HUD=fps"), Some(("DXVK_HUD".to_string(), "fps".to_string())));
+        assert_eq!(parsed("KEY="), Some(("KEY".to_string(), String::new())));
+        assert_eq!(parsed("OPTS=a=b"), Some(("OPTS".to_string(), "a=b".to_string())));
+        assert_eq!(parsed("_X1=y"), Some(("_X1".to_string(), "y".to_string())));
+        for bad in ["1KEY=x", "=x", "KEY", "MY-KEY=x", "KEY NAME=x"] {
+            assert_eq!(parsed(bad), None, "{bad}");
+        }
+    }
+}
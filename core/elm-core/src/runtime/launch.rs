@@ -1,21 +1,94 @@
 use anyhow::{anyhow, Context, Result};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::process::Command;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fmt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tracing::{debug, info, warn};
 
+use crate::engine::runner::Runner;
+use crate::error::ElmError;
+use crate::integration::steam;
+
+#[derive(Debug, Clone)]
 pub struct LaunchSpec {
+    /// Engine root: a Proton build, or a plain Wine build (see [`Runner`])
     pub proton_root: PathBuf,
     pub prefix_dir: PathBuf,
     pub exe_path_in_prefix: PathBuf, // relative to pfx/, e.g. drive_c/.../evelauncher.exe
     pub args: Vec<String>,
     pub env: HashMap<String, String>,
+    /// Capture Proton's stdout/stderr to this file (still echoed to the terminal
+    /// for foreground launches)
+    pub log_path: Option<PathBuf>,
+    /// Steam Linux Runtime `_v2-entry-point` to run Proton inside, as Steam
+    /// does (see [`super::steam_runtime::resolve`]); `None` runs bare proton
+    pub steam_runtime: Option<PathBuf>,
 }
 
-pub async fn launch(spec: LaunchSpec) -> Result<()> {
-    let proton = spec.proton_root.join("proton");
-    if !proton.exists() {
-        return Err(anyhow!("proton not found: {}", proton.display()));
+/// A fully resolved Proton invocation, to run or to show the user
+#[derive(Debug, Clone)]
+pub struct ProtonCommand {
+    pub program: OsString,
+    pub args: Vec<OsString>,
+    /// Set on top of the inherited environment
+    pub env: Vec<(String, OsString)>,
+}
+
+impl ProtonCommand {
+    /// The command ready to spawn, for callers that run it themselves
+    pub fn std_command(&self) -> std::process::Command {
+        let mut cmd = std::process::Command::new(&self.program);
+        cmd.args(&self.args);
+        for (k, v) in &self.env {
+            cmd.env(k, v);
+        }
+        cmd
+    }
+}
+
+/// One line that can be pasted into a shell: `KEY=value ... python3 <proton> run <exe> <args>`,
+/// or `KEY=value ... <wine> <exe> <args>` for a Wine engine
+impl fmt::Display for ProtonCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (k, v) in &self.env {
+            write!(f, "{}={} ", k, shell_quote(&v.to_string_lossy()))?;
+        }
+        write!(f, "{}", shell_quote(&self.program.to_string_lossy()))?;
+        for a in &self.args {
+            write!(f, " {}", shell_quote(&a.to_string_lossy()))?;
+        }
+        Ok(())
     }
+}
+
+/// `s` as a single shell word, single-quoted only when it needs to be
+fn shell_quote(s: &str) -> String {
+    let plain = !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "_-+=:,./@%".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// Resolve the command that runs `spec` through its engine, checking that the
+/// engine and the exe exist. Variables are listed Steam's (or `WINEPREFIX`)
+/// first, then `spec.env` sorted.
+pub fn build_command(spec: &LaunchSpec) -> Result<ProtonCommand> {
+    build_command_with(spec, steam::steam_root)
+}
+
+/// [`build_command`] with the Steam install supplied by `steam_root` instead of
+/// looked up, so the command can be built and inspected on a machine without
+/// Steam (e.g. against a stub `proton` script). Only Proton engines call it.
+pub fn build_command_with(spec: &LaunchSpec, steam_root: impl FnOnce() -> Result<PathBuf>) -> Result<ProtonCommand> {
+    let runner = Runner::detect(&spec.proton_root)?;
 
     // Proton uses pfx/ subdirectory for the actual Wine prefix
     let exe_abs = spec.prefix_dir.join("pfx").join(&spec.exe_path_in_prefix);
@@ -23,65 +96,436 @@ pub async fn launch(spec: LaunchSpec) -> Result<()> {
         return Err(anyhow!("exe not found: {}", exe_abs.display()));
     }
 
-    // Find Steam installation path
-    let home = std::env::var("HOME").unwrap_or_default();
-    let steam_path = format!("{home}/.steam/steam");
+    if runner == Runner::Wine {
+        if spec.steam_runtime.is_some() {
+            warn!("the Steam Linux Runtime only wraps Proton; running {} directly", spec.proton_root.display());
+        }
+        let mut env = vec![("WINEPREFIX".to_string(), spec.prefix_dir.join("pfx").into_os_string())];
+        env.extend(spec.env.iter().map(|(k, v)| (k.clone(), OsString::from(v))).collect::<BTreeMap<_, _>>());
+        let mut args = vec![exe_abs.into_os_string()];
+        args.extend(spec.args.iter().map(OsString::from));
+        return Ok(ProtonCommand { program: spec.proton_root.join("bin/wine").into_os_string(), args, env });
+    }
 
-    let mut cmd = Command::new("python3");
-    cmd.arg(&proton);
-    cmd.env("STEAM_COMPAT_DATA_PATH", &spec.prefix_dir);
-    cmd.env("STEAM_COMPAT_CLIENT_INSTALL_PATH", &steam_path);
+    let proton = spec.proton_root.join("proton");
+    let steam_path = steam_root()?;
+
+    let mut env = vec![
+        ("STEAM_COMPAT_DATA_PATH".to_string(), spec.prefix_dir.clone().into_os_string()),
+        ("STEAM_COMPAT_CLIENT_INSTALL_PATH".to_string(), steam_path.into_os_string()),
+    ];
+    let mut sorted: BTreeMap<_, _> = spec.env.iter().map(|(k, v)| (k.clone(), OsString::from(v))).collect();
 
-    for (k, v) in &spec.env {
-        cmd.env(k, v);
+    let Some(entry_point) = &spec.steam_runtime else {
+        env.extend(sorted);
+        let mut args = vec![proton.into_os_string(), "run".into(), exe_abs.into_os_string()];
+        args.extend(spec.args.iter().map(OsString::from));
+        return Ok(ProtonCommand { program: "python3".into(), args, env });
+    };
+
+    // The container only sees $HOME and what it's told to mount: Proton itself
+    // and the prefix, plus any mounts the manifest asks for
+    let mut mounts = OsString::from(&spec.proton_root);
+    mounts.push(":");
+    mounts.push(&spec.prefix_dir);
+    if let Some(extra) = sorted.remove("STEAM_COMPAT_MOUNTS").filter(|m| !m.is_empty()) {
+        mounts.push(":");
+        mounts.push(extra);
     }
+    env.push(("STEAM_COMPAT_TOOL_PATHS".to_string(), spec.proton_root.clone().into_os_string()));
+    env.push(("STEAM_COMPAT_MOUNTS".to_string(), mounts));
+    env.extend(sorted);
 
-    cmd.arg("run").arg(exe_abs);
+    // Same shape as Steam's own launches: <entry point> --verb=... -- <proton> <verb> <exe>
+    let mut args = vec![
+        "--verb=waitforexitandrun".into(),
+        "--".into(),
+        proton.into_os_string(),
+        "waitforexitandrun".into(),
+        exe_abs.into_os_string(),
+    ];
+    args.extend(spec.args.iter().map(OsString::from));
+    Ok(ProtonCommand { program: entry_point.clone().into_os_string(), args, env })
+}
 
-    for a in &spec.args {
-        cmd.arg(a);
+fn open_log(path: &Path) -> Result<std::fs::File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
     }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))
+}
 
-    let status = cmd.status().await.context("launch proton run")?;
-    if !status.success() {
-        return Err(anyhow!("launch failed with status: {status}"));
+/// Copy `src` to both the terminal stream and the log file as it arrives
+async fn tee(mut src: impl AsyncRead + Unpin, mut echo: impl AsyncWrite + Unpin, log: std::fs::File) -> std::io::Result<()> {
+    let mut log = tokio::fs::File::from_std(log);
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = src.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        // A closed terminal shouldn't stop the log capture
+        let _ = echo.write_all(&buf[..n]).await;
+        let _ = echo.flush().await;
+        log.write_all(&buf[..n]).await?;
     }
-    Ok(())
+    log.flush().await
 }
 
-/// Launch EVE in background (for multiboxing) - spawns process and returns immediately
-pub fn launch_background(spec: LaunchSpec) -> Result<()> {
-    let proton = spec.proton_root.join("proton");
-    if !proton.exists() {
-        return Err(anyhow!("proton not found: {}", proton.display()));
+/// How long Proton gets to shut Wine down after a forwarded Ctrl-C before
+/// the whole process group is killed
+pub const CANCEL_GRACE: Duration = Duration::from_secs(10);
+
+/// SIGINT/SIGTERM, caught so they go to Proton's process group instead of
+/// just killing ELM and orphaning Wine
+struct Signals {
+    interrupt: Signal,
+    terminate: Signal,
+}
+
+impl Signals {
+    fn new() -> Result<Self> {
+        Ok(Signals {
+            interrupt: signal(SignalKind::interrupt()).context("install SIGINT handler")?,
+            terminate: signal(SignalKind::terminate()).context("install SIGTERM handler")?,
+        })
     }
 
-    // Proton uses pfx/ subdirectory for the actual Wine prefix
-    let exe_abs = spec.prefix_dir.join("pfx").join(&spec.exe_path_in_prefix);
-    if !exe_abs.exists() {
-        return Err(anyhow!("exe not found: {}", exe_abs.display()));
+    async fn recv(&mut self) -> libc::c_int {
+        tokio::select! {
+            _ = self.interrupt.recv() => libc::SIGINT,
+            _ = self.terminate.recv() => libc::SIGTERM,
+        }
     }
 
-    // Find Steam installation path
-    let home = std::env::var("HOME").unwrap_or_default();
-    let steam_path = format!("{home}/.steam/steam");
+    /// Wait for `child`, the leader of its own process group. The first signal
+    /// is passed on to the group and Proton gets [`CANCEL_GRACE`] to exit; a
+    /// second signal or the grace running out kills the group and the prefix's
+    /// wineserver. Returns whether a signal was forwarded.
+    async fn wait(&mut self, child: &mut Child, spec: &LaunchSpec) -> Result<(ExitStatus, bool)> {
+        let Some(pgid) = child.id() else {
+            return Ok((child.wait().await.context("wait for proton run")?, false));
+        };
+        let pgid = pgid as libc::pid_t;
 
-    let mut cmd = std::process::Command::new("python3");
-    cmd.arg(&proton);
-    cmd.env("STEAM_COMPAT_DATA_PATH", &spec.prefix_dir);
-    cmd.env("STEAM_COMPAT_CLIENT_INSTALL_PATH", &steam_path);
+        let sig = tokio::select! {
+            status = child.wait() => return Ok((status.context("wait for proton run")?, false)),
+            sig = self.recv() => sig,
+        };
+        warn!(
+            "stopping EVE; waiting up to {}s for it to exit (interrupt again to kill it)",
+            CANCEL_GRACE.as_secs()
+        );
+        kill_group(pgid, sig);
 
-    for (k, v) in &spec.env {
-        cmd.env(k, v);
+        let status = tokio::select! {
+            status = child.wait() => status,
+            _ = self.recv() => kill_hard(child, pgid, spec).await,
+            _ = tokio::time::sleep(CANCEL_GRACE) => kill_hard(child, pgid, spec).await,
+        };
+        Ok((status.context("wait for proton run")?, true))
     }
+}
 
-    cmd.arg("run").arg(exe_abs);
+fn kill_group(pgid: libc::pid_t, sig: libc::c_int) {
+    // SAFETY: killpg has no memory-safety preconditions; a group that has
+    // already exited just gives ESRCH
+    unsafe {
+        libc::killpg(pgid, sig);
+    }
+}
+
+/// SIGKILL the launch's process group and whatever Wine left running in the prefix
+async fn kill_hard(child: &mut Child, pgid: libc::pid_t, spec: &LaunchSpec) -> std::io::Result<ExitStatus> {
+    warn!("EVE did not exit, killing it");
+    kill_group(pgid, libc::SIGKILL);
+    let status = child.wait().await;
+    // wineserver may have detached from the group
+    let _ = crate::prefix::shutdown(&spec.prefix_dir, &spec.proton_root).await;
+    status
+}
+
+/// How a foreground launch ended
+#[derive(Debug, Clone, Copy)]
+pub struct LaunchOutcome {
+    /// Exit code, if the process exited normally
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    /// Terminating signal, if the process was killed
+    pub signal: Option<i32>,
+    /// The user interrupted the launch (Ctrl-C or SIGTERM to ELM)
+    pub cancelled: bool,
+}
+
+impl LaunchOutcome {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
 
-    for a in &spec.args {
-        cmd.arg(a);
+    /// The outcome as a shell would report it: the exit code, 128 + the
+    /// signal for a killed process, 130 when cancelled, 1 if unknown
+    pub fn exit_status(&self) -> i32 {
+        if self.cancelled {
+            return 130;
+        }
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => code,
+            (None, Some(sig)) => 128 + sig,
+            (None, None) => 1,
+        }
+    }
+
+    /// Turn an unsuccessful exit into an error, as `launch` used to
+    pub fn into_result(self) -> crate::error::Result<Self> {
+        if self.cancelled {
+            return Err(ElmError::LaunchCancelled);
+        }
+        if self.success() {
+            return Ok(self);
+        }
+        Err(ElmError::LaunchFailed { exit_code: self.exit_code, signal: self.signal })
+    }
+}
+
+/// Run EVE through Proton and wait for it. A non-zero exit is reported in the
+/// outcome, not as an error; errors mean Proton couldn't be started at all.
+/// Ctrl-C is forwarded to the whole Wine process tree (see [`CANCEL_GRACE`]).
+pub async fn launch(spec: LaunchSpec) -> crate::error::Result<LaunchOutcome> {
+    Ok(run(spec).await?)
+}
+
+async fn run(spec: LaunchSpec) -> Result<LaunchOutcome> {
+    let proton_cmd = build_command(&spec)?;
+    info!("launching {} in {}", spec.exe_path_in_prefix.display(), spec.prefix_dir.display());
+    debug!("proton command: {proton_cmd}");
+    let mut std_cmd = proton_cmd.std_command();
+    // Own process group, so a forwarded signal reaches Wine and EVE and not
+    // just Proton's python wrapper. Out of the terminal's foreground group,
+    // reading the terminal would stop it, so it gets no stdin.
+    std_cmd.process_group(0).stdin(Stdio::null());
+    let mut cmd = Command::from(std_cmd);
+    let mut signals = Signals::new()?;
+
+    let start = Instant::now();
+    let (status, cancelled) = match &spec.log_path {
+        Some(log_path) => {
+            // Both handles append, so stdout and stderr interleave in arrival order
+            let log = open_log(log_path)?;
+            let log_err = log.try_clone()?;
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+            let mut child = cmd.spawn().context("launch proton run")?;
+            let stdout = child.stdout.take().expect("stdout is piped");
+            let stderr = child.stderr.take().expect("stderr is piped");
+            let out_task = tokio::spawn(tee(stdout, tokio::io::stdout(), log));
+            let err_task = tokio::spawn(tee(stderr, tokio::io::stderr(), log_err));
+            let waited = signals.wait(&mut child, &spec).await?;
+            for task in [out_task, err_task] {
+                if let Ok(Err(e)) = task.await {
+                    warn!("launch log capture failed: {e}");
+                }
+            }
+            waited
+        }
+        None => {
+            let mut child = cmd.spawn().context("launch proton run")?;
+            signals.wait(&mut child, &spec).await?
+        }
+    };
+    Ok(LaunchOutcome {
+        exit_code: status.code(),
+        duration: start.elapsed(),
+        signal: status.signal(),
+        cancelled,
+    })
+}
+
+/// Launch EVE in background (for multiboxing) - spawns process and returns its PID immediately
+pub fn launch_background(spec: LaunchSpec) -> Result<u32> {
+    let proton_cmd = build_command(&spec)?;
+    info!("launching {} in {} (background)", spec.exe_path_in_prefix.display(), spec.prefix_dir.display());
+    debug!("proton command: {proton_cmd}");
+    let mut cmd = proton_cmd.std_command();
+
+    // Nobody is left to echo a background launch's output, so it goes straight to the log
+    if let Some(log_path) = &spec.log_path {
+        let log = open_log(log_path)?;
+        cmd.stderr(log.try_clone()?);
+        cmd.stdout(log);
     }
 
     // Spawn without waiting - process runs independently
-    cmd.spawn().context("spawn proton run")?;
-    Ok(())
+    let child = cmd.spawn().context("spawn proton run")?;
+    Ok(child.id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXE: &str = "drive_c/CCP/EVE/evelauncher.exe";
+
+    /// An engine root with a stub `proton` (or `bin/wine`) and a prefix holding the launcher
+    fn fixture(runner: Runner) -> (tempfile::TempDir, LaunchSpec) {
+        let dir = tempfile::tempdir().unwrap();
+        let proton_root = dir.path().join("engine");
+        let stub = match runner {
+            Runner::Proton => proton_root.join("proton"),
+            Runner::Wine => proton_root.join("bin/wine"),
+        };
+        std::fs::create_dir_all(stub.parent().unwrap()).unwrap();
+        std::fs::write(&stub, "#!/bin/sh\n").unwrap();
+        let prefix_dir = dir.path().join("prefix");
+        let exe = prefix_dir.join("pfx").join(EXE);
+        std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        std::fs::write(&exe, "").unwrap();
+        let spec = LaunchSpec {
+            proton_root,
+            prefix_dir,
+            exe_path_in_prefix: PathBuf::from(EXE),
+            args: vec!["--tq".to_string()],
+            env: HashMap::from([("DXVK_HUD".to_string(), "fps".to_string())]),
+            log_path: None,
+            steam_runtime: None,
+        };
+        (dir, spec)
+    }
+
+    fn steam() -> Result<PathBuf> {
+        Ok(PathBuf::from("/steam"))
+    }
+
+    fn os(parts: &[&Path]) -> Vec<OsString> {
+        parts.iter().map(|p| p.as_os_str().to_os_string()).collect()
+    }
+
+    fn env_of(cmd: &ProtonCommand) -> Vec<(&str, PathBuf)> {
+        cmd.env.iter().map(|(k, v)| (k.as_str(), PathBuf::from(v))).collect()
+    }
+
+    #[test]
+    fn proton_command() {
+        let (_dir, spec) = fixture(Runner::Proton);
+        let cmd = build_command_with(&spec, steam).unwrap();
+        assert_eq!(cmd.program, "python3");
+        let exe = spec.prefix_dir.join("pfx").join(EXE);
+        assert_eq!(
+            cmd.args,
+            os(&[&spec.proton_root.join("proton"), Path::new("run"), &exe, Path::new("--tq")])
+        );
+        assert_eq!(
+            env_of(&cmd),
+            vec![
+                ("STEAM_COMPAT_DATA_PATH", spec.prefix_dir.clone()),
+                ("STEAM_COMPAT_CLIENT_INSTALL_PATH", PathBuf::from("/steam")),
+                ("DXVK_HUD", PathBuf::from("fps")),
+            ]
+        );
+    }
+
+    #[test]
+    fn wine_command() {
+        let (_dir, mut spec) = fixture(Runner::Wine);
+        // Ignored with a warning: the runtime only wraps Proton
+        spec.steam_runtime = Some(PathBuf::from("/runtime/_v2-entry-point"));
+        let cmd = build_command_with(&spec, || panic!("a Wine engine doesn't need Steam")).unwrap();
+        assert_eq!(cmd.program, spec.proton_root.join("bin/wine"));
+        let exe = spec.prefix_dir.join("pfx").join(EXE);
+        assert_eq!(cmd.args, os(&[&exe, Path::new("--tq")]));
+        assert_eq!(
+            env_of(&cmd),
+            vec![("WINEPREFIX", spec.prefix_dir.join("pfx")), ("DXVK_HUD", PathBuf::from("fps"))]
+        );
+    }
+
+    #[test]
+    fn steam_runtime_command() {
+        let (_dir, mut spec) = fixture(Runner::Proton);
+        let entry_point = PathBuf::from("/runtime/_v2-entry-point");
+        spec.steam_runtime = Some(entry_point.clone());
+        spec.env.insert("STEAM_COMPAT_MOUNTS".to_string(), "/games".to_string());
+        let cmd = build_command_with(&spec, steam).unwrap();
+        assert_eq!(cmd.program, entry_point.into_os_string());
+        let exe = spec.prefix_dir.join("pfx").join(EXE);
+        assert_eq!(
+            cmd.args,
+            os(&[
+                Path::new("--verb=waitforexitandrun"),
+                Path::new("--"),
+                &spec.proton_root.join("proton"),
+                Path::new("waitforexitandrun"),
+                &exe,
+                Path::new("--tq"),
+            ])
+        );
+        let mounts = format!("{}:{}:/games", spec.proton_root.display(), spec.prefix_dir.display());
+        assert_eq!(
+            env_of(&cmd),
+            vec![
+                ("STEAM_COMPAT_DATA_PATH", spec.prefix_dir.clone()),
+                ("STEAM_COMPAT_CLIENT_INSTALL_PATH", PathBuf::from("/steam")),
+                ("STEAM_COMPAT_TOOL_PATHS", spec.proton_root.clone()),
+                ("STEAM_COMPAT_MOUNTS", PathBuf::from(mounts)),
+                ("DXVK_HUD", PathBuf::from("fps")),
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes_only_what_needs_it() {
+        assert_eq!(shell_quote("DXVK_HUD=fps,memory"), "DXVK_HUD=fps,memory");
+        assert_eq!(shell_quote("/games/EVE Online/pfx"), "'/games/EVE Online/pfx'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn display_is_a_shell_line() {
+        let cmd = ProtonCommand {
+            program: "/opt/Wine GE/bin/wine".into(),
+            args: vec!["C:\\EVE\\exefile.exe".into(), "--name=Jita's".into()],
+            env: vec![("WINEPREFIX".to_string(), "/home/p/EVE Online/pfx".into()), ("DXVK_HUD".to_string(), "1".into())],
+        };
+        assert_eq!(
+            cmd.to_string(),
+            r"WINEPREFIX='/home/p/EVE Online/pfx' DXVK_HUD=1 '/opt/Wine GE/bin/wine' 'C:\EVE\exefile.exe' '--name=Jita'\''s'"
+        );
+    }
+
+    #[test]
+    fn env_is_engine_first_then_sorted() {
+        let (_dir, mut spec) = fixture(Runner::Proton);
+        // Each map is seeded differently, so iteration order varies between them
+        for _ in 0..4 {
+            spec.env = ["ZZ_LAST", "DXVK_HUD", "MANGOHUD", "AA_FIRST"]
+                .into_iter()
+                .map(|k| (k.to_string(), "1".to_string()))
+                .collect();
+            let cmd = build_command_with(&spec, steam).unwrap();
+            let keys: Vec<&str> = cmd.env.iter().map(|(k, _)| k.as_str()).collect();
+            assert_eq!(
+                keys,
+                [
+                    "STEAM_COMPAT_DATA_PATH",
+                    "STEAM_COMPAT_CLIENT_INSTALL_PATH",
+                    "AA_FIRST",
+                    "DXVK_HUD",
+                    "MANGOHUD",
+                    "ZZ_LAST"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn missing_exe() {
+        let (_dir, mut spec) = fixture(Runner::Proton);
+        spec.exe_path_in_prefix = PathBuf::from("drive_c/missing.exe");
+        let err = build_command_with(&spec, steam).unwrap_err();
+        assert!(err.to_string().starts_with("exe not found"), "{err}");
+    }
 }
@@ -1 +1,4 @@
+pub mod env;
 pub mod launch;
+pub mod session;
+pub mod steam_runtime;
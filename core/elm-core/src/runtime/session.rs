@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A background EVE client started by ELM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub pid: u32,
+    pub profile: String,
+    /// Unix seconds
+    pub started: u64,
+    pub prefix_dir: PathBuf,
+    pub proton_root: PathBuf,
+}
+
+/// `<data_dir>/sessions.json`
+pub fn sessions_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("sessions.json")
+}
+
+fn load(data_dir: &Path) -> Result<Vec<Session>> {
+    let path = sessions_path(data_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("parsing {}", path.display()))
+}
+
+fn save(data_dir: &Path, sessions: &[Session]) -> Result<()> {
+    fs::create_dir_all(data_dir)?;
+    let path = sessions_path(data_dir);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(sessions)?)
+        .with_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("replacing {}", path.display()))?;
+    Ok(())
+}
+
+/// True if `pid` is still running Proton. Checking the cmdline guards against
+/// a recycled PID belonging to some unrelated process.
+pub fn is_alive(pid: u32) -> bool {
+    fs::read(format!("/proc/{pid}/cmdline"))
+        .map(|cmdline| String::from_utf8_lossy(&cmdline).contains("proton"))
+        .unwrap_or(false)
+}
+
+/// Record a freshly spawned client
+pub fn record(data_dir: &Path, pid: u32, profile: &str, prefix_dir: &Path, proton_root: &Path) -> Result<Session> {
+    let session = Session {
+        pid,
+        profile: profile.to_string(),
+        started: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        prefix_dir: prefix_dir.to_path_buf(),
+        proton_root: proton_root.to_path_buf(),
+    };
+    let mut sessions = live_sessions(data_dir)?;
+    sessions.retain(|s| s.pid != pid);
+    sessions.push(session.clone());
+    save(data_dir, &sessions)?;
+    Ok(session)
+}
+
+/// Sessions whose process is still alive; dead entries are dropped from the file
+pub fn live_sessions(data_dir: &Path) -> Result<Vec<Session>> {
+    let sessions = load(data_dir)?;
+    let total = sessions.len();
+    let live: Vec<Session> = sessions.into_iter().filter(|s| is_alive(s.pid)).collect();
+    if live.len() != total {
+        save(data_dir, &live)?;
+    }
+    Ok(live)
+}
+
+/// Live sessions matching a PID or profile name
+pub fn find(data_dir: &Path, target: &str) -> Result<Vec<Session>> {
+    let pid = target.parse::<u32>().ok();
+    Ok(live_sessions(data_dir)?
+        .into_iter()
+        .filter(|s| Some(s.pid) == pid || s.profile == target)
+        .collect())
+}
+
+/// Terminate a session: SIGTERM the Proton process, then shut down the
+/// prefix's wineserver so the game and launcher exit with it
+pub fn stop(data_dir: &Path, session: &Session) -> Result<()> {
+    if is_alive(session.pid) {
+        let status = std::process::Command::new("kill")
+            .arg(session.pid.to_string())
+            .status()
+            .context("running kill")?;
+        if !status.success() {
+            return Err(anyhow!("kill {} failed: {}", session.pid, status));
+        }
+    }
+
+    if let Ok(bin) = crate::prefix::proton_wine_bin(&session.proton_root) {
+        let _ = std::process::Command::new(bin.join("wineserver"))
+            .arg("-k")
+            .env("WINEPREFIX", session.prefix_dir.join("pfx"))
+            .status();
+    }
+
+    let mut sessions = load(data_dir)?;
+    sessions.retain(|s| s.pid != session.pid);
+    save(data_dir, &sessions)
+}
@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::config::models::SteamRuntime;
+use crate::integration::steam;
+
+/// Script that starts a command inside a Steam Linux Runtime container
+const ENTRY_POINT: &str = "_v2-entry-point";
+
+/// Install directory of a runtime under `steamapps/common`
+fn runtime_dir(runtime: SteamRuntime) -> Option<&'static str> {
+    match runtime {
+        SteamRuntime::Sniper => Some("SteamLinuxRuntime_sniper"),
+        SteamRuntime::Soldier => Some("SteamLinuxRuntime_soldier"),
+        SteamRuntime::Off | SteamRuntime::Auto => None,
+    }
+}
+
+/// The runtime's entry point in `steam_root`'s library, if it is installed
+pub fn find_entry_point(steam_root: &Path, runtime: SteamRuntime) -> Option<PathBuf> {
+    let entry = steam_root.join("steamapps/common").join(runtime_dir(runtime)?).join(ENTRY_POINT);
+    entry.is_file().then_some(entry)
+}
+
+/// Entry point to wrap Proton in for the manifest's `runtime.steam_runtime`.
+/// `None` means bare proton: the setting is off, or the runtime isn't installed
+/// (warned about when one was asked for by name).
+pub fn resolve(setting: SteamRuntime) -> Option<PathBuf> {
+    if setting.is_off() {
+        return None;
+    }
+    let steam_root = steam::steam_root().ok()?;
+    let candidates: &[SteamRuntime] = match setting {
+        SteamRuntime::Auto => &[SteamRuntime::Sniper, SteamRuntime::Soldier],
+        _ => std::slice::from_ref(&setting),
+    };
+    let found = candidates.iter().find_map(|r| find_entry_point(&steam_root, *r));
+    match &found {
+        Some(entry) => debug!("using Steam Linux Runtime at {}", entry.display()),
+        None if setting == SteamRuntime::Auto => debug!("no Steam Linux Runtime installed; running bare proton"),
+        None => warn!(
+            "{} is not installed in {}; running bare proton (install it from Steam's library)",
+            runtime_dir(setting).unwrap_or_default(),
+            steam_root.display()
+        ),
+    }
+    found
+}
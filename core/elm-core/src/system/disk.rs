@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Free space an engine needs once unpacked
+pub const ENGINE_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// Free space the EVE launcher needs to install and download the game client
+pub const EVE_INSTALL_SPACE: u64 = 20 * 1024 * 1024 * 1024;
+
+/// Bytes available to unprivileged users on the filesystem holding `path`.
+/// `path` need not exist yet; its nearest existing ancestor is measured.
+pub fn available(path: &Path) -> Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| anyhow!("no existing directory above {}", path.display()))?;
+    let c_path = CString::new(existing.as_os_str().as_bytes())
+        .with_context(|| format!("invalid path {}", existing.display()))?;
+
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a valid NUL-terminated string and stat a valid out pointer
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("statvfs {}", existing.display()));
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Fail with a "need X, have Y" error unless `needed` bytes are free at `path`,
+/// so `what` doesn't run out of space halfway through
+pub fn ensure_space(path: &Path, needed: u64, what: &str) -> Result<()> {
+    let have = available(path)?;
+    if have < needed {
+        return Err(anyhow!(
+            "not enough disk space to {what}: need {}, have {} free at {}",
+            format_bytes(needed),
+            format_bytes(have),
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// `1.5 GB` style size for messages
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Space `path` takes up on disk: allocated blocks rather than apparent
+/// length, so sparse files count only what they use. Directories are walked
+/// without following symlinks, and a file hardlinked more than once inside
+/// counts once. This is what deleting `path` frees; use apparent sizes
+/// (`metadata().len()`) for how much there is to copy or archive.
+pub fn usage(path: &Path) -> u64 {
+    fn walk(path: &Path, seen: &mut HashSet<(u64, u64)>) -> u64 {
+        let Ok(meta) = std::fs::symlink_metadata(path) else { return 0 };
+        if !meta.is_dir() && meta.nlink() > 1 && !seen.insert((meta.dev(), meta.ino())) {
+            return 0;
+        }
+        let own = meta.blocks() * 512;
+        if !meta.is_dir() {
+            return own;
+        }
+        let children: u64 = std::fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|e| walk(&e.path(), seen)).sum())
+            .unwrap_or(0);
+        own + children
+    }
+    walk(path, &mut HashSet::new())
+}
+
+/// Leftovers of interrupted operations under `data_dir`: partial downloads
+/// (`downloads/*.part`), engine unpacks (`engines/.<id>.partial`) and restore
+/// staging dirs next to prefixes (`prefixes/eve-<name>.restoring` etc.).
+/// Only safe to delete while no install or restore is running.
+pub fn leftovers(data_dir: &Path) -> Vec<PathBuf> {
+    use crate::prefix::profile::{DIR_PREFIX, STAGING_SUFFIXES};
+
+    let matching = |dir: &str, is_leftover: &dyn Fn(&str) -> bool| -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(data_dir.join(dir)) else { return Vec::new() };
+        entries
+            .flatten()
+            .filter(|e| is_leftover(&e.file_name().to_string_lossy()))
+            .map(|e| e.path())
+            .collect()
+    };
+    let mut found = matching("downloads", &|name| name.ends_with(".part"));
+    found.extend(matching("engines", &|name| name.starts_with('.') && name.ends_with(".partial")));
+    found.extend(matching("prefixes", &|name| {
+        name.starts_with(DIR_PREFIX)
+            && name.rsplit_once('.').is_some_and(|(_, suffix)| STAGING_SUFFIXES.contains(&suffix))
+    }));
+    found.sort();
+    found
+}
@@ -0,0 +1,249 @@
+use super::disk;
+use super::filesystem;
+use super::gpu::{self, Gpu};
+use super::vulkan::{self, VulkanInfo};
+use crate::integration::steam;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// One `elm doctor` check
+#[derive(Debug, Serialize)]
+pub struct DoctorCheck {
+    pub name: String,
+    /// "system" or "library"
+    pub category: &'static str,
+    pub ok: bool,
+    /// Failed required checks count as issues; optional ones are informational
+    pub required: bool,
+    pub detail: String,
+    /// How to fix a failed check
+    pub fix: Option<String>,
+}
+
+impl DoctorCheck {
+    fn new(name: &str, category: &'static str, ok: bool, required: bool, detail: String) -> Self {
+        DoctorCheck { name: name.to_string(), category, ok, required, detail, fix: None }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        if !self.ok {
+            self.fix = Some(fix.into());
+        }
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+    pub gpus: Vec<Gpu>,
+    /// Parsed `vulkaninfo --summary`, if it ran
+    pub vulkan: Option<VulkanInfo>,
+    /// Free space on the filesystem holding the ELM data dir, e.g. `120.5 GB`
+    pub disk_available: Option<String>,
+    pub disk_device: Option<String>,
+    /// Number of failed required checks
+    pub issues: usize,
+    pub ready: bool,
+}
+
+fn command_succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn command_stdout(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .unwrap_or_default()
+}
+
+fn driver_version() -> Option<String> {
+    let nvidia = Path::new("/proc/driver/nvidia/version");
+    if nvidia.exists() {
+        let ver = std::fs::read_to_string(nvidia).unwrap_or_default();
+        let first = ver.lines().next().unwrap_or("");
+        return Some(format!("NVIDIA {}", first.split_whitespace().nth(7).unwrap_or("")));
+    }
+    let glx = command_stdout("sh", &["-c", "glxinfo 2>/dev/null | grep 'OpenGL version' | head -1"]);
+    let glx = glx.trim();
+    (!glx.is_empty()).then(|| glx.chars().take(50).collect())
+}
+
+/// Open-file limit esync needs; Proton's own recommendation
+pub const ESYNC_NOFILE: u64 = 524_288;
+
+/// esync/fsync are on unless the launch env disables both
+fn sync_enabled(launch_env: &HashMap<String, String>) -> bool {
+    let disabled = |key: &str| launch_env.get(key).is_some_and(|v| v != "0" && !v.is_empty());
+    !(disabled("PROTON_NO_ESYNC") && disabled("PROTON_NO_FSYNC"))
+}
+
+fn nofile_check(launch_env: &HashMap<String, String>) -> DoctorCheck {
+    let Ok((soft, hard)) = rlimit::getrlimit(rlimit::Resource::NOFILE) else {
+        return DoctorCheck::new("Files", "system", false, false, "could not read RLIMIT_NOFILE".into());
+    };
+    let sync = sync_enabled(launch_env);
+    let ok = soft >= ESYNC_NOFILE || !sync;
+    let detail = if soft >= ESYNC_NOFILE {
+        format!("ulimit -n {soft} (hard {hard})")
+    } else if !sync {
+        format!("ulimit -n {soft} (fine: esync/fsync disabled in config)")
+    } else {
+        format!("ulimit -n {soft} (hard {hard}) is below {ESYNC_NOFILE}; esync/fsync may stall")
+    };
+    DoctorCheck::new("Files", "system", ok, sync, detail).with_fix(
+        "Add DefaultLimitNOFILE=1048576 to /etc/systemd/system.conf and /etc/systemd/user.conf\n\
+         or '* hard nofile 1048576' and '* soft nofile 1048576' to /etc/security/limits.conf,\n\
+         then log out and back in. Or set PROTON_NO_ESYNC=1 and PROTON_NO_FSYNC=1 (elm config edit)",
+    )
+}
+
+fn governor_check() -> DoctorCheck {
+    let path = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor";
+    let Ok(governor) = std::fs::read_to_string(path) else {
+        return DoctorCheck::new("CPU", "system", false, false, "scaling governor unavailable".into());
+    };
+    let governor = governor.trim();
+    let ok = governor != "powersave";
+    let detail = if ok {
+        format!("{governor} governor")
+    } else {
+        format!("{governor} governor (clocks stay low; use performance while playing)")
+    };
+    DoctorCheck::new("CPU", "system", ok, true, detail).with_fix(
+        "sudo cpupower frequency-set -g performance\n\
+         or: echo performance | sudo tee /sys/devices/system/cpu/cpu*/cpufreq/scaling_governor",
+    )
+}
+
+/// `None` on kernels without split-lock detection
+fn split_lock_check() -> Option<DoctorCheck> {
+    let value = std::fs::read_to_string("/proc/sys/kernel/split_lock_mitigate").ok()?;
+    let ok = value.trim() != "1";
+    let detail = if ok {
+        "split-lock mitigation off".to_string()
+    } else {
+        "split-lock mitigation on (can tank EVE's frame times)".to_string()
+    };
+    Some(DoctorCheck::new("Kernel", "system", ok, true, detail).with_fix(
+        "sudo sysctl kernel.split_lock_mitigate=0\n\
+         persist: echo 'kernel.split_lock_mitigate=0' | sudo tee /etc/sysctl.d/99-split-lock.conf",
+    ))
+}
+
+fn vulkan_check(info: Option<&VulkanInfo>, min_vulkan: Option<&str>) -> DoctorCheck {
+    let Some(info) = info else {
+        return DoctorCheck::new("Vulkan", "system", false, true, "not found (install vulkan-tools)".into());
+    };
+    let Some(device) = info.primary_device() else {
+        return DoctorCheck::new("Vulkan", "system", false, true, "no Vulkan devices reported".into());
+    };
+
+    let api = device.api_version.as_deref().or(info.instance_version.as_deref());
+    let driver = [device.driver_name.as_deref(), device.driver_version.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut detail = format!("{} on {}", api.unwrap_or("unknown version"), device.name);
+    if !driver.is_empty() {
+        detail.push_str(&format!(" ({driver})"));
+    }
+
+    let ok = match (api, min_vulkan) {
+        (Some(api), Some(min)) => vulkan::version_at_least(api, min),
+        _ => true,
+    };
+    if let (false, Some(min)) = (ok, min_vulkan) {
+        detail.push_str(&format!(", below required {min}"));
+    }
+    DoctorCheck::new("Vulkan", "system", ok, true, detail)
+        .with_fix("Update your GPU driver (NVIDIA) or Mesa (AMD/Intel) to a release with newer Vulkan support")
+}
+
+/// Run every compatibility check. `data_dir` is used for the disk space probe,
+/// `launch_env` (the active manifest's environment) decides which checks apply,
+/// and `min_vulkan` comes from the channel's constraints.
+pub fn run_checks(
+    data_dir: &Path,
+    launch_env: &HashMap<String, String>,
+    min_vulkan: Option<&str>,
+) -> DoctorReport {
+    let mut checks = Vec::new();
+
+    let vulkan = vulkan::probe();
+    checks.push(vulkan_check(vulkan.as_ref(), min_vulkan));
+
+    let gpus = gpu::enumerate();
+    let gpu_detail = match gpus.as_slice() {
+        [] => "unknown".to_string(),
+        [gpu] => gpu.device.clone(),
+        _ => format!("{} found (select with elm run --gpu <index|vendor>)", gpus.len()),
+    };
+    checks.push(DoctorCheck::new("GPU", "system", !gpus.is_empty(), false, gpu_detail));
+
+    let driver = driver_version();
+    let driver_ok = driver.is_some();
+    checks.push(DoctorCheck::new("Driver", "system", driver_ok, false, driver.unwrap_or_else(|| "unknown".into())));
+
+    let steam = steam::steam_root();
+    checks.push(DoctorCheck::new(
+        "Steam",
+        "system",
+        steam.is_ok(),
+        true,
+        match steam {
+            Ok(path) => path.display().to_string(),
+            Err(e) => e.to_string(),
+        },
+    ));
+
+    let python_ver = command_stdout("python3", &["--version"]);
+    let python_ok = !python_ver.is_empty();
+    checks.push(DoctorCheck::new(
+        "Python",
+        "system",
+        python_ok,
+        true,
+        if python_ok { python_ver.trim().to_string() } else { "python3 not found".into() },
+    ));
+
+    let mangohud_ok = command_succeeds("mangohud", &["--version"]);
+    checks.push(DoctorCheck::new(
+        "MangoHud",
+        "system",
+        mangohud_ok,
+        false,
+        if mangohud_ok {
+            "available (use --hud to enable)".into()
+        } else {
+            "not installed (optional, for FPS overlay)".into()
+        },
+    ));
+
+    checks.push(nofile_check(launch_env));
+    checks.push(governor_check());
+    checks.extend(split_lock_check());
+
+    let ldconfig = command_stdout("ldconfig", &["-p"]);
+    for (lib, pkg) in [("libvulkan", "vulkan-icd-loader"), ("libGL", "mesa"), ("libX11", "libx11")] {
+        let found = ldconfig.contains(lib);
+        let detail = if found { String::new() } else { format!("install {pkg}") };
+        checks.push(DoctorCheck::new(lib, "library", found, true, detail));
+    }
+
+    let disk_available = disk::available(data_dir).ok().map(disk::format_bytes);
+    let disk_device = filesystem::mount_source(data_dir);
+
+    let issues = checks.iter().filter(|c| c.required && !c.ok).count();
+    DoctorReport { checks, gpus, vulkan, disk_available, disk_device, issues, ready: issues == 0 }
+}
@@ -0,0 +1,139 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Filesystems where `cp --reflink` shares extents instead of copying data
+const REFLINK_FILESYSTEMS: &[&str] = &["btrfs", "xfs", "bcachefs"];
+
+/// How [`copy_dir`] copied a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMethod {
+    /// Copy-on-write clone: near instant, no extra space until files change
+    Reflink,
+    Full,
+}
+
+/// Decode the octal escapes (`\040` for a space) used in /proc/self/mountinfo
+fn unescape_mount_path(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        let code = rest.get(i + 1..i + 4).and_then(|o| u8::from_str_radix(o, 8).ok());
+        match code {
+            Some(c) => {
+                out.push(c as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The /proc/self/mountinfo entry for the mount containing `path`:
+/// (mount point, filesystem type, source device)
+fn mount_entry(path: &Path) -> Option<(PathBuf, String, String)> {
+    let path = fs::canonicalize(path).ok()?;
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+
+    // The deepest mount point containing the path wins
+    let mut best: Option<(PathBuf, String, String)> = None;
+    for line in mountinfo.lines() {
+        let fields: Vec<&str> = line.split(' ').collect();
+        let Some(sep) = fields.iter().position(|f| *f == "-") else { continue };
+        let (Some(mount_point), Some(fs_type)) = (fields.get(4), fields.get(sep + 1)) else { continue };
+        let source = fields.get(sep + 2).map(|s| unescape_mount_path(s)).unwrap_or_default();
+        let mount_point = PathBuf::from(unescape_mount_path(mount_point));
+        if path.starts_with(&mount_point)
+            && best.as_ref().is_none_or(|(b, _, _)| mount_point.as_os_str().len() >= b.as_os_str().len())
+        {
+            best = Some((mount_point, fs_type.to_string(), source));
+        }
+    }
+    best
+}
+
+/// Filesystem type (`btrfs`, `ext4`, ...) of the mount containing `path`,
+/// from /proc/self/mountinfo. `None` if it can't be determined.
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    mount_entry(path).map(|(_, fs_type, _)| fs_type)
+}
+
+/// Device (or other source, e.g. `tmpfs`) mounted at the mount containing `path`
+pub fn mount_source(path: &Path) -> Option<String> {
+    mount_entry(path).map(|(_, _, source)| source).filter(|s| !s.is_empty())
+}
+
+/// Whether copies within the filesystem holding `path` can be reflinked
+pub fn supports_reflink(path: &Path) -> bool {
+    filesystem_type(path).is_some_and(|t| REFLINK_FILESYSTEMS.contains(&t.as_str()))
+}
+
+/// How often [`copy_dir`] measures the destination to report progress
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Apparent size of everything under `path` (symlinks not followed)
+fn tree_size(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else { return 0 };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| tree_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Run `cp` with `args`, calling `progress` with the bytes under `dst` every
+/// [`PROGRESS_INTERVAL`] until it exits. Returns whether it succeeded.
+fn run_cp(args: &[&std::ffi::OsStr], dst: &Path, progress: &mut impl FnMut(u64)) -> Result<bool> {
+    let mut child = Command::new("cp").args(args).spawn().context("running cp")?;
+    let mut last_report = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status.success());
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            progress(tree_size(dst));
+            last_report = Instant::now();
+        }
+    }
+}
+
+/// Copy `src` to `dst` (which must not exist) preserving symlinks and attributes.
+/// Uses a reflink clone when both sides are on a CoW filesystem, otherwise a full copy.
+/// `progress` is called periodically with the bytes copied so far.
+pub fn copy_dir(src: &Path, dst: &Path, mut progress: impl FnMut(u64)) -> Result<CopyMethod> {
+    if dst.exists() {
+        return Err(anyhow!("{} already exists", dst.display()));
+    }
+    let dst_parent = dst.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dst_parent).with_context(|| format!("create {}", dst_parent.display()))?;
+
+    let same_fs = fs::metadata(src).ok().zip(fs::metadata(dst_parent).ok()).is_some_and(|(a, b)| {
+        use std::os::unix::fs::MetadataExt;
+        a.dev() == b.dev()
+    });
+    if same_fs && supports_reflink(src) {
+        let args = ["-a".as_ref(), "--reflink=always".as_ref(), src.as_os_str(), dst.as_os_str()];
+        if run_cp(&args, dst, &mut progress).unwrap_or(false) {
+            return Ok(CopyMethod::Reflink);
+        }
+        // e.g. XFS formatted without reflink support: start over with a full copy
+        if dst.exists() {
+            fs::remove_dir_all(dst).with_context(|| format!("remove partial copy {}", dst.display()))?;
+        }
+    }
+
+    if !run_cp(&["-a".as_ref(), src.as_os_str(), dst.as_os_str()], dst, &mut progress)? {
+        return Err(anyhow!("cp -a {} {} failed", src.display(), dst.display()));
+    }
+    Ok(CopyMethod::Full)
+}
@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+/// Directories searched for Vulkan ICD manifests
+const ICD_DIRS: &[&str] = &["/usr/share/vulkan/icd.d", "/etc/vulkan/icd.d"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Other,
+}
+
+impl GpuVendor {
+    fn from_description(desc: &str) -> Self {
+        let d = desc.to_lowercase();
+        if d.contains("nvidia") {
+            GpuVendor::Nvidia
+        } else if d.contains("advanced micro devices") || d.contains("amd") || d.contains("ati ") {
+            GpuVendor::Amd
+        } else if d.contains("intel") {
+            GpuVendor::Intel
+        } else {
+            GpuVendor::Other
+        }
+    }
+
+    /// Filename prefix of this vendor's Vulkan ICD manifest
+    fn icd_prefix(self) -> Option<&'static str> {
+        match self {
+            GpuVendor::Nvidia => Some("nvidia"),
+            GpuVendor::Amd => Some("radeon"),
+            GpuVendor::Intel => Some("intel"),
+            GpuVendor::Other => None,
+        }
+    }
+}
+
+impl fmt::Display for GpuVendor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GpuVendor::Nvidia => "NVIDIA",
+            GpuVendor::Amd => "AMD",
+            GpuVendor::Intel => "Intel",
+            GpuVendor::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Gpu {
+    pub index: usize,
+    pub vendor: GpuVendor,
+    pub device: String,
+    /// PCI address with domain, e.g. `0000:01:00.0`
+    pub pci_slot: String,
+}
+
+/// List display controllers via `lspci`, in bus order. Empty if lspci is unavailable.
+pub fn enumerate() -> Vec<Gpu> {
+    let output = Command::new("lspci")
+        .arg("-D")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .unwrap_or_default();
+    parse_lspci(&output)
+}
+
+fn parse_lspci(output: &str) -> Vec<Gpu> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (slot, rest) = line.split_once(' ')?;
+            let (class, device) = rest.split_once(": ")?;
+            let class = class.to_lowercase();
+            if !(class.contains("vga") || class.contains("3d controller") || class.contains("display controller")) {
+                return None;
+            }
+            Some((slot.to_string(), device.trim().to_string()))
+        })
+        .enumerate()
+        .map(|(index, (pci_slot, device))| Gpu {
+            index,
+            vendor: GpuVendor::from_description(&device),
+            device,
+            pci_slot,
+        })
+        .collect()
+}
+
+/// Pick a GPU by index (`1`) or vendor name (`nvidia`, `amd`, `intel`)
+pub fn select<'a>(gpus: &'a [Gpu], selector: &str) -> Result<&'a Gpu> {
+    if gpus.is_empty() {
+        return Err(anyhow!("no GPUs detected (is lspci installed?)"));
+    }
+    if let Ok(index) = selector.parse::<usize>() {
+        return gpus.get(index).ok_or_else(|| {
+            anyhow!("GPU index {} out of range (found {})", index, gpus.len())
+        });
+    }
+    let vendor = match selector.to_lowercase().as_str() {
+        "nvidia" => GpuVendor::Nvidia,
+        "amd" | "radeon" => GpuVendor::Amd,
+        "intel" => GpuVendor::Intel,
+        other => return Err(anyhow!("unknown GPU selector '{}' (use an index, nvidia, amd or intel)", other)),
+    };
+    gpus.iter()
+        .find(|g| g.vendor == vendor)
+        .ok_or_else(|| anyhow!("no {} GPU detected", vendor))
+}
+
+/// Environment that steers OpenGL and Vulkan onto `gpu` (PRIME render offload)
+pub fn offload_env(gpu: &Gpu, gpus: &[Gpu]) -> HashMap<String, String> {
+    let mut env = HashMap::new();
+    match gpu.vendor {
+        GpuVendor::Nvidia => {
+            env.insert("__NV_PRIME_RENDER_OFFLOAD".to_string(), "1".to_string());
+            env.insert("__GLX_VENDOR_LIBRARY_NAME".to_string(), "nvidia".to_string());
+            env.insert("__VK_LAYER_NV_optimus".to_string(), "NVIDIA_only".to_string());
+        }
+        _ => {
+            // Mesa accepts a PCI tag, which also works when both GPUs share a driver
+            let tag = format!("pci-{}", gpu.pci_slot.replace([':', '.'], "_"));
+            env.insert("DRI_PRIME".to_string(), tag);
+        }
+    }
+
+    // Restricting the ICD list only helps when the vendor is unique on this machine
+    let same_vendor = gpus.iter().filter(|g| g.vendor == gpu.vendor).count();
+    if same_vendor == 1 {
+        if let Some(icds) = gpu.vendor.icd_prefix().and_then(find_icds) {
+            env.insert("VK_ICD_FILENAMES".to_string(), icds);
+        }
+    }
+    env
+}
+
+fn find_icds(prefix: &str) -> Option<String> {
+    let mut icds: Vec<String> = ICD_DIRS
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(Path::new(dir)).ok())
+        .flatten()
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with(prefix) && name.ends_with(".json")
+        })
+        .map(|e| e.path().to_string_lossy().to_string())
+        .collect();
+    icds.sort();
+    if icds.is_empty() {
+        None
+    } else {
+        Some(icds.join(":"))
+    }
+}
@@ -0,0 +1,6 @@
+pub mod disk;
+pub mod doctor;
+pub mod filesystem;
+pub mod gpu;
+pub mod status;
+pub mod vulkan;
@@ -0,0 +1,95 @@
+use crate::rollback::snapshot::list_snapshots;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct EngineStatus {
+    pub id: String,
+    /// `installed.json` marker present
+    pub installed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrefixStatus {
+    pub name: String,
+    /// `pfx/drive_c` exists
+    pub initialized: bool,
+    /// Bytes allocated on disk (see [`crate::system::disk::usage`])
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotStatus {
+    pub name: String,
+    pub size: u64,
+    /// Unix seconds
+    pub created: u64,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    pub engines: Vec<EngineStatus>,
+    pub prefixes: Vec<PrefixStatus>,
+    pub snapshots: Vec<SnapshotStatus>,
+    /// User manifest, if one exists
+    pub manifest: Option<PathBuf>,
+    pub data_dir: PathBuf,
+    pub config_dir: PathBuf,
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Gather installed engines, prefixes and snapshots under `data_dir`
+pub fn gather(data_dir: &Path, config_dir: &Path) -> StatusReport {
+    let engines = subdirs(&data_dir.join("engines"))
+        .into_iter()
+        // Skip staging dirs of in-progress installs
+        .filter(|p| !file_name(p).starts_with('.'))
+        .map(|p| EngineStatus { id: file_name(&p), installed: p.join("installed.json").exists() })
+        .collect();
+
+    let prefixes = subdirs(&data_dir.join("prefixes"))
+        .into_iter()
+        .map(|p| PrefixStatus {
+            name: file_name(&p),
+            initialized: p.join("pfx/drive_c").exists(),
+            size: crate::system::disk::usage(&p),
+        })
+        .collect();
+
+    let snapshots = list_snapshots(&data_dir.join("snapshots"))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| SnapshotStatus {
+            created: s
+                .created
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            name: s.name,
+            size: s.size,
+            profile: s.profile,
+        })
+        .collect();
+
+    StatusReport {
+        engines,
+        prefixes,
+        snapshots,
+        manifest: crate::config::load::find_config(&config_dir.join("manifests"), "eve-online"),
+        data_dir: data_dir.to_path_buf(),
+        config_dir: config_dir.to_path_buf(),
+    }
+}
@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::process::Command;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VulkanDevice {
+    pub name: String,
+    /// Vulkan API version the driver exposes, e.g. `1.3.255`
+    pub api_version: Option<String>,
+    pub driver_name: Option<String>,
+    pub driver_version: Option<String>,
+    /// e.g. `PHYSICAL_DEVICE_TYPE_DISCRETE_GPU`
+    pub device_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VulkanInfo {
+    pub instance_version: Option<String>,
+    pub devices: Vec<VulkanDevice>,
+}
+
+impl VulkanInfo {
+    /// The device EVE will most likely run on: a discrete GPU, else any real GPU,
+    /// else whatever is listed first (e.g. llvmpipe)
+    pub fn primary_device(&self) -> Option<&VulkanDevice> {
+        let is_type = |d: &VulkanDevice, t: &str| d.device_type.as_deref().is_some_and(|dt| dt.contains(t));
+        self.devices
+            .iter()
+            .find(|d| is_type(d, "DISCRETE_GPU"))
+            .or_else(|| self.devices.iter().find(|d| !is_type(d, "CPU")))
+            .or_else(|| self.devices.first())
+    }
+}
+
+/// Run `vulkaninfo --summary`; `None` if it is missing or fails
+pub fn probe() -> Option<VulkanInfo> {
+    let output = Command::new("vulkaninfo").arg("--summary").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_summary(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// First dotted version number in `s` (handles both `1.3.255` and `4206847 (1.3.255)`)
+fn extract_version(s: &str) -> Option<String> {
+    s.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .find(|part| part.contains('.') && part.split('.').all(|n| !n.is_empty()))
+        .map(str::to_string)
+}
+
+fn parse_summary(text: &str) -> VulkanInfo {
+    let mut info = VulkanInfo { instance_version: None, devices: Vec::new() };
+    let mut current: Option<VulkanDevice> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(v) = trimmed.strip_prefix("Vulkan Instance Version:") {
+            info.instance_version = extract_version(v);
+            continue;
+        }
+        // Device sections start with "GPU0:", "GPU1:", ...
+        if trimmed.starts_with("GPU") && trimmed.ends_with(':') && !trimmed.contains('=') {
+            info.devices.extend(current.take());
+            current = Some(VulkanDevice {
+                name: String::new(),
+                api_version: None,
+                driver_name: None,
+                driver_version: None,
+                device_type: None,
+            });
+            continue;
+        }
+        let (Some(device), Some((key, value))) = (current.as_mut(), trimmed.split_once('=')) else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "deviceName" => device.name = value,
+            "apiVersion" => device.api_version = extract_version(&value),
+            "driverName" => device.driver_name = Some(value),
+            // driverInfo is the human-readable version; driverVersion is vendor-encoded
+            "driverInfo" => device.driver_version = Some(value),
+            "driverVersion" if device.driver_version.is_none() => device.driver_version = Some(value),
+            "deviceType" => device.device_type = Some(value),
+            _ => {}
+        }
+    }
+    info.devices.extend(current);
+    info
+}
+
+/// Compare dotted versions numerically; `1.3.255 >= 1.3` is true
+pub fn version_at_least(have: &str, min: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> { v.split('.').map(|n| n.parse().unwrap_or(0)).collect() };
+    let (have, min) = (parse(have), parse(min));
+    for i in 0..have.len().max(min.len()) {
+        let (h, m) = (have.get(i).copied().unwrap_or(0), min.get(i).copied().unwrap_or(0));
+        if h != m {
+            return h > m;
+        }
+    }
+    true
+}